@@ -351,3 +351,17 @@ def test_lzma(benchmark, file, use_cramjam: bool):
             decompress=lzma.decompress,
             data=data,
         )
+
+
+@pytest.mark.parametrize("batch", (True, False), ids=lambda val: "compress_batch" if val else "compress")
+def test_zstd_dictionary_batch(benchmark, batch: bool):
+    """
+    Many small similar documents with a shared dictionary; the batch digests the dictionary once
+    and compresses in parallel, compared to digesting it per call.
+    """
+    dictionary = FILES[0].read_bytes()[:1 << 16]
+    inputs = [dictionary[i : i + 4096] for i in range(0, len(dictionary) - 4096, 512)]
+    if batch:
+        benchmark(cramjam.zstd.compress_batch, inputs, level=3, dictionary=dictionary)
+    else:
+        benchmark(lambda: [cramjam.zstd.compress(data, level=3, dictionary=dictionary) for data in inputs])
@@ -0,0 +1,105 @@
+//! ASCII armor, a base64 text form of binary data for pasting where only text survives.
+use crate::io::RustyBuffer;
+use crate::BytesType;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+const HEADER: &str = "-----BEGIN CRAMJAM DATA-----";
+const FOOTER: &str = "-----END CRAMJAM DATA-----";
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Armor `data` as text: standard base64 wrapped to `line_width` characters per line, between
+/// a `-----BEGIN CRAMJAM DATA-----` header and `-----END CRAMJAM DATA-----` footer line.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> text = cramjam.armor(cramjam.gzip.compress(b'some bytes here'), line_width=76)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, line_width=76))]
+pub fn armor(data: BytesType, line_width: usize) -> PyResult<String> {
+    if line_width == 0 {
+        return Err(PyValueError::new_err("line_width must be greater than 0"));
+    }
+    let encoded = encode(&data.read_bytes()?);
+    let mut text = String::with_capacity(encoded.len() + encoded.len() / line_width + HEADER.len() + FOOTER.len() + 3);
+    text.push_str(HEADER);
+    text.push('\n');
+    for line in encoded.as_bytes().chunks(line_width) {
+        // base64 is ascii, so any split is on a char boundary
+        text.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+        text.push('\n');
+    }
+    text.push_str(FOOTER);
+    text.push('\n');
+    Ok(text)
+}
+
+/// Parse text from `armor` back to the original bytes.
+///
+/// Whitespace anywhere, ie. re-wrapped lines, indentation or `\r\n` line endings picked up on
+/// the way through a ticket, is ignored, as is any text before the header or after the footer.
+/// Raises `ValueError` if the header or footer is missing or the base64 between them is invalid.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.gzip.decompress(cramjam.dearmor(text))
+/// ```
+#[pyfunction]
+pub fn dearmor(text: &str) -> PyResult<RustyBuffer> {
+    let start = text
+        .find(HEADER)
+        .map(|i| i + HEADER.len())
+        .ok_or_else(|| PyValueError::new_err("armor header not found"))?;
+    let len = text[start..]
+        .find(FOOTER)
+        .ok_or_else(|| PyValueError::new_err("armor footer not found"))?;
+    let body = text[start..start + len]
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    decode(&body).map(RustyBuffer::from).map_err(PyValueError::new_err)
+}
+
+fn encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+fn decode(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if !input.len().is_multiple_of(4) {
+        return Err("armor base64 length is not a multiple of 4");
+    }
+    let mut out = Vec::with_capacity(input.len() / 4 * 3);
+    let n_groups = input.len() / 4;
+    for (n, chunk) in input.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|b| **b == b'=').count();
+        if padding > 2 || (padding > 0 && n + 1 != n_groups) {
+            return Err("armor base64 has misplaced padding");
+        }
+        let mut group = 0u32;
+        for (i, b) in chunk[..4 - padding].iter().enumerate() {
+            let value = ALPHABET
+                .iter()
+                .position(|a| a == b)
+                .ok_or("armor contains a character that isn't base64")?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        out.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(out)
+}
@@ -271,6 +271,12 @@ pub mod blosc2 {
             }
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
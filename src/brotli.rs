@@ -6,8 +6,9 @@ use pyo3::prelude::*;
 pub mod brotli {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::io::{IncrementalDecoder, LimitedWriter, RustyBuffer};
     use crate::{AsBytes, BytesType};
+    use libcramjam::brotli::brotli::DecompressorWriter;
     use pyo3::prelude::*;
     use pyo3::PyResult;
     use std::io::{Cursor, Write};
@@ -18,41 +19,63 @@ pub mod brotli {
 
     /// Brotli decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.brotli.decompress(compressed_bytes, output_len=Optional[int])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::brotli::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = crate::generic!(
+            py,
+            libcramjam::brotli::decompress[data],
+            output_len = output_len,
+            max_output_len = max_output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// Brotli compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.brotli.compress(b'some bytes here', level=9, output_len=Option[int])  # level defaults to 11
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, return_len=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
-    ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::brotli::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        return_len: bool,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = crate::generic!(py, libcramjam::brotli::compress[data], output_len = output_len, level)
+            .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::brotli::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
@@ -89,6 +112,12 @@ pub mod brotli {
             crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -97,10 +126,88 @@ pub mod brotli {
             })
         }
     }
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(brotli, u32, 0, 11);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            brotli, level: u32;
+            checked, reproducible, bench, prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records,
+    };
+
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(brotli);
+        crate::make_decompressor!(brotli, decoder = StreamDecoder::default());
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    /// Incremental decoder of concatenated brotli streams
+    struct StreamDecoder {
+        writer: DecompressorWriter<LimitedWriter<Vec<u8>>>,
+        /// Whether the current stream has been fed any input
+        fed: bool,
+    }
+
+    impl Default for StreamDecoder {
+        fn default() -> Self {
+            Self {
+                writer: DecompressorWriter::new(LimitedWriter::new(vec![], None), BUF_SIZE),
+                fed: false,
+            }
+        }
+    }
+
+    impl IncrementalDecoder for StreamDecoder {
+        fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            let start = output.len();
+            let remaining = |output: &Vec<u8>| max_len.map(|max_len| max_len.saturating_sub(output.len() - start));
+            // the writer fails as soon as its output goes over the budget, part way through a write
+            self.writer.get_mut().reset(max_len);
+            let mut pos = 0;
+            while pos < input.len() {
+                match self.writer.write(&input[pos..])? {
+                    // the stream ended, the rest of the input starts the next one
+                    0 if self.fed => {
+                        output.append(self.writer.get_mut().get_mut());
+                        self.finish(output, remaining(output))?;
+                        self.writer.get_mut().reset(remaining(output));
+                    }
+                    0 => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "Invalid brotli stream",
+                        ))
+                    }
+                    n_bytes => {
+                        pos += n_bytes;
+                        self.fed = true;
+                    }
+                }
+            }
+            output.append(self.writer.get_mut().get_mut());
+            Ok(())
+        }
+
+        fn finish(&mut self, output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            if self.fed {
+                self.writer.get_mut().reset(max_len);
+                self.writer.close()?;
+                output.append(self.writer.get_mut().get_mut());
+                *self = Self::default();
+            }
+            Ok(())
+        }
+    }
 }
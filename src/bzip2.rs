@@ -6,51 +6,75 @@ use pyo3::prelude::*;
 pub mod bzip2 {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::io::{IncrementalDecoder, RustyBuffer};
     use crate::{AsBytes, BytesType};
+    use libcramjam::bzip2::bzip2::{Decompress, Status};
     use pyo3::prelude::*;
     use pyo3::PyResult;
     use std::io::Cursor;
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+    const DECOMPRESS_CHUNK_LEN: usize = 32 * 1024;
 
     /// bzip2 decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.bzip2.decompress(compressed_bytes, output_len=Optional[int])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::bzip2::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = crate::generic!(
+            py,
+            libcramjam::bzip2::decompress[data],
+            output_len = output_len,
+            max_output_len = max_output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// bzip2 compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.bzip2.compress(b'some bytes here', level=6, output_len=Option[int])  # level defaults to 6
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, return_len=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
-    ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::bzip2::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        return_len: bool,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = crate::generic!(py, libcramjam::bzip2::compress[data], output_len = output_len, level)
+            .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::bzip2::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
@@ -88,6 +112,12 @@ pub mod bzip2 {
             crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -95,10 +125,80 @@ pub mod bzip2 {
         }
     }
 
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(bzip2, u32, 1, 9);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            bzip2, level: u32;
+            checked, reproducible, bench, strict(b"BZh"), prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records, decompress_strict,
+    };
+
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(bzip2);
+        crate::make_decompressor!(bzip2, decoder = StreamDecoder::default());
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    /// Incremental decoder of concatenated bzip2 streams
+    struct StreamDecoder {
+        bz: Decompress,
+        /// Whether the input so far ends part way through a stream
+        in_stream: bool,
+    }
+
+    impl Default for StreamDecoder {
+        fn default() -> Self {
+            Self {
+                bz: Decompress::new(false),
+                in_stream: false,
+            }
+        }
+    }
+
+    impl IncrementalDecoder for StreamDecoder {
+        fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            let (start, mut pos) = (output.len(), 0);
+            while pos < input.len() {
+                let (total_in, produced) = (self.bz.total_in(), output.len());
+                output.reserve(DECOMPRESS_CHUNK_LEN);
+                let status = self
+                    .bz
+                    .decompress_vec(&input[pos..], output)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                pos += (self.bz.total_in() - total_in) as usize;
+                crate::io::check_output_len(output.len() - start, max_len)?;
+                self.in_stream = true;
+                if matches!(status, Status::StreamEnd) {
+                    self.bz = Decompress::new(false);
+                    self.in_stream = false;
+                } else if self.bz.total_in() == total_in && output.len() == produced {
+                    break;
+                }
+            }
+            Ok(())
+        }
+
+        fn finish(&mut self, _output: &mut Vec<u8>, _max_len: Option<usize>) -> std::io::Result<()> {
+            if self.in_stream {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Input ends part way through a bzip2 stream",
+                ));
+            }
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,45 @@
+//! CRC-32 and Adler-32 checksums, as used by the gzip and zlib formats, for validating their payloads.
+use crate::BytesType;
+use pyo3::prelude::*;
+
+/// CRC-32 checksum of `data`, the same as Python's `zlib.crc32`. `initial` is the checksum of the
+/// data preceding it, so a stream can be checksummed piecewise by passing each result to the next call.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> crc = cramjam.crc32(b'some bytes ')
+/// >>> cramjam.crc32(b'here', crc) == cramjam.crc32(b'some bytes here')
+/// True
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, initial=0))]
+pub fn crc32(py: Python, data: BytesType, initial: u32) -> PyResult<u32> {
+    let bytes = &*data.read_bytes()?;
+    Ok(py.allow_threads(|| {
+        let mut hasher = crc32fast::Hasher::new_with_initial(initial);
+        hasher.update(bytes);
+        hasher.finalize()
+    }))
+}
+
+/// Adler-32 checksum of `data`, the same as Python's `zlib.adler32`. `initial` is the checksum of the
+/// data preceding it, so a stream can be checksummed piecewise by passing each result to the next call.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> checksum = cramjam.adler32(b'some bytes ')
+/// >>> cramjam.adler32(b'here', checksum) == cramjam.adler32(b'some bytes here')
+/// True
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, initial=1))]
+pub fn adler32(py: Python, data: BytesType, initial: u32) -> PyResult<u32> {
+    let bytes = &*data.read_bytes()?;
+    Ok(py.allow_threads(|| {
+        let mut hasher = adler2::Adler32::from_checksum(initial);
+        hasher.write_slice(bytes);
+        hasher.checksum()
+    }))
+}
@@ -0,0 +1,98 @@
+//! Dispatch to the codecs by name, for APIs taking the codec as an argument, ie. `cramjam.text`.
+use crate::exceptions::CompressionError;
+use pyo3::exceptions::PyValueError;
+use pyo3::PyResult;
+use std::io::{Error, ErrorKind};
+
+/// Codec names accepted, the same as their module names
+pub(crate) const CODECS: [&str; 9] = [
+    "snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib",
+];
+
+/// Raise `ValueError` if `codec` isn't one of `CODECS`
+pub(crate) fn check_codec(codec: &str) -> PyResult<()> {
+    if CODECS.contains(&codec) {
+        return Ok(());
+    }
+    Err(PyValueError::new_err(format!(
+        "Unknown codec '{}', expected one of {:?}",
+        codec, CODECS
+    )))
+}
+
+/// Raise a `CompressionError` if `level` is outside the range `codec`'s module accepts, as its
+/// `compress` does; snappy has no levels and accepts any.
+pub(crate) fn check_level(codec: &str, level: Option<i32>) -> PyResult<()> {
+    let unsigned = || {
+        level
+            .map(|level| {
+                u32::try_from(level)
+                    .map_err(|_| CompressionError::new_err(format!("level {} out of range for {}", level, codec)))
+            })
+            .transpose()
+    };
+    match codec {
+        "snappy" => Ok(()),
+        "brotli" => crate::brotli::brotli::_level::check_level(unsigned()?),
+        "bzip2" => crate::bzip2::bzip2::_level::check_level(unsigned()?),
+        "lz4" => crate::lz4::lz4::_level::check_level(unsigned()?),
+        "gzip" => crate::gzip::gzip::_level::check_level(unsigned()?),
+        "deflate" => crate::deflate::deflate::_level::check_level(unsigned()?),
+        "zstd" => crate::zstd::zstd::_level::check_level(level),
+        "xz" => crate::xz::xz::_level::check_level(unsigned()?),
+        "zlib" => crate::zlib::zlib::_level::check_level(unsigned()?),
+        _ => unreachable!("codec checked by `check_codec`"),
+    }
+}
+
+/// Compress `input` with `codec` at `level`, or the codec's default; snappy has no levels
+/// and ignores it.
+pub(crate) fn compress_with(
+    codec: &str,
+    input: &[u8],
+    output: &mut Vec<u8>,
+    level: Option<i32>,
+) -> std::io::Result<usize> {
+    let unsigned = || {
+        level
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("Invalid {} level {:?}", codec, level)))
+    };
+    match codec {
+        "snappy" => libcramjam::snappy::compress(input, output),
+        "brotli" => libcramjam::brotli::compress(input, output, unsigned()?),
+        "bzip2" => libcramjam::bzip2::compress(input, output, unsigned()?),
+        "lz4" => libcramjam::lz4::compress(input, output, unsigned()?),
+        "gzip" => libcramjam::gzip::compress(input, output, unsigned()?),
+        "deflate" => libcramjam::deflate::compress(input, output, unsigned()?),
+        "zstd" => libcramjam::zstd::compress(input, output, level),
+        "xz" => libcramjam::xz::compress(
+            input,
+            output,
+            unsigned()?,
+            None::<libcramjam::xz::Format>,
+            None::<libcramjam::xz::Check>,
+            None::<libcramjam::xz::Filters>,
+            None::<libcramjam::xz::LzmaOptions>,
+        ),
+        "zlib" => crate::zlib::zlib::compress_with(input, output, unsigned()?),
+        _ => unreachable!("codec checked by `check_codec`"),
+    }
+}
+
+/// Decompress `input` with `codec`
+pub(crate) fn decompress_with(codec: &str, input: &[u8], output: &mut Vec<u8>) -> std::io::Result<usize> {
+    match codec {
+        "snappy" => libcramjam::snappy::decompress(input, output),
+        "brotli" => libcramjam::brotli::decompress(input, output),
+        "bzip2" => libcramjam::bzip2::decompress(input, output),
+        "lz4" => libcramjam::lz4::decompress(input, output),
+        "gzip" => libcramjam::gzip::decompress(input, output),
+        "deflate" => libcramjam::deflate::decompress(input, output),
+        "zstd" => libcramjam::zstd::decompress(input, output),
+        "xz" => libcramjam::xz::decompress(input, output),
+        "zlib" => crate::zlib::zlib::decompress_with(input, output),
+        _ => unreachable!("codec checked by `check_codec`"),
+    }
+}
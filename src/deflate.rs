@@ -8,52 +8,142 @@ pub mod deflate {
     use crate::exceptions::{CompressionError, DecompressionError};
     use crate::io::RustyBuffer;
     use crate::{AsBytes, BytesType};
+    use libcramjam::deflate::flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+    use pyo3::exceptions::{PyOverflowError, PyValueError};
     use pyo3::prelude::*;
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+    const BUF_SIZE: usize = 1 << 15;
+    const FLUSH_MODES: [&str; 3] = ["sync", "full", "partial"];
 
     /// Deflate decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
+    /// `dictionary` must be the preset dictionary the data was compressed with. Raw deflate records
+    /// nothing identifying it, so a missing dictionary raises a `DecompressionError` as soon as the data
+    /// refers back into it, but a wrong one of at least the same length decodes to wrong output.
+    /// Dictionaries need the `deflate-dictionary` feature, on by default, see `compress`.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.deflate.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.deflate.decompress(compressed_bytes, output_len=Optional[int], dictionary=None)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::deflate::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, dictionary=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        dictionary: Option<BytesType>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = match dictionary {
+            Some(dictionary) => {
+                let dictionary = &*dictionary.read_bytes()?;
+                crate::generic!(
+                    py,
+                    decompress_with_dictionary[data],
+                    output_len = output_len,
+                    max_output_len = max_output_len,
+                    dictionary
+                )
+            }
+            None => crate::generic!(
+                py,
+                libcramjam::deflate::decompress[data],
+                output_len = output_len,
+                max_output_len = max_output_len
+            ),
+        }
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// Deflate compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
+    /// `dictionary` is a preset dictionary: bytes likely to occur in `data`, ie. a sample of similar
+    /// payloads, which back-references can point into from the start; greatly improving the ratio on
+    /// short, structured data. The same dictionary must then be given to `decompress`.
+    /// flate2's default backend, miniz_oxide, has no preset dictionaries, so they need the
+    /// `deflate-dictionary` feature, on by default, which switches it to zlib-rs; in a build without
+    /// it, giving one raises a `CompressionError`. `cramjam.build_info()` reports the backend.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.deflate.compress(b'some bytes here', level=5, output_len=Optional[int])  # level defaults to 6
+    /// >>> cramjam.deflate.compress(b'{"id": 1, "kind": "event"}', dictionary=b'{"id": , "kind": "event"}')
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, return_len=false, dictionary=None))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
-    ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::deflate::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        return_len: bool,
+        dictionary: Option<BytesType>,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = match dictionary {
+            Some(dictionary) => {
+                let dictionary = &*dictionary.read_bytes()?;
+                crate::generic!(
+                    py,
+                    compress_with_dictionary[data],
+                    output_len = output_len,
+                    level,
+                    dictionary
+                )
+            }
+            None => crate::generic!(py, libcramjam::deflate::compress[data], output_len = output_len, level),
+        }
+        .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::deflate::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
+    /// Worst case length of `compress_into` output for `n` bytes of input, for sizing its output
+    /// buffer. An upper limit, not the actual compressed length, which is usually far less.
+    /// Raises `OverflowError` if the bound doesn't fit in a `usize`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> output = bytearray(cramjam.deflate.compress_bound(len(data)))
+    /// >>> n_bytes = cramjam.deflate.compress_into(data, output)
+    /// ```
+    #[pyfunction]
+    pub fn compress_bound(n: usize) -> PyResult<usize> {
+        deflate_bound(n)
+    }
+
+    /// zlib's `deflateBound` for any level and settings, without the zlib wrapper: the larger of the
+    /// bounds for fixed Huffman and for stored blocks. `OverflowError` if it doesn't fit in a `usize`.
+    pub(crate) fn deflate_bound(n: usize) -> PyResult<usize> {
+        let sum = |terms: [usize; 5]| terms.into_iter().try_fold(0usize, usize::checked_add);
+        sum([n, n >> 3, n >> 8, n >> 9, 4])
+            .zip(sum([n, n >> 5, n >> 7, n >> 11, 7]))
+            .map(|(fixed_len, stored_len)| fixed_len.max(stored_len))
+            .ok_or_else(|| PyOverflowError::new_err(format!("compress bound of {} bytes overflows", n)))
+    }
+
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
@@ -63,19 +153,22 @@ pub mod deflate {
     /// Deflate Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::deflate::flate2::write::DeflateEncoder<Cursor<Vec<u8>>>>,
+        inner: Option<Encoder>,
+        finished: Option<RustyBuffer>,
     }
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, with an optional preset `dictionary`, see `compress`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
-            let level = level.unwrap_or_else(|| DEFAULT_COMPRESSION_LEVEL);
-            let compression = libcramjam::deflate::flate2::Compression::new(level);
-            let inner = libcramjam::deflate::flate2::write::DeflateEncoder::new(Cursor::new(vec![]), compression);
-            Ok(Self { inner: Some(inner) })
+        #[pyo3(signature = (level=None, dictionary=None))]
+        pub fn __init__(level: Option<u32>, dictionary: Option<BytesType>) -> PyResult<Self> {
+            let dictionary = dictionary.as_ref().map(|d| d.read_bytes()).transpose()?;
+            let compress = new_compress(level, dictionary.as_deref()).map_err(CompressionError::from_err)?;
+            Ok(Self {
+                inner: Some(Encoder::new(compress)),
+                finished: None,
+            })
         }
 
         /// Compress input into the current compressor's stream.
@@ -83,21 +176,253 @@ pub mod deflate {
             crate::io::stream_compress(&mut self.inner, input)
         }
 
-        /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        /// Flush and return current compressed stream.
+        ///
+        /// `mode` follows zlib's flush modes:
+        /// ```bash
+        /// "sync": Z_SYNC_FLUSH, align to a byte boundary with an empty stored block (default)
+        /// "full": Z_FULL_FLUSH, as "sync" but also reset the compression state
+        /// "partial": Z_PARTIAL_FLUSH, emit pending output without the trailing empty stored block
+        /// ```
+        #[pyo3(signature = (mode=None))]
+        pub fn flush(&mut self, mode: Option<&str>) -> PyResult<RustyBuffer> {
+            let mode = match mode.unwrap_or("sync") {
+                "sync" => FlushCompress::Sync,
+                "full" => FlushCompress::Full,
+                "partial" => FlushCompress::Partial,
+                other => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown flush mode '{}', expected one of {:?}",
+                        other, FLUSH_MODES
+                    )))
+                }
+            };
+            match self.inner.as_mut() {
+                Some(inner) => {
+                    inner.run(&[], mode).map_err(CompressionError::from_err)?;
+                    let buf = std::mem::take(inner.get_mut().get_mut());
+                    inner.get_mut().set_position(0);
+                    Ok(RustyBuffer::from(buf))
+                }
+                None => Ok(RustyBuffer::from(vec![])),
+            }
+        }
+
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            FLUSH_MODES.to_vec()
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            if let Some(buffer) = self.finished.take() {
+                return Ok(buffer);
+            }
+            crate::io::stream_finish(&mut self.inner, |inner| inner.finish())
+        }
+
+        /// Enter a `with` block; on leaving it the stream is finished, see `__exit__`.
+        fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        /// Finish the stream on leaving a `with` block, even if it raised, the compressed stream then
+        /// being returned by the next `finish()` call.
+        fn __exit__(
+            &mut self,
+            exc_type: &Bound<'_, PyAny>,
+            _exc_value: &Bound<'_, PyAny>,
+            _traceback: &Bound<'_, PyAny>,
+        ) -> PyResult<bool> {
+            let finished = self.finish();
+            self.finished = crate::io::stream_exit(finished, !exc_type.is_none())?;
+            Ok(false)
+        }
+    }
+
+    /// Raw deflate `Compress` stream at `level`, primed with the preset `dictionary` if given
+    fn new_compress(level: Option<u32>, dictionary: Option<&[u8]>) -> std::io::Result<Compress> {
+        let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+        let mut compress = Compress::new(Compression::new(level), false);
+        if let Some(dictionary) = dictionary {
+            dictionary::set_compress(&mut compress, dictionary)?;
+        }
+        Ok(compress)
+    }
+
+    /// Preset dictionaries, which flate2 only supports with a zlib backend, chosen by the
+    /// `deflate-dictionary` feature.
+    #[cfg(feature = "deflate-dictionary")]
+    mod dictionary {
+        use super::{Compress, Decompress};
+
+        pub(super) fn set_compress(compress: &mut Compress, dictionary: &[u8]) -> std::io::Result<()> {
+            compress
+                .set_dictionary(dictionary)
+                .map(|_| ())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+        }
+
+        pub(super) fn set_decompress(decompress: &mut Decompress, dictionary: &[u8]) -> std::io::Result<()> {
+            decompress
+                .set_dictionary(dictionary)
+                .map(|_| ())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+        }
+    }
+
+    #[cfg(not(feature = "deflate-dictionary"))]
+    mod dictionary {
+        use super::{Compress, Decompress};
+
+        pub(super) fn set_compress(_: &mut Compress, _: &[u8]) -> std::io::Result<()> {
+            Err(unsupported())
+        }
+
+        pub(super) fn set_decompress(_: &mut Decompress, _: &[u8]) -> std::io::Result<()> {
+            Err(unsupported())
         }
+
+        fn unsupported() -> std::io::Error {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Preset dictionaries need cramjam built with the `deflate-dictionary` feature",
+            )
+        }
+    }
+
+    fn compress_with_dictionary<W: Write + ?Sized, R: Read>(
+        mut input: R,
+        output: &mut W,
+        level: Option<u32>,
+        dictionary: &[u8],
+    ) -> std::io::Result<usize> {
+        let mut encoder = Encoder::new(new_compress(level, Some(dictionary))?);
+        crate::io::copy(&mut input, &mut encoder)?;
+        let compressed = encoder.finish()?;
+        output.write_all(&compressed)?;
+        Ok(compressed.len())
     }
 
+    fn decompress_with_dictionary<W: Write + ?Sized, R: Read>(
+        mut input: R,
+        output: &mut W,
+        dictionary: &[u8],
+    ) -> std::io::Result<usize> {
+        let mut decompress = Decompress::new(false);
+        dictionary::set_decompress(&mut decompress, dictionary)?;
+        let mut inbuf = vec![0; BUF_SIZE];
+        let mut outbuf = Vec::with_capacity(BUF_SIZE);
+        let (mut start, mut end, mut eof) = (0, 0, false);
+        let mut n_bytes = 0;
+        loop {
+            if start == end && !eof {
+                (start, end) = (0, input.read(&mut inbuf)?);
+                eof = end == 0;
+            }
+            outbuf.clear();
+            let before = decompress.total_in();
+            let status = decompress
+                .decompress_vec(&inbuf[start..end], &mut outbuf, FlushDecompress::None)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            start += (decompress.total_in() - before) as usize;
+            output.write_all(&outbuf)?;
+            n_bytes += outbuf.len();
+            match status {
+                Status::StreamEnd => return Ok(n_bytes),
+                // no more input, and none of what's left produces output: the input ended mid-stream
+                _ if eof && outbuf.is_empty() => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Streaming deflate encoder over a raw `Compress` stream, needed to reach
+    /// zlib flush modes that `flate2::write::DeflateEncoder` doesn't expose.
+    pub(crate) struct Encoder {
+        compress: Compress,
+        inner: Cursor<Vec<u8>>,
+    }
+
+    impl Encoder {
+        pub(crate) fn new(compress: Compress) -> Self {
+            Self {
+                compress,
+                inner: Cursor::new(vec![]),
+            }
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut Cursor<Vec<u8>> {
+            &mut self.inner
+        }
+
+        /// Feed `input` through the compressor with the given flush mode, appending all output
+        pub(crate) fn run(&mut self, mut input: &[u8], flush: FlushCompress) -> std::io::Result<usize> {
+            let start = self.compress.total_in();
+            loop {
+                let out = self.inner.get_mut();
+                out.reserve(BUF_SIZE);
+                let before = self.compress.total_in();
+                let status = self
+                    .compress
+                    .compress_vec(input, out, flush)
+                    .map_err(std::io::Error::other)?;
+                input = &input[(self.compress.total_in() - before) as usize..];
+
+                // Output space left over means the compressor had nothing more to give for this mode
+                let drained = out.len() < out.capacity();
+                match status {
+                    Status::StreamEnd => break,
+                    Status::BufError if input.is_empty() => break,
+                    _ if drained && input.is_empty() && flush != FlushCompress::Finish => break,
+                    _ => continue,
+                }
+            }
+            let len = self.inner.get_ref().len();
+            self.inner.set_position(len as u64);
+            Ok((self.compress.total_in() - start) as usize)
+        }
+
+        pub(crate) fn finish(mut self) -> std::io::Result<Vec<u8>> {
+            self.run(&[], FlushCompress::Finish)?;
+            Ok(self.inner.into_inner())
+        }
+    }
+
+    impl Write for Encoder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.run(buf, FlushCompress::None)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.run(&[], FlushCompress::Sync).map(|_| ())
+        }
+    }
+
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(deflate, u32, 0, 9);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            deflate, level: u32;
+            checked, reproducible, bench, prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records,
+    };
+
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(deflate);
+        crate::make_decompressor!(deflate, decoder = crate::zlib::zlib::StreamDecoder::new(false));
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
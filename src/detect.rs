@@ -0,0 +1,139 @@
+//! Cheap detection of already compressed data, to avoid compressing it twice.
+use crate::exceptions::DecompressionError;
+use crate::io::{AsBytes, RustyBuffer};
+use crate::BytesType;
+use pyo3::prelude::*;
+
+/// Magic bytes of compressed formats; the codecs here and common compressed containers.
+const MAGIC: [&[u8]; 12] = [
+    b"\x1f\x8b",               // gzip
+    b"\x28\xb5\x2f\xfd",       // zstd
+    b"\xfd7zXZ\x00",           // xz
+    b"\x5d\x00\x00",           // lzma alone
+    b"BZh",                    // bzip2
+    b"\x04\x22\x4d\x18",       // lz4 frame
+    b"\xff\x06\x00\x00sNaPpY", // snappy framed
+    b"PK\x03\x04",             // zip
+    b"7z\xbc\xaf\x27\x1c",     // 7z
+    b"\x89PNG\r\n\x1a\n",      // png
+    b"\xff\xd8\xff",           // jpeg
+    b"\x5e\x2a\x4d\x18",       // zstd/lz4 skippable frame
+];
+
+/// Magic bytes of the codecs `guess_codec` recognises, by module name; zlib has none, see `is_zlib`.
+const CODEC_MAGIC: [(&str, &[u8]); 6] = [
+    ("zstd", b"\x28\xb5\x2f\xfd"),
+    ("gzip", b"\x1f\x8b\x08"),
+    ("xz", b"\xfd7zXZ\x00"),
+    ("bzip2", b"BZh"),
+    ("lz4", b"\x04\x22\x4d\x18"),
+    ("snappy", b"\xff\x06\x00\x00sNaPpY"),
+];
+
+/// Bytes peeked at by `guess_codec`, enough for the longest of `CODEC_MAGIC`.
+const PEEK_LEN: usize = 10;
+
+/// Bytes sampled for the entropy estimate, taken as evenly spaced chunks across the input.
+const SAMPLE_LEN: usize = 4096;
+const SAMPLE_CHUNKS: usize = 16;
+
+/// Shannon entropy, in bits per byte, above which a sample is considered compressed.
+const ENTROPY_THRESHOLD: f64 = 7.5;
+
+/// Whether `data` looks to already be compressed, so compressing it again is likely wasted effort.
+///
+/// True if `data` starts with the magic bytes of a known compressed format, (ie. gzip, zstd, xz, bzip2,
+/// lz4 and snappy frames, zip, png or jpeg) or if a sample of it has near random entropy. Formats
+/// without magic bytes, (ie. raw deflate, brotli or snappy's raw format) are only caught by the
+/// latter. Inputs shorter than the sample are judged on magic bytes alone, as entropy of a small sample
+/// is too noisy. A `File` is read and left at the same position.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> if not cramjam.looks_compressed(data):
+/// ...     data = cramjam.zstd.compress(data)
+/// ```
+#[pyfunction]
+pub fn looks_compressed(py: Python, data: BytesType) -> PyResult<bool> {
+    // the sample spans all of the input, so a file is read whole
+    let bytes = &*data.peek(usize::MAX)?;
+    Ok(py.allow_threads(|| is_compressed(bytes)))
+}
+
+/// Name of the codec module, ie. `"zstd"`, whose format `data` looks to be in from its first few
+/// bytes, or `None` if none match; so blobs from mixed sources can be dispatched to the right
+/// `cramjam.<codec>.decompress`.
+///
+/// Recognised are zstd, gzip, xz, bzip2, and the lz4 and snappy frame formats by their magic bytes,
+/// and zlib by its two byte header, whose checksum a random header still passes 1 time in 31; so
+/// treat a `"zlib"` guess as a hint. Formats without a header, (ie. raw deflate, brotli or snappy's
+/// raw format) can't be recognised. A `File` is peeked at and left at the same position.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> codec = cramjam.guess_codec(blob)
+/// >>> data = getattr(cramjam, codec).decompress(blob) if codec else blob
+/// ```
+#[pyfunction]
+pub fn guess_codec(data: BytesType) -> PyResult<Option<&'static str>> {
+    let bytes = &*data.peek(PEEK_LEN)?;
+    let codec = CODEC_MAGIC
+        .iter()
+        .find(|(_, magic)| bytes.starts_with(magic))
+        .map(|(codec, _)| *codec);
+    Ok(codec.or_else(|| is_zlib(bytes).then_some("zlib")))
+}
+
+/// Whether `bytes` start with a zlib header: deflate with a window of at most 32KiB, no preset
+/// dictionary, and the header's check bits making it a multiple of 31.
+fn is_zlib(bytes: &[u8]) -> bool {
+    match bytes {
+        [cmf, flg, ..] => {
+            let (method, window) = (cmf & 0x0f, cmf >> 4);
+            let preset_dictionary = flg & 0x20 != 0;
+            method == 8 && window <= 7 && !preset_dictionary && u16::from_be_bytes([*cmf, *flg]) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+/// Raise `DecompressionError` if `reject_nested` and the decompressed `buffer` itself looks compressed
+pub(crate) fn check_nested(buffer: RustyBuffer, reject_nested: bool) -> PyResult<RustyBuffer> {
+    if reject_nested && is_compressed(buffer.as_bytes()) {
+        return Err(DecompressionError::new_err(
+            "Decompressed data looks to be compressed itself, refusing nested compressed data",
+        ));
+    }
+    Ok(buffer)
+}
+
+/// Magic bytes, then entropy check of `looks_compressed`
+pub(crate) fn is_compressed(bytes: &[u8]) -> bool {
+    if MAGIC.iter().any(|magic| bytes.starts_with(magic)) {
+        return true;
+    }
+    bytes.len() >= SAMPLE_LEN && entropy(bytes) > ENTROPY_THRESHOLD
+}
+
+/// Shannon entropy of evenly spaced chunks of `bytes`, in bits per byte
+fn entropy(bytes: &[u8]) -> f64 {
+    let chunk_len = SAMPLE_LEN / SAMPLE_CHUNKS;
+    let stride = (bytes.len() - chunk_len) / (SAMPLE_CHUNKS - 1);
+
+    let mut counts = [0usize; 256];
+    for i in 0..SAMPLE_CHUNKS {
+        for byte in &bytes[i * stride..i * stride + chunk_len] {
+            counts[*byte as usize] += 1;
+        }
+    }
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / SAMPLE_LEN as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
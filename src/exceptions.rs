@@ -2,19 +2,197 @@
 //! cramjam specific Python exceptions
 use pyo3::create_exception;
 use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::any::Any;
 
 create_exception!(cramjam, CompressionError, PyException);
 create_exception!(cramjam, DecompressionError, PyException);
 
+/// Attributes describing where a de/compression failed, always present on both exceptions and
+/// `None` when not known:
+/// ```bash
+/// codec: name of the codec module, ie. "zstd"
+/// consumed: bytes read from the input before the failure
+/// produced: bytes written to the output before the failure
+/// backend_code: the codec library's own error code, currently only zstd's ZSTD_ErrorCode
+/// zstd_code: zstd's ZSTD_ErrorCode, for zstd errors
+/// zstd_name: symbolic name of `zstd_code`, ie. "ZSTD_error_dstSize_tooSmall"
+/// ```
+const CONTEXT_ATTRS: [&str; 6] = [
+    "codec",
+    "consumed",
+    "produced",
+    "backend_code",
+    "zstd_code",
+    "zstd_name",
+];
+
+/// zstd's ZSTD_ErrorCode values and their names in zstd_errors.h
+const ZSTD_ERROR_NAMES: [(usize, &str); 35] = [
+    (1, "ZSTD_error_GENERIC"),
+    (10, "ZSTD_error_prefix_unknown"),
+    (12, "ZSTD_error_version_unsupported"),
+    (14, "ZSTD_error_frameParameter_unsupported"),
+    (16, "ZSTD_error_frameParameter_windowTooLarge"),
+    (20, "ZSTD_error_corruption_detected"),
+    (22, "ZSTD_error_checksum_wrong"),
+    (24, "ZSTD_error_literals_headerWrong"),
+    (30, "ZSTD_error_dictionary_corrupted"),
+    (32, "ZSTD_error_dictionary_wrong"),
+    (34, "ZSTD_error_dictionaryCreation_failed"),
+    (40, "ZSTD_error_parameter_unsupported"),
+    (41, "ZSTD_error_parameter_combination_unsupported"),
+    (42, "ZSTD_error_parameter_outOfBound"),
+    (44, "ZSTD_error_tableLog_tooLarge"),
+    (46, "ZSTD_error_maxSymbolValue_tooLarge"),
+    (48, "ZSTD_error_maxSymbolValue_tooSmall"),
+    (49, "ZSTD_error_cannotProduce_uncompressedBlock"),
+    (50, "ZSTD_error_stabilityCondition_notRespected"),
+    (60, "ZSTD_error_stage_wrong"),
+    (62, "ZSTD_error_init_missing"),
+    (64, "ZSTD_error_memory_allocation"),
+    (66, "ZSTD_error_workSpace_tooSmall"),
+    (70, "ZSTD_error_dstSize_tooSmall"),
+    (72, "ZSTD_error_srcSize_wrong"),
+    (74, "ZSTD_error_dstBuffer_null"),
+    (80, "ZSTD_error_noForwardProgress_destFull"),
+    (82, "ZSTD_error_noForwardProgress_inputEmpty"),
+    (100, "ZSTD_error_frameIndex_tooLarge"),
+    (102, "ZSTD_error_seekableIO"),
+    (104, "ZSTD_error_dstBuffer_wrong"),
+    (105, "ZSTD_error_srcBuffer_wrong"),
+    (106, "ZSTD_error_sequenceProducer_failed"),
+    (107, "ZSTD_error_externalSequences_invalid"),
+    (120, "ZSTD_error_maxCode"),
+];
+
 impl CompressionError {
     // From<ToString> already impl
-    pub fn from_err<T: ToString>(err: T) -> pyo3::PyErr {
-        CompressionError::new_err(err.to_string()).into()
+    pub fn from_err<T: ToString + 'static>(err: T) -> pyo3::PyErr {
+        python_err(&err).unwrap_or_else(|| with_context(CompressionError::new_err(err.to_string()), &err))
     }
 }
 
 impl DecompressionError {
-    pub fn from_err<T: ToString>(err: T) -> pyo3::PyErr {
-        DecompressionError::new_err(err.to_string())
+    pub fn from_err<T: ToString + 'static>(err: T) -> pyo3::PyErr {
+        python_err(&err).unwrap_or_else(|| with_context(DecompressionError::new_err(err.to_string()), &err))
+    }
+}
+
+/// Context of a failed de/compression, carried out through an `io::Error` to be set
+/// as attributes of the raised exception by `from_err`.
+#[derive(Debug)]
+pub(crate) struct ErrorContext {
+    codec: &'static str,
+    consumed: usize,
+    produced: usize,
+    source: std::io::Error,
+}
+
+impl ErrorContext {
+    /// Wrap `source` with its context, `module_path` being that of the codec's module, ie. from `module_path!()`
+    pub(crate) fn wrap(
+        source: std::io::Error,
+        module_path: &'static str,
+        consumed: usize,
+        produced: usize,
+    ) -> std::io::Error {
+        // Python exceptions, (ie. KeyboardInterrupt) are raised as-is, without context.
+        if source.get_ref().is_some_and(|err| err.is::<PyErr>()) {
+            return source;
+        }
+        let codec = module_path.split("::").nth(1).unwrap_or(module_path);
+        let context = ErrorContext {
+            codec,
+            consumed,
+            produced,
+            source,
+        };
+        std::io::Error::new(context.source.kind(), context)
     }
+
+    fn backend_code(&self) -> Option<usize> {
+        match self.codec {
+            // zstd crate only keeps the error's name, so map it back to its ZSTD_ErrorCode
+            "zstd" => {
+                let name = self.source.to_string();
+                ZSTD_ERROR_NAMES
+                    .iter()
+                    .map(|(code, _)| *code)
+                    .find(|code| libcramjam::zstd::zstd::zstd_safe::get_error_name(0usize.wrapping_sub(*code)) == name)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Default the context attributes to `None` on the exception classes, for errors raised without context
+pub(crate) fn add_context_defaults(py: Python) -> PyResult<()> {
+    for attr in CONTEXT_ATTRS {
+        py.get_type_bound::<CompressionError>().setattr(attr, py.None())?;
+        py.get_type_bound::<DecompressionError>().setattr(attr, py.None())?;
+    }
+    Ok(())
+}
+
+fn with_context<T: 'static>(pyerr: PyErr, err: &T) -> PyErr {
+    let context = (err as &dyn Any)
+        .downcast_ref::<std::io::Error>()
+        .and_then(|err| err.get_ref())
+        .and_then(|err| err.downcast_ref::<ErrorContext>());
+    if let Some(context) = context {
+        // Failing to set an attribute on a fresh exception instance is unlikely, and the original
+        // error is still the more useful one to raise if it did.
+        let _ = Python::with_gil(|py| -> PyResult<()> {
+            let value = pyerr.value_bound(py);
+            value.setattr("codec", context.codec)?;
+            value.setattr("consumed", context.consumed)?;
+            value.setattr("produced", context.produced)?;
+            let backend_code = context.backend_code();
+            value.setattr("backend_code", backend_code)?;
+            if let Some(code) = backend_code {
+                value.setattr("zstd_code", code)?;
+                value.setattr("zstd_name", zstd_error_name(code))?;
+            }
+            Ok(())
+        });
+    }
+    pyerr
+}
+
+/// Set the zstd error attributes of `pyerr` for `result`, the error result of a libzstd function
+pub(crate) fn with_zstd_code(pyerr: PyErr, result: libcramjam::zstd::zstd::zstd_safe::ErrorCode) -> PyErr {
+    let code = 0usize.wrapping_sub(result);
+    let _ = Python::with_gil(|py| -> PyResult<()> {
+        let value = pyerr.value_bound(py);
+        value.setattr("codec", "zstd")?;
+        value.setattr("backend_code", code)?;
+        value.setattr("zstd_code", code)?;
+        value.setattr("zstd_name", zstd_error_name(code))
+    });
+    pyerr
+}
+
+fn zstd_error_name(code: usize) -> Option<&'static str> {
+    ZSTD_ERROR_NAMES.iter().find(|(c, _)| *c == code).map(|(_, name)| *name)
+}
+
+/// A Python exception raised mid-operation and carried out through an `io::Error`,
+/// ie. `KeyboardInterrupt` from `SignalCheckedReader`, which should be raised as-is.
+fn python_err<T: 'static>(err: &T) -> Option<PyErr> {
+    let err = (err as &dyn Any).downcast_ref::<std::io::Error>()?;
+    let err = err.get_ref()?.downcast_ref::<PyErr>()?;
+    Some(Python::with_gil(|py| err.clone_ref(py)))
 }
@@ -0,0 +1,181 @@
+//! Filters which rearrange bytes to make data more compressible, to be paired
+//! with any of the codecs.
+use crate::io::RustyBuffer;
+use crate::BytesType;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Byte shuffle; transpose fixed-width elements so the first byte of every element
+/// comes first, then every second byte, and so on. Same as Blosc's shuffle filter.
+///
+/// Trailing bytes which don't make up a full element are copied through unchanged.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> shuffled = cramjam.shuffle(np.arange(100, dtype=np.float64), element_size=8)
+/// >>> compressed = cramjam.zstd.compress(shuffled)
+/// ```
+#[pyfunction]
+pub fn shuffle(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let n_elements = bytes.len() / element_size;
+        let mut out = vec![0; bytes.len()];
+        for (i, element) in bytes.chunks_exact(element_size).enumerate() {
+            for (j, byte) in element.iter().enumerate() {
+                out[j * n_elements + i] = *byte;
+            }
+        }
+        let tail = n_elements * element_size;
+        out[tail..].copy_from_slice(&bytes[tail..]);
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+/// Reverse a byte [`shuffle`](fn.shuffle.html), `element_size` must match what was used to shuffle.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.unshuffle(cramjam.zstd.decompress(compressed), element_size=8)
+/// ```
+#[pyfunction]
+pub fn unshuffle(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let n_elements = bytes.len() / element_size;
+        let mut out = vec![0; bytes.len()];
+        for (i, element) in out.chunks_exact_mut(element_size).enumerate() {
+            for (j, byte) in element.iter_mut().enumerate() {
+                *byte = bytes[j * n_elements + i];
+            }
+        }
+        let tail = n_elements * element_size;
+        out[tail..].copy_from_slice(&bytes[tail..]);
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+/// Bit shuffle; transpose the bits of fixed-width elements so that bit `k` of byte `j` of every
+/// element are stored together, same layout as the HDF5 bitshuffle filter treating the input as a single block.
+///
+/// Only whole groups of 8 elements are transposed, any remaining bytes are copied through unchanged.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> shuffled = cramjam.bitshuffle(np.arange(100, dtype=np.int32), element_size=4)
+/// >>> compressed = cramjam.zstd.compress(shuffled)
+/// ```
+#[pyfunction]
+pub fn bitshuffle(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let n_elements = bytes.len() / element_size / 8 * 8;
+        let row_len = n_elements / 8;
+        let mut out = vec![0; bytes.len()];
+        for (i, element) in bytes[..n_elements * element_size]
+            .chunks_exact(element_size)
+            .enumerate()
+        {
+            for (j, byte) in element.iter().enumerate() {
+                for k in 0..8 {
+                    out[(j * 8 + k) * row_len + i / 8] |= ((byte >> k) & 1) << (i % 8);
+                }
+            }
+        }
+        let tail = n_elements * element_size;
+        out[tail..].copy_from_slice(&bytes[tail..]);
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+/// Reverse a [`bitshuffle`](fn.bitshuffle.html), `element_size` must match what was used to shuffle.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.bitunshuffle(cramjam.zstd.decompress(compressed), element_size=4)
+/// ```
+#[pyfunction]
+pub fn bitunshuffle(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let n_elements = bytes.len() / element_size / 8 * 8;
+        let row_len = n_elements / 8;
+        let mut out = vec![0; bytes.len()];
+        for (i, element) in out[..n_elements * element_size]
+            .chunks_exact_mut(element_size)
+            .enumerate()
+        {
+            for (j, byte) in element.iter_mut().enumerate() {
+                for k in 0..8 {
+                    *byte |= ((bytes[(j * 8 + k) * row_len + i / 8] >> (i % 8)) & 1) << k;
+                }
+            }
+        }
+        let tail = n_elements * element_size;
+        out[tail..].copy_from_slice(&bytes[tail..]);
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+/// Delta encoding; each byte is replaced with its wrapping difference from the byte `element_size`
+/// positions before it. Same semantics as the xz delta filter with `dist=element_size`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> encoded = cramjam.delta_encode(np.arange(100, dtype=np.uint16), element_size=2)
+/// >>> compressed = cramjam.xz.compress(encoded)
+/// ```
+#[pyfunction]
+pub fn delta_encode(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let mut out = bytes.to_vec();
+        for i in (element_size..out.len()).rev() {
+            out[i] = out[i].wrapping_sub(out[i - element_size]);
+        }
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+/// Reverse a [`delta_encode`](fn.delta_encode.html), `element_size` must match what was used to encode.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.delta_decode(cramjam.xz.decompress(compressed), element_size=2)
+/// ```
+#[pyfunction]
+pub fn delta_decode(py: Python, data: BytesType, element_size: usize) -> PyResult<RustyBuffer> {
+    check_element_size(element_size)?;
+    let bytes = &*data.read_bytes()?;
+    let out = py.allow_threads(|| {
+        let mut out = bytes.to_vec();
+        for i in element_size..out.len() {
+            out[i] = out[i].wrapping_add(out[i - element_size]);
+        }
+        out
+    });
+    Ok(RustyBuffer::from(out))
+}
+
+fn check_element_size(element_size: usize) -> PyResult<()> {
+    if element_size == 0 {
+        return Err(PyValueError::new_err("element_size must be greater than 0"));
+    }
+    Ok(())
+}
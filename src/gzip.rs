@@ -6,60 +6,791 @@ use pyo3::prelude::*;
 pub mod gzip {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IncrementalDecoder, LimitedWriter, PythonReader, PythonWriter, RustyBuffer};
     use crate::BytesType;
+    use libcramjam::gzip::flate2::{
+        Compress, Compression, Crc, Decompress, FlushCompress, FlushDecompress, GzBuilder, Status,
+    };
+    use pyo3::exceptions::{PyIndexError, PyOverflowError, PyValueError};
     use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyDict};
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+    const DECOMPRESS_CHUNK_LEN: usize = 32 * 1024;
+    const PARALLEL_BLOCK_SIZE: usize = 128 * 1024; // pigz's default
+    const OS_UNKNOWN: u8 = 255;
+    const FLUSH_MODES: [&str; 1] = ["sync"];
+    const HEADER_LEN: usize = 10;
+    const TRAILER_LEN: usize = 8;
 
     /// Gzip decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
+    /// `on_crc_error` controls what happens when a member's CRC32 trailer doesn't match its body:
+    /// `"raise"` (default) raises a `DecompressionError`, `"warn"` emits a `UserWarning` and returns
+    /// the body, and `"ignore"` returns the body silently; useful for salvaging truncated-then-patched
+    /// or bit-rotted archives. Malformed deflate data still raises in every mode.
+    ///
+    /// With `require_exact=True`, `data` must be exactly one member: a `DecompressionError` is raised
+    /// if any bytes, be they another member or anything else, follow it. Finding the member's end
+    /// takes a decoding pass of its own, see `frame_compressed_size`.
+    ///
+    /// With `first_member_only=True`, only the first member is decompressed and anything after it,
+    /// be it more members or unrelated data, is left alone; a tuple of the output and the number of
+    /// bytes of `data` the member took is returned, locating where the rest starts. CRC32 mismatches
+    /// then always raise.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.gzip.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.gzip.decompress(compressed_bytes, on_crc_error="warn")
+    /// >>> output, n_consumed = cramjam.gzip.decompress(compressed_bytes, first_member_only=True)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::gzip::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, on_crc_error="raise", max_input_len=None, require_exact=false, first_member_only=false, max_output_len=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress(
+        py: Python,
+        mut data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        on_crc_error: &str,
+        max_input_len: Option<usize>,
+        require_exact: bool,
+        first_member_only: bool,
+        max_output_len: Option<usize>,
+    ) -> PyResult<PyObject> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        if require_exact {
+            crate::io::check_exact(&data, |bytes| {
+                py.allow_threads(|| member_len(bytes))
+                    .map_err(DecompressionError::from_err)
+            })?;
+        }
+        if first_member_only {
+            if on_crc_error != "raise" {
+                return Err(PyValueError::new_err(
+                    "first_member_only can't be combined with on_crc_error",
+                ));
+            }
+            let mut file_bytes = vec![];
+            let bytes = match &mut data {
+                BytesType::RustyFile(_) => {
+                    data.read_to_end(&mut file_bytes)?;
+                    &file_bytes[..]
+                }
+                _ => data.as_bytes(),
+            };
+            let (out, consumed) = py
+                .allow_threads(|| -> std::io::Result<_> {
+                    let mut decoder = libcramjam::gzip::flate2::bufread::GzDecoder::new(bytes);
+                    let mut out = Vec::with_capacity(output_len.unwrap_or(0));
+                    std::io::copy(&mut decoder, &mut LimitedWriter::new(&mut out, max_output_len))?;
+                    Ok((out, bytes.len() - decoder.into_inner().len()))
+                })
+                .map_err(DecompressionError::from_err)?;
+            let buffer = crate::detect::check_nested(RustyBuffer::from(out), reject_nested)?;
+            return Ok((buffer, consumed).into_py(py));
+        }
+        let buffer = match on_crc_error {
+            "raise" => crate::generic!(
+                py,
+                libcramjam::gzip::decompress[data],
+                output_len = output_len,
+                max_output_len = max_output_len
+            )
+            .map_err(DecompressionError::from_err)?,
+            "warn" | "ignore" => {
+                let mut input = vec![];
+                data.read_to_end(&mut input)?;
+                let (out, crc_ok) = py
+                    .allow_threads(|| decompress_unchecked(&input, max_output_len))
+                    .map_err(DecompressionError::from_err)?;
+                if !crc_ok && on_crc_error == "warn" {
+                    let category = py.get_type_bound::<pyo3::exceptions::PyUserWarning>();
+                    PyErr::warn_bound(py, &category, "gzip CRC32 checksum mismatch, data may be corrupt", 1)?;
+                }
+                RustyBuffer::from(out)
+            }
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "on_crc_error must be one of 'raise', 'warn' or 'ignore', got {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(crate::detect::check_nested(buffer, reject_nested)?.into_py(py))
+    }
+
+    /// Decompress all members without failing on CRC32 mismatches, returning the output and
+    /// whether every member's checksum matched.
+    fn decompress_unchecked(input: &[u8], max_output_len: Option<usize>) -> std::io::Result<(Vec<u8>, bool)> {
+        use libcramjam::gzip::flate2::bufread::DeflateDecoder;
+        use std::io::{Error, ErrorKind};
+
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        let mut out = vec![];
+        let mut crc_ok = true;
+        let mut remaining = input;
+        while !remaining.is_empty() {
+            let header_len = header_len(remaining).ok_or_else(|| invalid("Invalid gzip header"))?;
+            let mut decoder = DeflateDecoder::new(&remaining[header_len..]);
+            let start = out.len();
+            let max_len = max_output_len.map(|max_len| max_len.saturating_sub(start));
+            std::io::copy(&mut decoder, &mut LimitedWriter::new(&mut out, max_len))?;
+            let rest = decoder.into_inner();
+            if rest.len() < 8 {
+                return Err(invalid("Truncated gzip trailer"));
+            }
+            let mut crc = Crc::new();
+            crc.update(&out[start..]);
+            let expected = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            crc_ok &= crc.sum() == expected;
+            remaining = &rest[8..];
+        }
+        Ok((out, crc_ok))
+    }
+
+    /// Length of the gzip member header at the start of `data`, `None` if it is invalid or truncated.
+    fn header_len(data: &[u8]) -> Option<usize> {
+        const FHCRC: u8 = 0x02;
+        const FEXTRA: u8 = 0x04;
+        const FNAME: u8 = 0x08;
+        const FCOMMENT: u8 = 0x10;
+
+        if data.len() < 10 || data[..3] != [0x1f, 0x8b, 0x08] {
+            return None;
+        }
+        let flags = data[3];
+        let mut pos = 10;
+        if flags & FEXTRA != 0 {
+            let xlen = u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+            pos += 2 + xlen;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                pos += data.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+        (pos <= data.len()).then_some(pos)
     }
 
     /// Gzip compression.
     ///
+    /// `filename` and `comment` are stored in the gzip header, which the format defines as
+    /// ISO-8859-1 (latin-1); a `ValueError` is raised if either has characters outside of
+    /// latin-1 or a NUL, which would terminate the field. `mtime` is the modification time stored
+    /// alongside them, in seconds since the epoch; `0`, the default, means none is recorded.
+    /// Read them back with `read_header`, and `gunzip -N` restores the file's name and time from them.
+    ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
+    /// `minimal=True` guarantees the most compact header, the fixed 10 bytes with `mtime=0`, `OS=255`
+    /// (unknown) and no flags, as `compress_reproducible` writes; so it can't be combined with
+    /// `filename`, `comment` or `mtime`.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.gzip.compress(b'some bytes here', level=2, output_len=Optional[int])  # Level defaults to 6
+    /// >>> cramjam.gzip.compress(b'some bytes here', filename='data.txt', comment='café', mtime=1700000000)
+    /// >>> cramjam.gzip.compress(b'some bytes here', minimal=True)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, filename=None, comment=None, return_len=false, minimal=false, mtime=None))]
+    #[allow(clippy::too_many_arguments)]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        filename: Option<&str>,
+        comment: Option<&str>,
+        return_len: bool,
+        minimal: bool,
+        mtime: Option<u32>,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = if minimal {
+            if filename.is_some() || comment.is_some() || mtime.is_some() {
+                return Err(PyValueError::new_err(
+                    "minimal=True writes no filename, comment or mtime to the header",
+                ));
+            }
+            crate::generic!(py, reproducible[data], output_len = output_len, level)
+        } else if filename.is_some() || comment.is_some() || mtime.is_some() {
+            let builder = header_builder(filename, comment, mtime)?;
+            crate::generic!(py, with_header[data], output_len = output_len, level, builder)
+        } else {
+            crate::generic!(py, libcramjam::gzip::compress[data], output_len = output_len, level)
+        }
+        .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
+    }
+
+    fn with_header<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<u32>,
+        builder: GzBuilder,
+    ) -> std::io::Result<usize> {
+        let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+        let mut encoder = builder.read(input, libcramjam::gzip::flate2::Compression::new(level));
+        let n_bytes = crate::io::copy(&mut encoder, output)?;
+        Ok(n_bytes as usize)
+    }
+
+    /// `GzBuilder` writing the given header fields, see `compress`
+    fn header_builder(filename: Option<&str>, comment: Option<&str>, mtime: Option<u32>) -> PyResult<GzBuilder> {
+        let mut builder = GzBuilder::new();
+        if let Some(filename) = filename {
+            builder = builder.filename(latin1("filename", filename)?);
+        }
+        if let Some(comment) = comment {
+            builder = builder.comment(latin1("comment", comment)?);
+        }
+        if let Some(mtime) = mtime {
+            builder = builder.mtime(mtime);
+        }
+        Ok(builder)
+    }
+
+    /// Encode a gzip header field as latin-1, rejecting what it can't represent
+    fn latin1(field: &str, value: &str) -> PyResult<Vec<u8>> {
+        value
+            .chars()
+            .map(|c| match u8::try_from(c) {
+                Ok(0) => Err(PyValueError::new_err(format!(
+                    "gzip {} cannot contain a NUL character",
+                    field
+                ))),
+                Ok(byte) => Ok(byte),
+                Err(_) => Err(PyValueError::new_err(format!(
+                    "gzip {} must be latin-1 encodable, found {:?}",
+                    field, c
+                ))),
+            })
+            .collect()
+    }
+
+    /// Gzip compression with a byte-stable header, suitable for comparing against golden files.
+    ///
+    /// All nondeterministic header fields are pinned: `mtime=0`, `OS=255` (unknown) and
+    /// no filename, comment or extra field; so the same data and level always give the same bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.compress_reproducible(b'some bytes here', level=2, output_len=Optional[int])  # Level defaults to 6
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, level=None, output_len=None))]
+    pub fn compress_reproducible(
+        py: Python,
+        data: BytesType,
+        level: Option<u32>,
+        output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        _level::check_level(level)?;
+        crate::generic!(py, reproducible[data], output_len = output_len, level).map_err(CompressionError::from_err)
+    }
+
+    fn reproducible<W: Write + ?Sized, R: Read>(input: R, output: &mut W, level: Option<u32>) -> std::io::Result<usize> {
+        let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+        let mut encoder = GzBuilder::new()
+            .mtime(0)
+            .operating_system(255)
+            .read(input, libcramjam::gzip::flate2::Compression::new(level));
+        let n_bytes = crate::io::copy(&mut encoder, output)?;
+        Ok(n_bytes as usize)
+    }
+
+    /// Gzip compression of `data` as pigz does, in blocks of `block_size` bytes deflated in parallel by
+    /// `n_workers` threads, then joined into a single gzip member; each block but the last ends on a
+    /// sync flush, so their deflate streams concatenate. `block_size` defaults to 128KiB and
+    /// `n_workers` to the number of CPUs.
+    ///
+    /// Blocks are compressed independently, without the previous block's data to match against, so
+    /// output is slightly larger than `compress`; but any gzip reader decodes it, as one member with
+    /// a minimal header, `mtime=0` and `OS=255` (unknown).
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.compress_parallel(data, block_size=1 << 20, level=6, n_workers=4)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, block_size=None, level=None, n_workers=None))]
+    pub fn compress_parallel(
+        py: Python,
+        data: BytesType,
+        block_size: Option<usize>,
+        level: Option<u32>,
+        n_workers: Option<usize>,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::gzip::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        let block_size = block_size.unwrap_or(PARALLEL_BLOCK_SIZE);
+        if block_size == 0 {
+            return Err(PyValueError::new_err("block_size must be greater than 0"));
+        }
+        if n_workers == Some(0) {
+            return Err(PyValueError::new_err("n_workers must be greater than 0"));
+        }
+        _level::check_level(level)?;
+        let level = Compression::new(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL));
+        let bytes = &*data.read_bytes()?;
+        let mut blocks = bytes.chunks(block_size).collect::<Vec<_>>();
+        if blocks.is_empty() {
+            blocks.push(&[]); // still a valid member, a single empty final block
+        }
+        let n_blocks = blocks.len();
+        let outputs = py
+            .allow_threads(|| {
+                let n_workers = n_workers.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+                let chunk_len = n_blocks.div_ceil(n_workers).max(1);
+                std::thread::scope(|scope| {
+                    let workers = blocks
+                        .chunks(chunk_len)
+                        .enumerate()
+                        .map(|(i, chunk)| {
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(j, block)| {
+                                        let last = i * chunk_len + j == n_blocks - 1;
+                                        let mut crc = Crc::new();
+                                        crc.update(block);
+                                        deflate_block(block, level, last).map(|out| (out, crc))
+                                    })
+                                    .collect::<std::io::Result<Vec<_>>>()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    workers
+                        .into_iter()
+                        .map(|worker| worker.join().expect("gzip parallel worker panicked"))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+            })
+            .map_err(CompressionError::from_err)?;
+
+        let outputs = outputs.into_iter().flatten().collect::<Vec<_>>();
+        let mut out = Vec::with_capacity(10 + outputs.iter().map(|(o, _)| o.len()).sum::<usize>() + 8);
+        let xfl = match level.level() {
+            9 => 2,
+            1 => 4,
+            _ => 0,
+        };
+        out.extend_from_slice(&[0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, xfl, OS_UNKNOWN]);
+        let mut crc = Crc::new();
+        for (compressed, block_crc) in outputs.iter() {
+            out.extend_from_slice(compressed);
+            crc.combine(block_crc);
+        }
+        out.extend_from_slice(&crc.sum().to_le_bytes());
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes()); // ISIZE is the length modulo 2^32
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// Raw deflate of one of `compress_parallel`'s blocks, ending on a sync flush so the next
+    /// block's stream can follow, or if `last`, with the final block.
+    fn deflate_block(block: &[u8], level: Compression, last: bool) -> std::io::Result<Vec<u8>> {
+        let mut compress = Compress::new(level, false);
+        let flush = if last {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Sync
+        };
+        let mut out = Vec::with_capacity(block.len() / 2 + 64);
+        loop {
+            let consumed = compress.total_in() as usize;
+            let status = compress
+                .compress_vec(&block[consumed..], &mut out, flush)
+                .map_err(std::io::Error::other)?;
+            // output space left over means the flush is complete
+            let flushed = compress.total_in() as usize == block.len() && out.len() < out.capacity();
+            match status {
+                Status::StreamEnd => break,
+                _ if flushed && !last => break,
+                _ => out.reserve(DECOMPRESS_CHUNK_LEN),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Gzip compression of everything read from `reader`, any object with a synchronous `read(n)`
+    /// method returning bytes. Reads may be short, and an empty result marks the end of the data;
+    /// a read returning `None`, as a non-blocking stream does when no data is available yet, raises
+    /// `BlockingIOError`, so `read` should block until it has data.
+    ///
+    /// Coroutine based readers, ie. an `asyncio.StreamReader`, can't be awaited from here; instead run
+    /// this in a worker thread with a synchronous shim submitting each read to the event loop.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open('data.txt', 'rb') as f:
+    /// ...     cramjam.gzip.compress_from_sync_reader(f, level=2)  # Level defaults to 6
+    /// >>>
+    /// >>> class SyncShim:
+    /// ...     def __init__(self, stream, loop):
+    /// ...         self.stream, self.loop = stream, loop
+    /// ...     def read(self, n):
+    /// ...         return asyncio.run_coroutine_threadsafe(self.stream.read(n), self.loop).result()
+    /// >>>
+    /// >>> shim = SyncShim(stream_reader, asyncio.get_running_loop())
+    /// >>> compressed = await asyncio.to_thread(cramjam.gzip.compress_from_sync_reader, shim)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (reader, level=None))]
+    pub fn compress_from_sync_reader(py: Python, reader: PyObject, level: Option<u32>) -> PyResult<RustyBuffer> {
+        let mut reader = PythonReader::new(reader);
+        let mut output = vec![];
+        py.allow_threads(|| libcramjam::gzip::compress(&mut reader, &mut output, level))
+            .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(output))
+    }
+
+    /// Gzip decompression from `reader`, an object with a synchronous `read(n)` method, to `writer`,
+    /// an object with a `write(b)` method, ie. two file objects; returns the number of bytes written.
+    ///
+    /// Data is streamed through in fixed size chunks, so memory use is constant whatever the size of
+    /// the input, making this suitable for multi-GB files. Concatenated members are all decompressed,
+    /// as with `decompress`. The GIL is released between calls to `read` and `write`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open('data.txt.gz', 'rb') as src, open('data.txt', 'wb') as dst:
+    /// ...     cramjam.gzip.decompress_streaming(src, dst)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_streaming(py: Python, reader: PyObject, writer: PyObject) -> PyResult<usize> {
+        let reader = std::io::BufReader::with_capacity(DECOMPRESS_CHUNK_LEN, PythonReader::new(reader));
+        let mut writer = std::io::BufWriter::with_capacity(DECOMPRESS_CHUNK_LEN, PythonWriter::new(writer));
+        py.allow_threads(|| -> std::io::Result<usize> {
+            let mut decoder = libcramjam::gzip::flate2::bufread::MultiGzDecoder::new(reader);
+            let n_bytes = crate::io::copy(&mut decoder, &mut writer)?;
+            writer.flush()?;
+            Ok(n_bytes as usize)
+        })
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// Decompress gzip `data` a window at a time, calling `callback` with each line, (as bytes, without
+    /// its trailing `\n`) and returning the number of lines; so a huge compressed log can be scanned
+    /// without ever holding all of it decompressed. A final line without a trailing `\n` is also passed.
+    ///
+    /// Memory is bounded by the window and the longest line; with `max_line_len`, lines longer than
+    /// that are truncated to their first `max_line_len` bytes, bounding it whatever the data. Exceptions
+    /// raised by `callback` stop the scan and are raised as-is.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> matches = []
+    /// >>> def on_line(line):
+    /// ...     if b'ERROR' in line:
+    /// ...         matches.append(line)
+    /// >>> n_lines = cramjam.gzip.scan_lines(compressed_log, on_line, max_line_len=1 << 20)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, callback, max_line_len=None))]
+    pub fn scan_lines(
+        py: Python,
+        data: BytesType,
+        callback: &Bound<'_, PyAny>,
+        max_line_len: Option<usize>,
+    ) -> PyResult<usize> {
+        let max_line_len = max_line_len.unwrap_or(usize::MAX);
+        // a File is read as it's decoded, through its own handle, rather than read whole up front
+        let reader: Box<dyn Read + Send + '_> = match &data {
+            BytesType::RustyFile(file) => Box::new(file.borrow().inner.try_clone()?),
+            _ => Box::new(data.as_bytes()),
+        };
+        let mut decoder = libcramjam::gzip::flate2::read::MultiGzDecoder::new(reader);
+        let mut window = vec![0; DECOMPRESS_CHUNK_LEN];
+        let mut carry = vec![];
+        let mut n_lines = 0;
+        loop {
+            let n_bytes = py
+                .allow_threads(|| decoder.read(&mut window))
+                .map_err(DecompressionError::from_err)?;
+            if n_bytes == 0 {
+                break;
+            }
+            let mut rest = &window[..n_bytes];
+            while let Some(end) = rest.iter().position(|b| *b == b'\n') {
+                extend_line(&mut carry, &rest[..end], max_line_len);
+                callback.call1((PyBytes::new_bound(py, &carry),))?;
+                n_lines += 1;
+                carry.clear();
+                rest = &rest[end + 1..];
+            }
+            // the rest of a line continuing into the next window
+            extend_line(&mut carry, rest, max_line_len);
+        }
+        // the data ends without a newline
+        if !carry.is_empty() {
+            callback.call1((PyBytes::new_bound(py, &carry),))?;
+            n_lines += 1;
+        }
+        Ok(n_lines)
+    }
+
+    /// Append `bytes` to `line`, truncating it at `max_len` bytes
+    fn extend_line(line: &mut Vec<u8>, bytes: &[u8], max_len: usize) {
+        let n_bytes = bytes.len().min(max_len.saturating_sub(line.len()));
+        line.extend_from_slice(&bytes[..n_bytes]);
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::gzip::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
+    /// Worst case length of `compress_into` output for `n` bytes of input, for sizing its output
+    /// buffer: the deflate bound, see `cramjam.deflate.compress_bound`, plus the 10 byte header and
+    /// 8 byte trailer. An upper limit, not the actual compressed length, which is usually far less.
+    /// Raises `OverflowError` if the bound doesn't fit in a `usize`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> output = bytearray(cramjam.gzip.compress_bound(len(data)))
+    /// >>> n_bytes = cramjam.gzip.compress_into(data, output)
+    /// ```
+    #[pyfunction]
+    pub fn compress_bound(n: usize) -> PyResult<usize> {
+        crate::deflate::deflate::deflate_bound(n)?
+            .checked_add(HEADER_LEN + TRAILER_LEN)
+            .ok_or_else(|| PyOverflowError::new_err(format!("compress bound of {} bytes overflows", n)))
+    }
+
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
         crate::generic!(py, libcramjam::gzip::decompress[input, output]).map_err(DecompressionError::from_err)
     }
 
+    /// Iterate over the members of a multi-member gzip stream, yielding each member's decompressed data.
+    /// The GIL is released while decoding each member, and control returns to Python between members,
+    /// so other threads, (ie. the asyncio event loop when iterating from a thread) can run in between.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for member in cramjam.gzip.decompress_frames(compressed_bytes):
+    /// ...     process(member)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_frames(mut data: BytesType) -> PyResult<Frames> {
+        let mut buf = vec![];
+        data.read_to_end(&mut buf)?;
+        Ok(Frames { data: buf, pos: 0 })
+    }
+
+    /// Compressed length in bytes of the gzip member at the start of `data`, up to and including its
+    /// trailer; ie. to index members embedded among other data. Deflate data doesn't record its
+    /// length, so the member is decompressed, discarding the output, to find its end. A
+    /// `DecompressionError` is raised if the member is invalid or incomplete. A `File` is read and
+    /// left at the same position.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.frame_compressed_size(compressed_bytes + b'trailing data')
+    /// ```
+    #[pyfunction]
+    pub fn frame_compressed_size(py: Python, data: BytesType) -> PyResult<usize> {
+        let bytes = &*data.peek(usize::MAX)?;
+        py.allow_threads(|| member_len(bytes))
+            .map_err(DecompressionError::from_err)
+    }
+
+    /// Header fields of the gzip member at the start of `data`, as a dict of `filename`, `mtime`,
+    /// `comment` and `os`, the operating system byte; see `compress`. Fields absent from the header are
+    /// `None`, as is an `mtime` of `0`, which the format defines as no time recorded. Text fields are
+    /// decoded as latin-1. A `DecompressionError` is raised if the header is invalid or incomplete. A
+    /// `File` is peeked at and left at the same position.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.read_header(cramjam.gzip.compress(b'some bytes here', filename='data.txt'))
+    /// {'filename': 'data.txt', 'mtime': None, 'comment': None, 'os': 255}
+    /// ```
+    #[pyfunction]
+    pub fn read_header<'py>(py: Python<'py>, data: BytesType) -> PyResult<Bound<'py, PyDict>> {
+        let header = match &data {
+            // the header is of no fixed length, so a File is read only as far as it through a buffer
+            BytesType::RustyFile(file) => {
+                let mut inner = file.borrow().inner.try_clone()?;
+                let pos = inner.stream_position()?;
+                let decoder =
+                    libcramjam::gzip::flate2::bufread::MultiGzDecoder::new(std::io::BufReader::new(&mut inner));
+                let header = decoder.header().cloned();
+                inner.seek(SeekFrom::Start(pos))?;
+                header
+            }
+            _ => libcramjam::gzip::flate2::bufread::MultiGzDecoder::new(data.as_bytes())
+                .header()
+                .cloned(),
+        };
+        let header = header.ok_or_else(|| DecompressionError::new_err("Invalid or incomplete gzip header"))?;
+        let decode = |field: Option<&[u8]>| field.map(|v| v.iter().map(|b| *b as char).collect::<String>());
+        let fields = PyDict::new_bound(py);
+        fields.set_item("filename", decode(header.filename()))?;
+        fields.set_item("mtime", Some(header.mtime()).filter(|mtime| *mtime != 0))?;
+        fields.set_item("comment", decode(header.comment()))?;
+        fields.set_item("os", header.operating_system())?;
+        Ok(fields)
+    }
+
+    /// Index of the members of a multi-member gzip stream, as a list of `(compressed_offset,
+    /// compressed_len, uncompressed_len)` tuples, one per member in order; ie. for a catalog to
+    /// later decompress single members by slicing `data`.
+    ///
+    /// As with `frame_compressed_size`, each member is decompressed to find its end, but the output is
+    /// discarded rather than held. Uncompressed lengths are read from the members' ISIZE trailer field,
+    /// which records them modulo 2^32. A `DecompressionError` is raised if any member is invalid or incomplete.
+    /// A `File` is read and left at the same position, with offsets relative to it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for offset, length, size in cramjam.gzip.member_index(compressed_bytes):
+    /// ...     catalog.append((offset, length, size))
+    /// ```
+    #[pyfunction]
+    pub fn member_index(py: Python, data: BytesType) -> PyResult<Vec<(usize, usize, u32)>> {
+        let bytes = &*data.peek(usize::MAX)?;
+        py.allow_threads(|| -> std::io::Result<_> {
+            let mut index = vec![];
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let len = member_len(&bytes[offset..])?;
+                let trailer = &bytes[offset + len - 4..offset + len];
+                index.push((offset, len, u32::from_le_bytes(trailer.try_into().unwrap())));
+                offset += len;
+            }
+            Ok(index)
+        })
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// Decompress only the member at `index` of a multi-member gzip stream, raising `IndexError` if
+    /// there are too few members.
+    ///
+    /// Finding a member's offset takes decoding those before it, with their output discarded; pass
+    /// `members`, a `member_index` of `data` kept from before, to go straight to the member instead.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.decompress_member(compressed_bytes, 1)
+    /// >>> cramjam.gzip.decompress_member(compressed_bytes, 1, members=catalog)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, index, members=None))]
+    pub fn decompress_member(
+        py: Python,
+        data: BytesType,
+        index: usize,
+        members: Option<Vec<(usize, usize, u32)>>,
+    ) -> PyResult<RustyBuffer> {
+        let bytes = &*data.read_bytes()?;
+        let member = match members {
+            Some(members) => {
+                let (offset, len, _) = *members.get(index).ok_or_else(|| {
+                    PyIndexError::new_err(format!("Member {} out of range of {} members", index, members.len()))
+                })?;
+                bytes
+                    .get(offset..offset.saturating_add(len))
+                    .ok_or_else(|| DecompressionError::new_err(format!("Member {} lies outside of data", index)))?
+            }
+            None => {
+                let offset = py
+                    .allow_threads(|| -> std::io::Result<_> {
+                        let mut offset = 0;
+                        for n_members in 0..index {
+                            if offset >= bytes.len() {
+                                return Ok(Err(n_members));
+                            }
+                            offset += member_len(&bytes[offset..])?;
+                        }
+                        Ok(if offset < bytes.len() { Ok(offset) } else { Err(index) })
+                    })
+                    .map_err(DecompressionError::from_err)?
+                    .map_err(|n_members| {
+                        PyIndexError::new_err(format!("Member {} out of range of {} members", index, n_members))
+                    })?;
+                &bytes[offset..]
+            }
+        };
+        py.allow_threads(|| {
+            let mut decoder = libcramjam::gzip::flate2::bufread::GzDecoder::new(member);
+            let mut out = vec![];
+            decoder.read_to_end(&mut out).map(|_| out)
+        })
+        .map(RustyBuffer::from)
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// Compressed length of the gzip member at the start of `data`, see `frame_compressed_size`
+    fn member_len(data: &[u8]) -> std::io::Result<usize> {
+        let mut decoder = libcramjam::gzip::flate2::bufread::GzDecoder::new(data);
+        crate::io::copy(&mut decoder, &mut std::io::sink())?;
+        Ok(data.len() - decoder.into_inner().len())
+    }
+
+    /// Iterator of decompressed members, see `decompress_frames`
+    #[pyclass]
+    pub struct Frames {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    #[pymethods]
+    impl Frames {
+        fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            let remaining = &self.data[self.pos..];
+            if remaining.is_empty() {
+                return Ok(None);
+            }
+            let (out, rest) = py
+                .allow_threads(|| {
+                    // bufread decoder consumes exactly the member's bytes, leaving the rest in the slice
+                    let mut decoder = libcramjam::gzip::flate2::bufread::GzDecoder::new(remaining);
+                    let mut out = vec![];
+                    decoder.read_to_end(&mut out).map(|_| (out, decoder.into_inner().len()))
+                })
+                .map_err(DecompressionError::from_err)?;
+            if rest == remaining.len() {
+                return Err(DecompressionError::new_err(format!(
+                    "Invalid gzip member at offset {}",
+                    self.pos
+                )));
+            }
+            self.pos = self.data.len() - rest;
+            Ok(Some(RustyBuffer::from(out)))
+        }
+    }
+
     /// GZIP Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
@@ -68,15 +799,18 @@ pub mod gzip {
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, with optional header fields, see `compress`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, filename=None, mtime=None, comment=None))]
+        pub fn __init__(
+            level: Option<u32>,
+            filename: Option<&str>,
+            mtime: Option<u32>,
+            comment: Option<&str>,
+        ) -> PyResult<Self> {
             let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
-            let inner = libcramjam::gzip::flate2::write::GzEncoder::new(
-                Cursor::new(vec![]),
-                libcramjam::gzip::flate2::Compression::new(level),
-            );
+            let inner = header_builder(filename, comment, mtime)?
+                .write(Cursor::new(vec![]), libcramjam::gzip::flate2::Compression::new(level));
             Ok(Self { inner: Some(inner) })
         }
 
@@ -85,11 +819,27 @@ pub mod gzip {
             crate::io::stream_compress(&mut self.inner, input)
         }
 
-        /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+        /// Flush and return current compressed stream.
+        ///
+        /// `mode` may only be "sync", zlib's Z_SYNC_FLUSH, aligning to a byte boundary with an
+        /// empty stored block; see `cramjam.deflate.Compressor` for the full and partial flushes.
+        #[pyo3(signature = (mode=None))]
+        pub fn flush(&mut self, mode: Option<&str>) -> PyResult<RustyBuffer> {
+            if let Some(other) = mode.filter(|mode| !FLUSH_MODES.contains(mode)) {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown flush mode '{}', expected one of {:?}",
+                    other, FLUSH_MODES
+                )));
+            }
             crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            FLUSH_MODES.to_vec()
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -97,10 +847,131 @@ pub mod gzip {
         }
     }
 
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(gzip, u32, 0, 9);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            gzip, level: u32;
+            checked, bench, strict(b"\x1f\x8b"), prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compressed_size, decompress_bench, decompress_checked, decompress_prefix,
+        decompress_records, decompress_strict,
+    };
+
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(gzip);
+        crate::make_decompressor!(gzip, decoder = MemberDecoder::default());
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    #[derive(Default)]
+    enum Stage {
+        #[default]
+        Header,
+        Body(Decompress),
+        Trailer,
+    }
+
+    /// Incremental decoder of gzip members, holding back input until there's enough to make progress.
+    /// Each member's CRC32 is accumulated as its output is produced, and checked as soon as its
+    /// trailer arrives; a mismatch is raised from the `Decompressor.decompress` call feeding it.
+    #[derive(Default)]
+    struct MemberDecoder {
+        pending: Vec<u8>,
+        stage: Stage,
+        crc: Crc,
+    }
+
+    impl MemberDecoder {
+        /// Whether the input so far ends part way through a member
+        fn in_member(&self) -> bool {
+            !matches!(self.stage, Stage::Header) || !self.pending.is_empty()
+        }
+    }
+
+    impl IncrementalDecoder for MemberDecoder {
+        fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+            self.pending.extend_from_slice(input);
+            let (start, mut pos) = (output.len(), 0);
+            loop {
+                let remaining = &self.pending[pos..];
+                match &mut self.stage {
+                    Stage::Header => {
+                        if remaining.len() >= 3 && remaining[..3] != [0x1f, 0x8b, 0x08] {
+                            return Err(invalid("Invalid gzip header".to_string()));
+                        }
+                        match header_len(remaining) {
+                            Some(n_bytes) => {
+                                pos += n_bytes;
+                                self.stage = Stage::Body(Decompress::new(false));
+                                self.crc.reset();
+                            }
+                            None => break,
+                        }
+                    }
+                    Stage::Body(inflate) => {
+                        let (total_in, produced) = (inflate.total_in(), output.len());
+                        output.reserve(DECOMPRESS_CHUNK_LEN);
+                        let status = inflate
+                            .decompress_vec(remaining, output, FlushDecompress::None)
+                            .map_err(|e| invalid(e.to_string()))?;
+                        pos += (inflate.total_in() - total_in) as usize;
+                        self.crc.update(&output[produced..]);
+                        crate::io::check_output_len(output.len() - start, max_len)?;
+                        if status == Status::StreamEnd {
+                            self.stage = Stage::Trailer;
+                        } else if inflate.total_in() == total_in && output.len() == produced {
+                            break;
+                        }
+                    }
+                    Stage::Trailer => {
+                        if remaining.len() < 8 {
+                            break;
+                        }
+                        let expected = u32::from_le_bytes(remaining[..4].try_into().unwrap());
+                        if self.crc.sum() != expected {
+                            return Err(invalid(format!(
+                                "CRC32 mismatch, expected {:#010x} but decompressed data has {:#010x}",
+                                expected,
+                                self.crc.sum()
+                            )));
+                        }
+                        let isize = u32::from_le_bytes(remaining[4..8].try_into().unwrap());
+                        if isize != self.crc.amount() {
+                            return Err(invalid(format!(
+                                "Length mismatch, expected {} bytes but decompressed {}",
+                                isize,
+                                self.crc.amount()
+                            )));
+                        }
+                        pos += 8;
+                        self.stage = Stage::Header;
+                    }
+                }
+            }
+            self.pending.drain(..pos);
+            Ok(())
+        }
+
+        fn finish(&mut self, _output: &mut Vec<u8>, _max_len: Option<usize>) -> std::io::Result<()> {
+            if self.in_member() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Input ended part way through a gzip member",
+                ));
+            }
+            Ok(())
+        }
+    }
 }
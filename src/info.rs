@@ -0,0 +1,85 @@
+//! Introspection of the build, for comparing performance across deployments.
+use libcramjam::zstd::zstd::zstd_safe::{CCtx, CParameter};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Backend of flate2, used by gzip/deflate streaming and decompression; zlib-rs, a pure Rust port of
+/// zlib, selected by the default `deflate-dictionary` feature, or without it flate2's own default.
+#[cfg(not(feature = "deflate-dictionary"))]
+const FLATE2_BACKEND: &str = "miniz_oxide";
+#[cfg(feature = "deflate-dictionary")]
+const FLATE2_BACKEND: &str = "zlib-rs";
+
+/// Information on this build of cramjam and the accelerations active at runtime:
+/// ```bash
+/// version: cramjam's version
+/// flate2_backend: deflate implementation behind gzip/deflate streaming and decompression
+/// zstd_version: version of the bundled libzstd
+/// zstd_multithreading: whether libzstd was built with multithreaded compression
+/// target_arch: CPU architecture this build targets, ie. "x86_64"
+/// cpu_features: codec relevant CPU features, (SIMD, CRC and carry-less multiply instructions) detected at runtime
+/// ```
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.build_info()
+/// {'version': '2.8.4', 'flate2_backend': 'miniz_oxide', 'zstd_version': '1.5.6', ...}
+/// ```
+#[pyfunction]
+pub fn build_info<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+    let info = PyDict::new_bound(py);
+    info.set_item("version", env!("CARGO_PKG_VERSION"))?;
+    info.set_item("flate2_backend", FLATE2_BACKEND)?;
+    info.set_item("zstd_version", libcramjam::zstd::zstd::zstd_safe::version_string())?;
+    // libzstd rejects any worker count unless built with ZSTD_MULTITHREAD
+    let zstd_multithreading = CCtx::create().set_parameter(CParameter::NbWorkers(1)).is_ok();
+    info.set_item("zstd_multithreading", zstd_multithreading)?;
+    info.set_item("target_arch", std::env::consts::ARCH)?;
+    info.set_item("cpu_features", cpu_features())?;
+    Ok(info)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    macro_rules! detect {
+        ($($feature:tt),+) => {
+            $(if std::arch::is_x86_feature_detected!($feature) {
+                features.push($feature);
+            })+
+        };
+    }
+    detect!(
+        "sse2",
+        "ssse3",
+        "sse4.1",
+        "sse4.2",
+        "pclmulqdq",
+        "avx",
+        "avx2",
+        "bmi1",
+        "bmi2",
+        "avx512f"
+    );
+    features
+}
+
+#[cfg(target_arch = "aarch64")]
+fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    macro_rules! detect {
+        ($($feature:tt),+) => {
+            $(if std::arch::is_aarch64_feature_detected!($feature) {
+                features.push($feature);
+            })+
+        };
+    }
+    detect!("neon", "crc", "aes", "sha2", "sve");
+    features
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn cpu_features() -> Vec<&'static str> {
+    vec![]
+}
@@ -4,17 +4,42 @@
 //!
 use std::convert::TryFrom;
 use std::fs::{File, OpenOptions};
-use std::io::{copy, Cursor, Read, Seek, SeekFrom, Write};
+use std::io::{BufRead, Cursor, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::os::raw::c_int;
 
-use crate::exceptions::CompressionError;
+use crate::exceptions::{CompressionError, DecompressionError};
 use crate::BytesType;
 use pyo3::exceptions::{self, PyBufferError};
 use pyo3::ffi;
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PySlice};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Number of bytes read between checks for pending Python signals in `SignalCheckedReader`
+const SIGNAL_CHECK_INTERVAL: usize = 1 << 20;
+
+/// Most bytes of allocations the pool of `release_buffer` retains
+const BUFFER_POOL_MAX_BYTES: usize = 64 << 20;
+
+/// Allocations returned by `release_buffer`, handed out again by `acquire_buffer`
+static BUFFER_POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// Buffer size of `std::io::copy`, and so initially of `copy`
+const DEFAULT_COPY_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Largest buffer size `set_copy_buffer_size` accepts
+const MAX_COPY_BUFFER_SIZE: usize = 16 << 20;
+
+/// Buffer size of `copy`, set by `set_copy_buffer_size`
+static COPY_BUFFER_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_COPY_BUFFER_SIZE);
+
+thread_local! {
+    /// Buffer `copy` reuses across calls on a thread, once it's larger than the default
+    static COPY_BUFFER: std::cell::RefCell<Vec<u8>> = const { std::cell::RefCell::new(Vec::new()) };
+}
 
 pub(crate) trait AsBytes {
     fn as_bytes(&self) -> &[u8];
@@ -176,11 +201,13 @@ impl RustyFile {
 pub struct PythonBuffer {
     pub(crate) inner: std::pin::Pin<Box<ffi::Py_buffer>>,
     pub(crate) pos: usize,
+    /// Whether it may be written to as an output, ie. the exporter didn't mark the view readonly
+    pub(crate) writable: bool,
     #[cfg(PyPy)]
     pub(crate) owner: PyObject,
 }
-// PyBuffer is thread-safe: the shape of the buffer is immutable while a Py_buffer exists.
-// Accessing the buffer contents is protected using the GIL.
+// PyBuffer is thread-safe: the shape of the buffer is immutable while a Py_buffer exists, and
+// its memory can't be resized or freed; so the contents may be accessed without the GIL, see `generic!`.
 unsafe impl Send for PythonBuffer {}
 unsafe impl Sync for PythonBuffer {}
 
@@ -220,6 +247,9 @@ impl PythonBuffer {
                 }
             })?;
         }
+        if !self.writable {
+            return Err(PyBufferError::new_err("Output buffer is readonly"));
+        }
         Ok(unsafe { std::slice::from_raw_parts_mut(self.buf_ptr() as *mut u8, self.len_bytes()) })
     }
     /// If underlying buffer is c_contiguous
@@ -271,9 +301,11 @@ impl<'a, 'py> TryFrom<&'a Bound<'py, PyAny>> for PythonBuffer {
             ));
         }
         let buf = Box::new(unsafe { mem::MaybeUninit::<ffi::Py_buffer>::assume_init(*buf) });
+        let writable = buf.readonly == 0;
         let buf = Self {
             inner: std::pin::Pin::from(buf),
             pos: 0,
+            writable,
             #[cfg(PyPy)]
             owner: Python::with_gil(|py| obj.to_object(py)),
         };
@@ -304,9 +336,7 @@ impl Read for PythonBuffer {
 impl Write for PythonBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let pos = self.position();
-        let slice = self
-            .as_slice_mut()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let slice = self.as_slice_mut().map_err(|e| std::io::Error::other(e.to_string()))?;
         let len = slice.len();
 
         if pos < slice.len() {
@@ -340,6 +370,21 @@ impl Write for PythonBuffer {
 #[derive(Default)]
 pub struct RustyBuffer {
     pub(crate) inner: Cursor<Vec<u8>>,
+    // views exported through the buffer protocol, which point into `inner`'s allocation
+    exports: usize,
+}
+
+impl RustyBuffer {
+    /// Raise `BufferError` if views of the buffer are exported through the buffer protocol; changing
+    /// the buffer's length or allocation would then leave them pointing to moved or freed memory.
+    pub(crate) fn check_not_exported(&self) -> PyResult<()> {
+        match self.exports {
+            0 => Ok(()),
+            _ => Err(PyBufferError::new_err(
+                "Existing exports of data: Buffer can't be modified while views of it exist",
+            )),
+        }
+    }
 }
 
 impl AsBytes for RustyBuffer {
@@ -354,7 +399,10 @@ impl AsBytes for RustyBuffer {
 
 impl From<Vec<u8>> for RustyBuffer {
     fn from(v: Vec<u8>) -> Self {
-        Self { inner: Cursor::new(v) }
+        Self {
+            inner: Cursor::new(v),
+            exports: 0,
+        }
     }
 }
 
@@ -377,9 +425,20 @@ impl RustyBuffer {
         if let Some(bytes) = data.as_mut() {
             bytes.read_to_end(&mut buf)?;
         }
-        Ok(Self {
-            inner: Cursor::new(buf),
-        })
+        Ok(Self::from(buf))
+    }
+
+    /// Instantiate an empty buffer with room for at least `capacity` bytes, so writing up to that
+    /// many bytes, (ie. `decompress_into` with a known decompressed size) won't reallocate.
+    ///
+    /// ### Python Example
+    /// ```python
+    /// >>> buffer = cramjam.Buffer.with_capacity(decompressed_size)
+    /// >>> cramjam.gzip.decompress_into(compressed, buffer)
+    /// ```
+    #[staticmethod]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::from(Vec::with_capacity(capacity))
     }
 
     /// Length of the underlying buffer
@@ -387,6 +446,11 @@ impl RustyBuffer {
         self.inner.get_ref().len()
     }
 
+    /// Number of bytes the underlying buffer can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.inner.get_ref().capacity()
+    }
+
     /// Write some bytes to the buffer, where input data can be anything in [BytesType](../enum.BytesType.html)
     pub fn write(&mut self, mut input: BytesType) -> PyResult<usize> {
         let r = write(&mut input, self)?;
@@ -398,9 +462,23 @@ impl RustyBuffer {
         read(self, py, n_bytes)
     }
     /// Read from the buffer in its current position, into a [BytesType](../enum.BytesType.html) object.
+    ///
+    /// A fixed size target, ie. `bytearray` or `memoryview`, gets at most `len(output)` bytes and the
+    /// position advances by as many, so repeated calls walk the buffer in pieces; `0` is returned
+    /// once the position is at or past the end. A `File` or `Buffer` target grows to take
+    /// everything from the current position on.
     pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
-        let r = copy(self, &mut output)?;
-        Ok(r as usize)
+        match output {
+            BytesType::PyBuffer(ref mut out) => {
+                let n_bytes = out.write(self.inner.fill_buf()?)?;
+                self.inner.consume(n_bytes);
+                Ok(n_bytes)
+            }
+            _ => {
+                let r = copy(self, &mut output)?;
+                Ok(r as usize)
+            }
+        }
     }
     /// Seek to a position within the buffer. whence follows the same values as IOBase.seek where:
     /// ```bash
@@ -434,11 +512,13 @@ impl RustyBuffer {
     /// Set the length of the buffer. If less than current length, it will truncate to the size given;
     /// otherwise will be null byte filled to the size.
     pub fn set_len(&mut self, size: usize) -> PyResult<()> {
+        self.check_not_exported()?;
         self.inner.get_mut().resize(size, 0);
         Ok(())
     }
     /// Truncate the buffer
     pub fn truncate(&mut self) -> PyResult<()> {
+        self.check_not_exported()?;
         self.inner.get_mut().truncate(0);
         self.inner.set_position(0);
         Ok(())
@@ -447,6 +527,29 @@ impl RustyBuffer {
     fn __len__(&self) -> usize {
         self.len()
     }
+    /// A byte as an `int` for an integer index, or a copy of a `slice` as `bytes`; negative
+    /// indices count from the end, as for `bytes`. The position isn't moved.
+    fn __getitem__(&self, py: Python, index: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let bytes = self.inner.get_ref();
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let indices = slice.indices(bytes.len() as isize)?;
+            if indices.step == 1 {
+                let start = indices.start as usize;
+                return Ok(PyBytes::new_bound(py, &bytes[start..start + indices.slicelength]).into_py(py));
+            }
+            let out = (0..indices.slicelength)
+                .map(|i| bytes[(indices.start + i as isize * indices.step) as usize])
+                .collect::<Vec<u8>>();
+            return Ok(PyBytes::new_bound(py, &out).into_py(py));
+        }
+        let i = index.extract::<isize>()?;
+        let wrapped = if i < 0 { i + bytes.len() as isize } else { i };
+        usize::try_from(wrapped)
+            .ok()
+            .and_then(|i| bytes.get(i))
+            .map(|byte| byte.into_py(py))
+            .ok_or_else(|| exceptions::PyIndexError::new_err("Buffer index out of range"))
+    }
     fn __contains__(&self, py: Python, x: BytesType) -> bool {
         let bytes = x.as_bytes();
         py.allow_threads(|| self.inner.get_ref().windows(bytes.len()).any(|w| w == bytes))
@@ -460,7 +563,7 @@ impl RustyBuffer {
     fn __bool__(&self) -> bool {
         self.len() > 0
     }
-    unsafe fn __getbuffer__(slf: PyRefMut<Self>, view: *mut ffi::Py_buffer, flags: c_int) -> PyResult<()> {
+    unsafe fn __getbuffer__(mut slf: PyRefMut<Self>, view: *mut ffi::Py_buffer, flags: c_int) -> PyResult<()> {
         if view.is_null() {
             return Err(pyo3::exceptions::PyBufferError::new_err("View is null"));
         }
@@ -476,7 +579,7 @@ impl RustyBuffer {
 
         (*view).buf = bytes.as_ptr() as *mut std::os::raw::c_void;
         (*view).len = bytes.len() as isize;
-        (*view).readonly = 0;
+        (*view).readonly = 1;
         (*view).itemsize = 1;
 
         (*view).format = std::ptr::null_mut();
@@ -498,9 +601,12 @@ impl RustyBuffer {
 
         (*view).suboffsets = std::ptr::null_mut();
         (*view).internal = std::ptr::null_mut();
+        slf.exports += 1;
         Ok(())
     }
-    unsafe fn __releasebuffer__(&self, _view: *mut ffi::Py_buffer) {}
+    unsafe fn __releasebuffer__(&mut self, _view: *mut ffi::Py_buffer) {
+        self.exports -= 1;
+    }
 }
 
 fn write<W: Write>(input: &mut BytesType, output: &mut W) -> std::io::Result<u64> {
@@ -550,6 +656,7 @@ impl Seek for PythonBuffer {
 }
 impl Write for RustyBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.check_not_exported().map_err(std::io::Error::other)?;
         self.inner.write(buf)
     }
     fn flush(&mut self) -> std::io::Result<()> {
@@ -575,12 +682,460 @@ impl Read for RustyFile {
     }
 }
 
+/// Reader which periodically re-acquires the GIL to run Python's signal handlers, so long running
+/// de/compression done without the GIL can still be interrupted, ie. by `KeyboardInterrupt`.
+/// The raised Python exception is carried in the returned `io::Error`.
+pub(crate) struct SignalCheckedReader<R> {
+    inner: R,
+    unchecked: usize,
+    consumed: usize,
+}
+
+impl<R: Read> SignalCheckedReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            unchecked: 0,
+            consumed: 0,
+        }
+    }
+
+    /// Total bytes read from the inner reader
+    pub(crate) fn consumed(&self) -> usize {
+        self.consumed
+    }
+}
+
+impl<R: Read> Read for SignalCheckedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.unchecked >= SIGNAL_CHECK_INTERVAL {
+            self.unchecked = 0;
+            Python::with_gil(|py| py.check_signals()).map_err(std::io::Error::other)?;
+        }
+        let n_bytes = self.inner.read(buf)?;
+        self.unchecked += n_bytes;
+        self.consumed += n_bytes;
+        Ok(n_bytes)
+    }
+}
+
+/// Reader over a Python object's `read(n)` method, (ie. a file object, or a synchronous shim over an
+/// async stream) re-acquiring the GIL for each call. `read` may return fewer bytes than asked for,
+/// and empty bytes mark EOF. `None`, as returned by a non-blocking stream with no data available yet,
+/// fails the read with `WouldBlock`, raised as `BlockingIOError`, rather than waiting on the stream.
+/// Exceptions raised by `read` are carried in the returned `io::Error`.
+pub(crate) struct PythonReader {
+    inner: Py<PyAny>,
+    // bytes returned beyond what was asked for, handed out by the following reads
+    pending: Vec<u8>,
+}
+
+impl PythonReader {
+    pub(crate) fn new(inner: Py<PyAny>) -> Self {
+        Self { inner, pending: vec![] }
+    }
+}
+
+impl Read for PythonReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            let chunk = Python::with_gil(|py| -> PyResult<Option<Vec<u8>>> {
+                py.check_signals()?;
+                let chunk = self.inner.call_method1(py, "read", (buf.len(),))?;
+                if chunk.is_none(py) {
+                    return Ok(None);
+                }
+                let chunk = chunk.extract::<BytesType>(py)?;
+                Ok(Some(chunk.as_bytes().to_vec()))
+            })
+            .map_err(std::io::Error::other)?;
+            self.pending = chunk.ok_or_else(|| {
+                let err = exceptions::PyBlockingIOError::new_err("read() returned None, no data is available yet");
+                std::io::Error::new(std::io::ErrorKind::WouldBlock, err)
+            })?;
+        }
+        let n_bytes = self.pending.len().min(buf.len());
+        buf[..n_bytes].copy_from_slice(&self.pending[..n_bytes]);
+        self.pending.drain(..n_bytes);
+        Ok(n_bytes)
+    }
+}
+
+/// Writer over a Python object's `write(b)` method, (ie. a file object) re-acquiring the GIL for each
+/// call. Short writes are continued from where they stopped, and a `write` returning `None`, as is
+/// common of sinks which aren't `io` streams, took all of `b`, as with `io.BufferedWriter`. One which
+/// accepts nothing fails with `WriteZero`. `flush` calls the object's `flush`, if it has one.
+/// Exceptions raised by the object are carried in the returned `io::Error`.
+pub(crate) struct PythonWriter {
+    inner: Py<PyAny>,
+}
+
+impl PythonWriter {
+    pub(crate) fn new(inner: Py<PyAny>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Write for PythonWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let written = Python::with_gil(|py| -> PyResult<usize> {
+            py.check_signals()?;
+            let written = self.inner.call_method1(py, "write", (PyBytes::new_bound(py, buf),))?;
+            match written.is_none(py) {
+                true => Ok(buf.len()),
+                false => written.extract::<usize>(py),
+            }
+        })
+        .map_err(std::io::Error::other)?;
+        match written {
+            0 => Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                "write() accepted no bytes",
+            )),
+            n => Ok(n.min(buf.len())),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Python::with_gil(|py| -> PyResult<()> {
+            if self.inner.bind(py).hasattr("flush")? {
+                self.inner.call_method0(py, "flush")?;
+            }
+            Ok(())
+        })
+        .map_err(std::io::Error::other)
+    }
+}
+
+/// Writer collecting at most `limit` bytes; once full, further writes fail, aborting the
+/// de/compression writing into it. Whether the limit was reached is checked with `is_full`.
+pub(crate) struct PrefixWriter {
+    inner: Vec<u8>,
+    limit: usize,
+}
+
+impl PrefixWriter {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(limit.min(1 << 20)),
+            limit,
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.inner.len() >= self.limit
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.inner
+    }
+}
+
+impl Write for PrefixWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.is_full() && !buf.is_empty() {
+            return Err(std::io::Error::other("Prefix limit reached"));
+        }
+        let n_bytes = buf.len().min(self.limit - self.inner.len());
+        self.inner.extend_from_slice(&buf[..n_bytes]);
+        Ok(n_bytes)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `std::io::sink()` counting the bytes written to it, for the size of output that isn't kept
+#[derive(Default)]
+pub(crate) struct CountingSink {
+    n_bytes: usize,
+}
+
+impl CountingSink {
+    pub(crate) fn n_bytes(&self) -> usize {
+        self.n_bytes
+    }
+}
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.n_bytes += buf.len();
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer failing once more than `max_len` bytes would have been written through it, so a decode
+/// stops as soon as its output crosses `max_output_len` rather than after producing all of it.
+pub(crate) struct LimitedWriter<W> {
+    inner: W,
+    written: usize,
+    max_len: Option<usize>,
+}
+
+impl<W> LimitedWriter<W> {
+    pub(crate) fn new(inner: W, max_len: Option<usize>) -> Self {
+        Self {
+            inner,
+            written: 0,
+            max_len,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Allow another `max_len` bytes through, counting from now
+    pub(crate) fn reset(&mut self, max_len: Option<usize>) {
+        self.written = 0;
+        self.max_len = max_len;
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        check_output_len(self.written + buf.len(), self.max_len)?;
+        let n_bytes = self.inner.write(buf)?;
+        self.written += n_bytes;
+        Ok(n_bytes)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// An error if `len` bytes of output exceed `max_output_len`
+pub(crate) fn check_output_len(len: usize, max_output_len: Option<usize>) -> std::io::Result<()> {
+    match max_output_len {
+        Some(max_len) if len > max_len => Err(std::io::Error::other("decompressed output exceeded max_output_len")),
+        _ => Ok(()),
+    }
+}
+
+/// Writer over a fixed size output which, once full, counts the bytes that don't fit rather than
+/// failing the write; so a too small output can be reported with the size actually needed.
+pub(crate) struct FixedOutput<'a> {
+    inner: Cursor<&'a mut [u8]>,
+    overflow: usize,
+}
+
+impl<'a> FixedOutput<'a> {
+    pub(crate) fn new(output: &'a mut [u8]) -> Self {
+        Self {
+            inner: Cursor::new(output),
+            overflow: 0,
+        }
+    }
+
+    /// `result` of writing into this output, an error if the output was too small
+    pub(crate) fn check<T>(&self, result: std::io::Result<T>) -> std::io::Result<T> {
+        let value = result?;
+        if self.overflow > 0 {
+            let len = self.inner.get_ref().len();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                format!(
+                    "Output buffer of {} bytes too small, needs at least {}",
+                    len,
+                    len + self.overflow
+                ),
+            ));
+        }
+        Ok(value)
+    }
+}
+
+impl Write for FixedOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n_bytes = self.inner.write(buf)?;
+        self.overflow += buf.len() - n_bytes;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Get an empty `Buffer` with at least `min_capacity` bytes allocated, reusing an allocation
+/// previously given back with `release_buffer` when one is large enough.
+///
+/// It's for `zstd.compress(..., scratch=buffer)`, the only function which reuses a `Buffer`'s
+/// allocation for its output, so high throughput zstd code can cycle a few buffers rather than
+/// allocate output per call; the other codecs' `compress` functions allocate new output each call.
+///
+/// ### Python Example
+/// ```python
+/// >>> buffer = cramjam.acquire_buffer(1 << 20)
+/// >>> sock.sendall(cramjam.zstd.compress(data, scratch=buffer))
+/// >>> cramjam.release_buffer(buffer)
+/// ```
+#[pyfunction]
+pub fn acquire_buffer(min_capacity: usize) -> RustyBuffer {
+    let mut pool = BUFFER_POOL.lock().unwrap_or_else(|e| e.into_inner());
+    // smallest pooled allocation which is large enough
+    let best = pool
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| v.capacity() >= min_capacity)
+        .min_by_key(|(_, v)| v.capacity())
+        .map(|(i, _)| i);
+    match best {
+        Some(i) => RustyBuffer::from(pool.swap_remove(i)),
+        None => RustyBuffer::with_capacity(min_capacity),
+    }
+}
+
+/// Give the allocation of `buffer` back to the pool of `acquire_buffer`, leaving `buffer` empty.
+/// The pool retains at most 64 MiB, beyond which released allocations are freed.
+///
+/// ### Python Example
+/// ```python
+/// >>> cramjam.release_buffer(buffer)
+/// ```
+#[pyfunction]
+pub fn release_buffer(buffer: &Bound<'_, RustyBuffer>) -> PyResult<()> {
+    buffer.try_borrow()?.check_not_exported()?;
+    let mut allocation = mem::take(buffer.try_borrow_mut()?.inner.get_mut());
+    buffer.try_borrow_mut()?.inner.set_position(0);
+    allocation.clear();
+    let mut pool = BUFFER_POOL.lock().unwrap_or_else(|e| e.into_inner());
+    let retained = pool.iter().map(|v| v.capacity()).sum::<usize>();
+    if allocation.capacity() > 0 && retained + allocation.capacity() <= BUFFER_POOL_MAX_BYTES {
+        pool.push(allocation);
+    }
+    Ok(())
+}
+
+/// Set the size in bytes of the buffer data is copied through between the readers and writers of
+/// de/compression, ie. from an encoder to the output; larger buffers mean fewer, larger writes,
+/// which may suit some workloads. It applies process wide, to all calls that follow, and defaults to
+/// 8KiB. Output is the same whatever the size. Raises `ValueError` for 0 or more than 16MiB.
+///
+/// **NB** It only applies where cramjam copies the data itself, ie. the streaming functions and the
+/// dictionary, prefix and option paths of `compress`/`decompress`. The plain calls into the codecs,
+/// ie. `cramjam.gzip.compress(data)`, still copy through `std::io::copy` and its 8KiB buffer.
+/// Each thread keeps a buffer of the set size between calls, rather than allocating one per call.
+///
+/// ### Python Example
+/// ```python
+/// >>> cramjam.set_copy_buffer_size(1 << 20)
+/// ```
+#[pyfunction]
+pub fn set_copy_buffer_size(n_bytes: usize) -> PyResult<()> {
+    if n_bytes == 0 || n_bytes > MAX_COPY_BUFFER_SIZE {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "copy buffer size must be between 1 and {} bytes",
+            MAX_COPY_BUFFER_SIZE
+        )));
+    }
+    COPY_BUFFER_SIZE.store(n_bytes, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Size in bytes of the copy buffer, see `set_copy_buffer_size`.
+#[pyfunction]
+pub fn get_copy_buffer_size() -> usize {
+    COPY_BUFFER_SIZE.load(Ordering::Relaxed)
+}
+
+/// `std::io::copy`, through a buffer of the size set by `set_copy_buffer_size`
+pub(crate) fn copy<R, W>(reader: &mut R, writer: &mut W) -> std::io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let size = get_copy_buffer_size();
+    if size == DEFAULT_COPY_BUFFER_SIZE {
+        return std::io::copy(reader, writer);
+    }
+    COPY_BUFFER.with(|buf| match buf.try_borrow_mut() {
+        Ok(mut buf) => {
+            buf.resize(size, 0);
+            copy_through(reader, writer, &mut buf)
+        }
+        // a copy within a copy, ie. from a reader calling back into cramjam
+        Err(_) => copy_through(reader, writer, &mut vec![0u8; size]),
+    })
+}
+
+fn copy_through<R, W>(reader: &mut R, writer: &mut W, buf: &mut [u8]) -> std::io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut n_bytes = 0;
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return Ok(n_bytes),
+            Ok(n) => {
+                writer.write_all(&buf[..n])?;
+                n_bytes += n as u64;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Raise `DecompressionError` if `data` is longer than `max_input_len`, before any decoding is done.
+pub(crate) fn check_input_len(data: &BytesType, max_input_len: Option<usize>) -> PyResult<()> {
+    match max_input_len {
+        Some(max_input_len) if data.len() > max_input_len => Err(DecompressionError::new_err(format!(
+            "Input of {} bytes exceeds max_input_len of {} bytes",
+            data.len(),
+            max_input_len
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Raise `DecompressionError` if `data` continues past its first frame, whose length in bytes
+/// `frame_len` finds; for `require_exact`.
+pub(crate) fn check_exact<F>(data: &BytesType, frame_len: F) -> PyResult<()>
+where
+    F: FnOnce(&[u8]) -> PyResult<usize>,
+{
+    if let BytesType::RustyFile(_) = data {
+        return Err(exceptions::PyValueError::new_err(
+            "require_exact isn't supported for File input",
+        ));
+    }
+    let frame_len = frame_len(data.as_bytes())?;
+    match data.len() - frame_len {
+        0 => Ok(()),
+        leftover => Err(DecompressionError::new_err(format!(
+            "{} bytes of input remain after the frame",
+            leftover
+        ))),
+    }
+}
+
+/// Return `buffer`, or with `return_len` a tuple of it and its length in bytes,
+/// sparing a separate `len()` call from Python.
+pub(crate) fn maybe_with_len(py: Python, buffer: RustyBuffer, return_len: bool) -> PyObject {
+    if return_len {
+        let len = buffer.len();
+        (buffer, len).into_py(py)
+    } else {
+        buffer.into_py(py)
+    }
+}
+
 // general stream compression interface. Can't use associated types due to pyo3::pyclass
 // not supporting generic structs.
 #[inline(always)]
 pub(crate) fn stream_compress<W: Write>(encoder: &mut Option<W>, input: &[u8]) -> PyResult<usize> {
     match encoder {
-        Some(encoder) => std::io::copy(&mut Cursor::new(input), encoder)
+        Some(encoder) => copy(&mut Cursor::new(input), encoder)
             .map(|v| v as usize)
             .map_err(CompressionError::from_err),
         None => Err(CompressionError::new_err(
@@ -596,7 +1151,7 @@ pub(crate) fn stream_compress<W: Write>(encoder: &mut Option<W>, input: &[u8]) -
 pub(crate) fn stream_finish<W, F, E>(encoder: &mut Option<W>, into_vec: F) -> PyResult<RustyBuffer>
 where
     W: Write,
-    E: ToString,
+    E: ToString + 'static,
     F: Fn(W) -> Result<Vec<u8>, E>,
 {
     // &mut encoder is part of a Compressor, often the .finish portion consumes
@@ -613,6 +1168,18 @@ where
     }
 }
 
+// context manager exit of a Compressor, given the result of its `finish()`; kept for a later
+// `finish()` call. A failure to finish is only raised if the `with` body itself didn't raise,
+// otherwise the body's exception takes precedence.
+#[inline(always)]
+pub(crate) fn stream_exit(finished: PyResult<RustyBuffer>, body_raised: bool) -> PyResult<Option<RustyBuffer>> {
+    match (finished, body_raised) {
+        (Ok(buffer), _) => Ok(Some(buffer)),
+        (Err(err), false) => Err(err),
+        (Err(_), true) => Ok(None),
+    }
+}
+
 // flush inner encoder data out
 #[inline(always)]
 pub(crate) fn stream_flush<W, F>(encoder: &mut Option<W>, cursor_mut_ref: F) -> PyResult<RustyBuffer>
@@ -632,3 +1199,14 @@ where
         None => Ok(RustyBuffer::from(vec![])),
     }
 }
+
+/// Incremental decoder behind a codec's `Decompressor`, fed its input in pieces of any size; a
+/// stream may span several pieces, and concatenated streams are decoded one after another.
+pub(crate) trait IncrementalDecoder: Send {
+    /// Decode as much of `input` as possible, appending the output produced to `output`; an error
+    /// as soon as more than `max_len` bytes are appended, checked as the output is decoded
+    fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()>;
+    /// End the input, appending any output held back, at most `max_len` bytes; an error if it
+    /// ends part way through a stream
+    fn finish(&mut self, output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()>;
+}
@@ -41,29 +41,40 @@
 //! 33  # goodie!
 //! >>>
 //! >>> compressed.seek(0)  # Go back to the start of the buffer so we can prepare to decompress
-//! >>> decompressed = b'0' * len(data)  # let's write to `bytes` as output
+//! >>> decompressed = bytearray(len(data))  # let's write to a `bytearray` as output; `bytes` is readonly
 //! >>> decompressed
-//! b'000000000000000'
+//! bytearray(b'\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00')
 //! >>>
 //! >>> snappy.decompress_into(compressed, decompressed)
 //! 15  # 15 bytes written to decompressed
 //! >>> decompressed
-//! b'some bytes here'
+//! bytearray(b'some bytes here')
 //! ```
 
+pub mod armor;
 pub mod blosc2;
 pub mod brotli;
 pub mod bzip2;
+pub mod checksum;
+pub(crate) mod codecs;
 pub mod deflate;
+pub mod detect;
 pub mod exceptions;
 
 pub mod experimental;
 
+pub mod filters;
 pub mod gzip;
+pub mod info;
 pub mod io;
 pub mod lz4;
+pub mod object;
 pub mod snappy;
+pub mod tagged;
+pub mod text;
+pub mod transcode;
 pub mod xz;
+pub mod zlib;
 pub mod zstd;
 
 use io::{PythonBuffer, RustyBuffer};
@@ -71,6 +82,7 @@ use pyo3::prelude::*;
 
 use crate::io::{AsBytes, RustyFile};
 use exceptions::{CompressionError, DecompressionError};
+use std::borrow::Cow;
 use std::io::{Read, Seek, SeekFrom, Write};
 
 /// Any possible input/output to de/compression algorithms.
@@ -84,7 +96,10 @@ pub enum BytesType<'a> {
     /// [`cramjam.File`](io/struct.RustyFile.html)
     #[pyo3(transparent, annotation = "File")]
     RustyFile(Bound<'a, RustyFile>),
-    /// `object` implementing the Buffer Protocol
+    /// `object` implementing the Buffer Protocol, read in place without copying. This includes
+    /// `numpy` arrays, `array.array` and `ctypes` arrays, ie. `(ctypes.c_char * n)`; items wider
+    /// than a byte are taken as their raw bytes. Objects without a C contiguous buffer, ie. strided
+    /// array views or `ctypes` pointers, are rejected with a `TypeError`.
     #[pyo3(transparent, annotation = "pybuffer")]
     PyBuffer(PythonBuffer),
 }
@@ -125,7 +140,7 @@ impl<'a> AsBytes for BytesType<'a> {
 impl<'a> Write for BytesType<'a> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let result = match self {
-            BytesType::RustyBuffer(out) => out.borrow_mut().inner.write(buf)?,
+            BytesType::RustyBuffer(out) => Write::write(&mut *out.borrow_mut(), buf)?,
             BytesType::RustyFile(out) => out.borrow_mut().inner.write(buf)?,
             BytesType::PyBuffer(out) => out.write(buf)?,
         };
@@ -179,40 +194,137 @@ impl<'a> BytesType<'a> {
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    /// The bytes of this input. A `File` has none in memory to borrow, so it's read to its end from
+    /// its current position, as de/compressing it through `generic!` would.
+    pub(crate) fn read_bytes(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            BytesType::RustyFile(file) => {
+                let mut bytes = vec![];
+                file.borrow_mut().inner.read_to_end(&mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+            _ => Ok(Cow::Borrowed(self.as_bytes())),
+        }
+    }
+    /// The first `n` bytes of this input, or all of it if shorter; a `File` is peeked at and left
+    /// at the same position.
+    pub(crate) fn peek(&self, n: usize) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            BytesType::RustyFile(file) => {
+                let mut file = file.borrow_mut();
+                let pos = file.inner.stream_position()?;
+                let mut head = vec![];
+                (&mut file.inner).take(n as u64).read_to_end(&mut head)?;
+                file.inner.seek(SeekFrom::Start(pos))?;
+                Ok(Cow::Owned(head))
+            }
+            _ => {
+                let bytes = self.as_bytes();
+                Ok(Cow::Borrowed(&bytes[..n.min(bytes.len())]))
+            }
+        }
+    }
+}
+
+/// Docs of the arguments guarding the codecs' `decompress` against untrusted input, shared by all
+/// of them as `#[doc = crate::decompress_guards_doc!()]`
+#[macro_export]
+macro_rules! decompress_guards_doc {
+    () => {
+        "With `reject_nested=True`, a `DecompressionError` is raised if the decompressed data itself
+looks compressed, see `cramjam.looks_compressed`; a guard against nested archives from untrusted input.
+
+`max_input_len` rejects `data` longer than that many bytes with a `DecompressionError` before
+any decoding is done, bounding the work spent on untrusted input.
+
+`max_output_len` raises a `DecompressionError` as soon as the output grows past that many bytes,
+rather than once all of it is decoded; a guard against decompression bombs."
+    };
+}
+
+/// Doc of the codecs' `compress(..., return_len=...)`, shared by all of them as
+/// `#[doc = crate::compress_return_len_doc!()]`
+#[macro_export]
+macro_rules! compress_return_len_doc {
+    () => {
+        "With `return_len=True`, a tuple of the compressed buffer and its length is returned."
+    };
+}
+
+/// Doc of the `Compressor.flush_modes` staticmethods, shared by all of them as
+/// `#[doc = crate::flush_modes_doc!()]`
+#[macro_export]
+macro_rules! flush_modes_doc {
+    () => {
+        "Flush modes supported by `flush`, the first being its default."
+    };
 }
 
 /// Macro for generating the implementation of de/compression against a variant interface
+///
+/// The de/compression itself runs with the GIL released, so other Python threads, ie. others
+/// compressing concurrently, carry on meanwhile. Slices of the input and output stay valid without
+/// the GIL as they're pinned for the whole expansion:
+/// - `PyBuffer`: the `Py_buffer` export holds a reference to its exporter, which can't resize or free
+///   its memory while the export exists, (ie. a `bytearray` raises `BufferError`) and is released
+///   on drop, after the GIL is re-acquired.
+/// - `RustyBuffer` / `RustyFile`: borrowed for the whole call, so any other thread's attempt to
+///   mutate them, (ie. to grow and so reallocate a `Buffer`) fails with an "Already borrowed" error.
+///
+/// Another thread can still write *into* a borrowed `bytearray` or array meanwhile, changing the
+/// data seen mid-operation; that gives garbage output but never touches freed memory.
+///
+/// An into output of fixed size, (ie. a numpy array) too small for the result fails with the size
+/// it needed, counted by running the operation to the end, see `io::FixedOutput`.
 #[macro_export]
 macro_rules! generic {
-    // de/compress
-    ($py:ident, $op:path[$input:expr], output_len = $output_len:ident $(, $args:ident)*) => {
+    // decompress, failing once the output exceeds `max_output_len`
+    ($py:ident, $op:path[$input:expr], output_len = $output_len:ident, max_output_len = $max_output_len:ident $(, $args:ident)*) => {
         {
-            use crate::io::RustyBuffer;
+            use $crate::exceptions::ErrorContext;
+            use $crate::io::{LimitedWriter, RustyBuffer, SignalCheckedReader};
 
             let mut output: Vec<u8> = match $output_len {
-                Some(len) => vec![0; len],
+                Some(len) => vec![0; $max_output_len.map_or(len, |max_len: usize| len.min(max_len))],
                 None => vec![]
             };
-            match $input {
+            let mut cursor = Cursor::new(&mut output);
+            let mut writer = LimitedWriter::new(&mut cursor, $max_output_len);
+            let (result, consumed) = match $input {
                 BytesType::RustyFile(f) => {
                     let borrowed = f.borrow();
-                    let file = &borrowed.inner;
-                    $py.allow_threads(|| {
-                        $op(file, &mut Cursor::new(&mut output) $(, $args)* )
-                    })
+                    let mut reader = SignalCheckedReader::new(&borrowed.inner);
+                    let result = $py.allow_threads(|| {
+                        $op(&mut reader, &mut writer $(, $args)* )
+                    });
+                    (result, reader.consumed())
                 },
                 _ => {
-                    let bytes = $input.as_bytes();
-                    $py.allow_threads(|| {
-                        $op(bytes, &mut Cursor::new(&mut output) $(, $args)* )
-                    })
+                    let mut reader = SignalCheckedReader::new($input.as_bytes());
+                    let result = $py.allow_threads(|| {
+                        $op(&mut reader, &mut writer $(, $args)* )
+                    });
+                    (result, reader.consumed())
                 }
-            }.map(|_| RustyBuffer::from(output))
+            };
+            let produced = cursor.position() as usize;
+            result
+                .map_err(|err| ErrorContext::wrap(err, module_path!(), consumed, produced))
+                .map(|_| RustyBuffer::from(output))
+        }
+    };
+    // de/compress
+    ($py:ident, $op:path[$input:expr], output_len = $output_len:ident $(, $args:ident)*) => {
+        {
+            let max_output_len: Option<usize> = None;
+            $crate::generic!($py, $op[$input], output_len = $output_len, max_output_len = max_output_len $(, $args)*)
         }
     };
     // de/compress_into
     ($py:ident, $op:path[$input:ident, $output:ident] $(, $args:ident)*) => {
         {
+            use $crate::io::{FixedOutput, SignalCheckedReader};
+
             match $input {
                 BytesType::RustyFile(f) => {
                     let borrowed = f.borrow();
@@ -222,21 +334,22 @@ macro_rules! generic {
                             let mut borrowed = f.borrow_mut();
                             let mut f_out = &mut borrowed.inner;
                             $py.allow_threads(|| {
-                                $op(f_in, &mut f_out $(, $args)* )
+                                $op(SignalCheckedReader::new(f_in), &mut f_out $(, $args)* )
                             })
                         },
                         BytesType::RustyBuffer(buffer) => {
                             let mut borrowed = buffer.borrow_mut();
-                            let mut buf_out = &mut borrowed.inner;
+                            let mut buf_out = &mut *borrowed;
                             $py.allow_threads(|| {
-                                $op(f_in, &mut buf_out $(, $args)* )
+                                $op(SignalCheckedReader::new(f_in), &mut buf_out $(, $args)* )
                             })
                         },
                         _ => {
-                            let bytes_out = $output.as_bytes_mut()?;
-                            $py.allow_threads(|| {
-                                $op(f_in, &mut Cursor::new(bytes_out) $(, $args)* )
-                            })
+                            let mut bytes_out = FixedOutput::new($output.as_bytes_mut()?);
+                            let result = $py.allow_threads(|| {
+                                $op(SignalCheckedReader::new(f_in), &mut bytes_out $(, $args)*)
+                            });
+                            bytes_out.check(result)
                         }
                     }
                 },
@@ -247,21 +360,22 @@ macro_rules! generic {
                             let mut borrowed = f.borrow_mut();
                             let mut f_out = &mut borrowed.inner;
                             $py.allow_threads(|| {
-                                $op(bytes_in, &mut f_out $(, $args)* )
+                                $op(SignalCheckedReader::new(bytes_in), &mut f_out $(, $args)* )
                             })
                         },
                         BytesType::RustyBuffer(buffer) => {
                             let mut borrowed = buffer.borrow_mut();
-                            let mut buf_out = &mut borrowed.inner;
+                            let mut buf_out = &mut *borrowed;
                             $py.allow_threads(|| {
-                                $op(bytes_in, &mut buf_out $(, $args)* )
+                                $op(SignalCheckedReader::new(bytes_in), &mut buf_out $(, $args)* )
                             })
                         },
                         _ => {
-                            let bytes_out = $output.as_bytes_mut()?;
-                            $py.allow_threads(|| {
-                                $op(bytes_in, &mut Cursor::new(bytes_out) $(, $args)*)
-                            })
+                            let mut bytes_out = FixedOutput::new($output.as_bytes_mut()?);
+                            let result = $py.allow_threads(|| {
+                                $op(SignalCheckedReader::new(bytes_in), &mut bytes_out $(, $args)*)
+                            });
+                            bytes_out.check(result)
                         }
                     }
                 }
@@ -271,80 +385,121 @@ macro_rules! generic {
 }
 
 /// Generate a `Decompressor` from a library's decompressor which implements Read
+///
+/// Optionally, `decoder = $decoder` gives an `IncrementalDecoder` to feed the input through, so
+/// a stream may span several `decompress` calls; otherwise each call's input is decoded whole,
+/// by the codec's own `decompress`.
 #[macro_export]
 macro_rules! make_decompressor {
     ($codec:ident) => {
+        /// Decodes each piece of input whole, the codec's frames can't be decoded in part
+        struct WholeStreams;
+
+        impl $crate::io::IncrementalDecoder for WholeStreams {
+            fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+                let mut output = $crate::io::LimitedWriter::new(output, max_len);
+                libcramjam::$codec::decompress(input, &mut output).map(|_| ())
+            }
+            fn finish(&mut self, _output: &mut Vec<u8>, _max_len: Option<usize>) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        $crate::make_decompressor!($codec, decoder = WholeStreams);
+    };
+    ($codec:ident, decoder = $decoder:expr) => {
         /// Decompressor object for streaming decompression
         /// **NB** This is mostly here for API complement to `Compressor`
         /// You'll almost always be statisfied with `de/compress` / `de/compress_into` functions.
+        ///
+        /// Output is returned by `decompress` as it's produced rather than accumulated. What's held
+        /// back, ie. output decoded by a `decompress` call before it raised, is returned by `flush`;
+        /// `len()` and the `len`/`in`/`bool` protocols are over that held back output.
+        ///
+        /// `max_output_len` raises a `DecompressionError` once the total output exceeds that many bytes;
+        /// checked as the output is decoded, so a small piece of input can't expand past it first.
         #[pyclass]
         pub struct Decompressor {
-            inner: Option<Cursor<Vec<u8>>>,
+            decoder: Box<dyn $crate::io::IncrementalDecoder>,
+            // output decoded but not yet returned
+            buffer: Vec<u8>,
+            // whether `finish` was called, after which the instance can't be used
+            finished: bool,
+            max_output_len: Option<usize>,
+            produced: usize,
         }
         #[pymethods]
         impl Decompressor {
             /// Initialize a new `Decompressor` instance.
             #[new]
-            pub fn __init__() -> PyResult<Self> {
+            #[pyo3(signature = (max_output_len=None))]
+            pub fn __init__(max_output_len: Option<usize>) -> PyResult<Self> {
                 Ok(Self {
-                    inner: Some(Default::default()),
+                    decoder: Box::new($decoder),
+                    buffer: vec![],
+                    finished: false,
+                    max_output_len,
+                    produced: 0,
                 })
             }
 
-            /// Length of internal buffer containing decompressed data.
+            /// Length of the output held back, see `flush`.
             pub fn len(&self) -> usize {
-                self.inner
-                    .as_ref()
-                    .map(|c| c.get_ref().len())
-                    .unwrap_or_else(|| 0)
+                self.buffer.len()
             }
 
-            /// Decompress this input into the inner buffer.
-            pub fn decompress(&mut self, py: Python, mut input: BytesType) -> PyResult<usize> {
-                match self.inner.as_mut() {
-                    Some(ref mut inner) => match &mut input {
-                        BytesType::RustyFile(f) => {
-                            let mut borrowed = f.borrow_mut();
-                            let f_in = &mut borrowed.inner;
-                            py.allow_threads(|| libcramjam::$codec::decompress(f_in, inner).map_err(Into::into))
-                        }
-                        _ => {
-                            let bytes = input.as_bytes();
-                            py.allow_threads(|| {
-                                libcramjam::$codec::decompress(&mut Cursor::new(bytes), inner).map_err(Into::into)
-                            })
-                        }
-                    },
-                    None => Err(DecompressionError::new_err(
-                        "Appears `finish()` was called on this instance",
-                    )),
-                }
+            /// Decompress this input, returning only the output it produced rather than
+            /// accumulating it; so a stream can be passed on piece by piece.
+            pub fn decompress(&mut self, py: Python, mut input: BytesType) -> PyResult<RustyBuffer> {
+                self.check_not_finished()?;
+                let mut file_bytes = vec![];
+                let bytes = match &mut input {
+                    BytesType::RustyFile(_) => {
+                        std::io::Read::read_to_end(&mut input, &mut file_bytes)?;
+                        &file_bytes[..]
+                    }
+                    _ => input.as_bytes(),
+                };
+                let max_len = self
+                    .max_output_len
+                    .map(|max_len| max_len.saturating_sub(self.produced));
+                let (decoder, buffer) = (&mut self.decoder, &mut self.buffer);
+                let held = buffer.len();
+                let result = py.allow_threads(|| decoder.feed(bytes, buffer, max_len));
+                self.produced += self.buffer.len() - held;
+                result.map_err(DecompressionError::from_err)?;
+                $crate::io::check_output_len(self.produced, self.max_output_len)
+                    .map_err(DecompressionError::from_err)?;
+                Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
             }
 
-            /// Flush and return current decompressed stream.
+            /// Return the output held back, which `decompress` would otherwise have returned; ie.
+            /// that decoded before it raised.
             pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-                match self.inner.as_mut() {
-                    Some(ref mut inner) => {
-                        let mut out = vec![];
-                        std::mem::swap(&mut out, inner.get_mut());
-                        inner.set_position(0);
-                        Ok(RustyBuffer::from(out))
-                    }
-                    None => Err(DecompressionError::new_err(
-                        "Appears `finish()` was called on this instance",
-                    )),
-                }
+                self.check_not_finished()?;
+                Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
             }
 
-            /// Consume the current Decompressor state and return the decompressed stream
+            /// Consume the current Decompressor state and return the rest of the decompressed stream,
+            /// raising `DecompressionError` if the input ended part way through a stream.
             /// **NB** The Decompressor will not be usable after this method is called.
-            pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-                match std::mem::take(&mut self.inner) {
-                    Some(inner) => Ok(RustyBuffer::from(inner.into_inner())),
-                    None => Err(DecompressionError::new_err(
+            pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+                if std::mem::replace(&mut self.finished, true) {
+                    return Err(DecompressionError::new_err(
                         "Appears `finish()` was called on this instance",
-                    )),
+                    ));
                 }
+                let max_len = self
+                    .max_output_len
+                    .map(|max_len| max_len.saturating_sub(self.produced));
+                let (decoder, buffer) = (&mut self.decoder, &mut self.buffer);
+                let held = buffer.len();
+                let result = py.allow_threads(|| decoder.finish(buffer, max_len));
+                self.produced += self.buffer.len() - held;
+                result.map_err(DecompressionError::from_err)?;
+                $crate::io::check_output_len(self.produced, self.max_output_len)
+                    .map_err(DecompressionError::from_err)?;
+                Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
             }
 
             fn __len__(&self) -> usize {
@@ -352,23 +507,463 @@ macro_rules! make_decompressor {
             }
             fn __contains__(&self, py: Python, x: BytesType) -> bool {
                 let bytes = x.as_bytes();
-                py.allow_threads(|| {
-                    self.inner
-                        .as_ref()
-                        .map(|c| c.get_ref().windows(bytes.len()).any(|w| w == bytes))
-                        .unwrap_or_else(|| false)
-                })
+                let buffer = &self.buffer;
+                py.allow_threads(|| buffer.windows(bytes.len()).any(|w| w == bytes))
             }
             fn __repr__(&self) -> String {
                 format!("Decompressor<len={}>", self.len())
             }
             fn __bool__(&self) -> bool {
-                self.inner.is_some() && self.len() > 0
+                !self.finished && self.len() > 0
+            }
+        }
+
+        impl Decompressor {
+            fn check_not_finished(&self) -> PyResult<()> {
+                match self.finished {
+                    true => Err(DecompressionError::new_err(
+                        "Appears `finish()` was called on this instance",
+                    )),
+                    false => Ok(()),
+                }
             }
         }
     };
 }
 
+/// Generate `compress_checked`/`decompress_checked` for a codec, wrapping its output in an envelope
+/// of the compressed bytes followed by the little-endian CRC32 of the uncompressed data.
+#[macro_export]
+macro_rules! make_checked {
+    ($codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        use libcramjam::gzip::flate2::Crc;
+
+        const CRC_LEN: usize = std::mem::size_of::<u32>();
+
+        /// Compression with a CRC32 footer, for integrity checking in `decompress_checked`.
+        ///
+        /// The envelope is the compressed bytes followed by the little-endian CRC32 of `data`.
+        #[pyfunction]
+        #[pyo3(signature = (data $(, $level=None)?))]
+        pub fn compress_checked(py: Python, data: BytesType $(, $level: Option<$level_ty>)?) -> PyResult<RustyBuffer> {
+            $(super::_level::check_level($level)?;)?
+            let bytes = &*data.read_bytes()?;
+            py.allow_threads(|| {
+                let mut out = vec![];
+                libcramjam::$codec::compress(bytes, &mut out $(, $level)?)?;
+                let mut crc = Crc::new();
+                crc.update(bytes);
+                out.extend_from_slice(&crc.sum().to_le_bytes());
+                Ok::<_, std::io::Error>(out)
+            })
+            .map_err(CompressionError::from_err)
+            .map(RustyBuffer::from)
+        }
+
+        /// Decompression of the output of `compress_checked`, raising `DecompressionError` if
+        /// the CRC32 of the decompressed data doesn't match the footer.
+        #[pyfunction]
+        pub fn decompress_checked(py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+            let bytes = &*data.read_bytes()?;
+            if bytes.len() < CRC_LEN {
+                return Err(DecompressionError::new_err("Input too short to contain a CRC32 footer"));
+            }
+            let (body, footer) = bytes.split_at(bytes.len() - CRC_LEN);
+            let expected = u32::from_le_bytes(footer.try_into().unwrap());
+            let out = py
+                .allow_threads(|| {
+                    let mut out = vec![];
+                    libcramjam::$codec::decompress(body, &mut out).map(|_| out)
+                })
+                .map_err(DecompressionError::from_err)?;
+            let mut crc = Crc::new();
+            crc.update(&out);
+            if crc.sum() != expected {
+                return Err(DecompressionError::new_err(format!(
+                    "CRC32 mismatch, expected {:#010x} but decompressed data has {:#010x}",
+                    expected,
+                    crc.sum()
+                )));
+            }
+            Ok(RustyBuffer::from(out))
+        }
+    };
+}
+
+/// Generates `compress_reproducible` for a codec module, compressing with its parameters pinned.
+#[macro_export]
+macro_rules! make_reproducible {
+    ($codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        /// Compression with byte-stable output, suitable for comparing against golden files.
+        ///
+        /// Everything but `data` and the level is pinned: a single thread, and the codec's default
+        /// parameters; so the same data and level always give the same bytes. A different version
+        /// of the codec's library may still compress differently.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.zstd.compress_reproducible(b'some bytes here', level=3)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data $(, $level=None)?))]
+        pub fn compress_reproducible(py: Python, data: BytesType $(, $level: Option<$level_ty>)?) -> PyResult<RustyBuffer> {
+            $(super::_level::check_level($level)?;)?
+            let bytes = &*data.read_bytes()?;
+            py.allow_threads(|| {
+                let mut out = vec![];
+                libcramjam::$codec::compress(bytes, &mut out $(, $level)?).map(|_| out)
+            })
+            .map_err(CompressionError::from_err)
+            .map(RustyBuffer::from)
+        }
+    };
+}
+
+/// Generates `decompress_bench` for a codec module, timing decompression into `std::io::sink()`.
+#[macro_export]
+macro_rules! make_bench {
+    ($codec:ident) => {
+        /// Decompress `data` `iterations` times, discarding the output, and return the total
+        /// time taken in nanoseconds.
+        ///
+        /// Output is written to a sink rather than a buffer, so no output is allocated and the GIL is
+        /// released throughout, giving decode-speed timings free of allocation noise.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> nanos = cramjam.snappy.decompress_bench(compressed_bytes, iterations=100)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data, iterations=1))]
+        pub fn decompress_bench(py: Python, data: BytesType, iterations: usize) -> PyResult<f64> {
+            if iterations == 0 {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "iterations must be greater than 0",
+                ));
+            }
+            let bytes = &*data.read_bytes()?;
+            py.allow_threads(|| {
+                let start = std::time::Instant::now();
+                for _ in 0..iterations {
+                    libcramjam::$codec::decompress(bytes, &mut std::io::sink())?;
+                }
+                Ok::<_, std::io::Error>(start.elapsed().as_nanos() as f64)
+            })
+            .map_err(DecompressionError::from_err)
+        }
+    };
+}
+
+/// Generates `decompress_strict` for a codec module, given the magic numbers its streams start with.
+/// Brotli and raw deflate streams have no magic number, so those modules don't have it.
+#[macro_export]
+macro_rules! make_strict {
+    ($codec:ident, $($magic:expr),+) => {
+        /// Decompression which first checks `data` starts with this codec's magic number, raising
+        /// `DecompressionError` before any decoding if it doesn't; so data of another codec fails loudly
+        /// rather than possibly decoding as garbage.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.gzip.decompress_strict(compressed_bytes, output_len=Optional[int])
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data, output_len=None))]
+        pub fn decompress_strict(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
+            let magics: &[&[u8]] = &[$(&$magic[..]),+];
+            let head = data.peek(magics.iter().map(|magic| magic.len()).max().unwrap_or(0))?;
+            if !magics.iter().any(|magic| head.starts_with(magic)) {
+                return Err(DecompressionError::new_err(concat!("not a ", stringify!($codec), " stream")));
+            }
+            $crate::generic!(py, libcramjam::$codec::decompress[data], output_len = output_len)
+                .map_err(DecompressionError::from_err)
+        }
+    };
+}
+
+/// Generates `compress_into_ring` for a codec module, compressing into a fixed size ring buffer.
+#[macro_export]
+macro_rules! make_ring {
+    ($codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        /// Compress `data` into `ring`, a `cramjam.Buffer` whose length is the ring's capacity, starting
+        /// at `pos` and wrapping around to its start at the end; for streaming within a fixed amount of
+        /// memory. Returns a tuple of the position after the output, where the next call should write,
+        /// and the number of bytes written.
+        ///
+        /// Output overwrites whatever is in the ring, so the consumer must keep up; a single output
+        /// longer than the whole ring raises a `CompressionError`, leaving the ring untouched.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> ring = cramjam.Buffer(b'\x00' * 4096)
+        /// >>> pos, n_bytes = cramjam.gzip.compress_into_ring(b'some bytes here', ring, 0)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data, ring, pos $(, $level=None)?))]
+        pub fn compress_into_ring(
+            py: Python,
+            data: BytesType,
+            mut ring: PyRefMut<'_, RustyBuffer>,
+            pos: usize
+            $(, $level: Option<$level_ty>)?
+        ) -> PyResult<(usize, usize)> {
+            $(super::_level::check_level($level)?;)?
+            let capacity = ring.len();
+            if pos >= capacity {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "pos {} is outside of the ring's capacity of {} bytes",
+                    pos, capacity
+                )));
+            }
+            let bytes = &*data.read_bytes()?;
+            let mut out = vec![];
+            py.allow_threads(|| libcramjam::$codec::compress(bytes, &mut out $(, $level)?))
+                .map_err(CompressionError::from_err)?;
+            if out.len() > capacity {
+                return Err(CompressionError::new_err(format!(
+                    "Compressed output of {} bytes exceeds the ring's capacity of {} bytes",
+                    out.len(),
+                    capacity
+                )));
+            }
+            let slots = ring.inner.get_mut();
+            let (head, tail) = out.split_at(std::cmp::min(out.len(), capacity - pos));
+            slots[pos..pos + head.len()].copy_from_slice(head);
+            slots[..tail.len()].copy_from_slice(tail);
+            Ok(((pos + out.len()) % capacity, out.len()))
+        }
+    };
+}
+
+/// Generates `compressed_size` for a codec module, compressing into a byte counting sink.
+#[macro_export]
+macro_rules! make_compressed_size {
+    ($codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        /// Size in bytes `data` compresses to, the same as `len(compress(data, level))`, but without
+        /// allocating the output; ie. to decide whether compressing is worth storing.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.gzip.compressed_size(b'some bytes here', level=6)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data $(, $level=None)?))]
+        pub fn compressed_size(py: Python, data: BytesType $(, $level: Option<$level_ty>)?) -> PyResult<usize> {
+            $(super::_level::check_level($level)?;)?
+            let bytes = &*data.read_bytes()?;
+            let mut sink = $crate::io::CountingSink::default();
+            py.allow_threads(|| libcramjam::$codec::compress(bytes, &mut sink $(, $level)?))
+                .map_err(CompressionError::from_err)?;
+            Ok(sink.n_bytes())
+        }
+    };
+}
+
+/// Generates `min_level` and `max_level` for a codec module, and `check_level` for its `compress`
+/// functions to reject a level outside that range before any work is done.
+#[macro_export]
+macro_rules! make_level_range {
+    ($codec:ident, $level_ty:ty, $min:expr, $max:expr) => {
+        /// Lowest compression level `compress` accepts.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.gzip.min_level()
+        /// 0
+        /// ```
+        #[pyfunction]
+        pub fn min_level() -> $level_ty {
+            $min
+        }
+
+        /// Highest compression level `compress` accepts.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.gzip.max_level()
+        /// 9
+        /// ```
+        #[pyfunction]
+        pub fn max_level() -> $level_ty {
+            $max
+        }
+
+        /// Raise a `CompressionError` if `level` is outside `min_level()..=max_level()`
+        pub(crate) fn check_level(level: Option<$level_ty>) -> PyResult<()> {
+            match level {
+                Some(level) if !(min_level()..=max_level()).contains(&level) => Err(CompressionError::new_err(format!(
+                    "level {} out of range {}..={} for {}",
+                    level,
+                    min_level(),
+                    max_level(),
+                    stringify!($codec)
+                ))),
+                _ => Ok(()),
+            }
+        }
+    };
+}
+
+/// Generates `decompress_prefix` for a codec module, decompressing only the start of the data.
+#[macro_export]
+macro_rules! make_prefix {
+    ($codec:ident) => {
+        /// Decompress only the first `n_bytes` of the output, ie. a file header, stopping the decode
+        /// once that much has been produced rather than decompressing all of `data`.
+        ///
+        /// Anything after the prefix isn't validated, so corruption past it goes unnoticed; if the
+        /// data decompresses to fewer than `n_bytes`, all of it is returned.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> header = cramjam.snappy.decompress_prefix(compressed_bytes, 512)
+        /// ```
+        #[pyfunction]
+        pub fn decompress_prefix(py: Python, data: BytesType, n_bytes: usize) -> PyResult<RustyBuffer> {
+            let bytes = &*data.read_bytes()?;
+            let out = py.allow_threads(|| {
+                let mut writer = $crate::io::PrefixWriter::new(n_bytes);
+                match libcramjam::$codec::decompress(bytes, &mut writer) {
+                    // the error of the aborted decode is expected once the prefix is complete
+                    Err(_) if writer.is_full() => Ok(writer.into_inner()),
+                    result => result.map(|_| writer.into_inner()),
+                }
+            });
+            out.map_err(DecompressionError::from_err).map(RustyBuffer::from)
+        }
+    };
+}
+
+/// Generates `decompress_records` for a codec module, splitting the output into fixed size records.
+#[macro_export]
+macro_rules! make_records {
+    ($codec:ident) => {
+        /// Decompress `data` and split the output into records of `record_size` bytes, ie. a stream
+        /// of fixed width rows; a `DecompressionError` is raised if the output isn't a whole number
+        /// of records.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> rows = cramjam.zstd.decompress_records(compressed_bytes, 16)
+        /// ```
+        #[pyfunction]
+        pub fn decompress_records(py: Python, data: BytesType, record_size: usize) -> PyResult<Vec<RustyBuffer>> {
+            if record_size == 0 {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "record_size must be greater than 0",
+                ));
+            }
+            let bytes = &*data.read_bytes()?;
+            let mut out = vec![];
+            py.allow_threads(|| libcramjam::$codec::decompress(bytes, &mut out))
+                .map_err(DecompressionError::from_err)?;
+            if out.len() % record_size != 0 {
+                return Err(DecompressionError::new_err(format!(
+                    "Decompressed length {} isn't a multiple of record_size {}",
+                    out.len(),
+                    record_size
+                )));
+            }
+            Ok(out
+                .chunks(record_size)
+                .map(|record| RustyBuffer::from(record.to_vec()))
+                .collect())
+        }
+    };
+}
+
+/// Generates a codec module's extras, the functions of the `make_*!` macros above, from a list
+/// naming them; `strict` takes its magic numbers, ie. `strict(b"BZh")`. `#[pymodule]` only sees
+/// the exports spelled out in the module, so they're generated into a module of their own and
+/// exported from it:
+/// ```ignore
+/// mod _extras {
+///     use super::*;
+///     crate::make_codec_extras!(bzip2, level: u32; checked, bench, strict(b"BZh"));
+/// }
+/// #[pymodule_export]
+/// use _extras::{compress_checked, decompress_bench, decompress_checked, decompress_strict};
+/// ```
+#[macro_export]
+macro_rules! make_codec_extras {
+    (@checked $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_checked!($codec $(, $level: $level_ty)?);
+    };
+    (@reproducible $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_reproducible!($codec $(, $level: $level_ty)?);
+    };
+    (@bench $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_bench!($codec);
+    };
+    (@strict $codec:ident $(, $level:ident: $level_ty:ty)?; $($magic:expr),+) => {
+        $crate::make_strict!($codec, $($magic),+);
+    };
+    (@prefix $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_prefix!($codec);
+    };
+    (@records $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_records!($codec);
+    };
+    (@ring $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_ring!($codec $(, $level: $level_ty)?);
+    };
+    (@compressed_size $codec:ident $(, $level:ident: $level_ty:ty)?) => {
+        $crate::make_compressed_size!($codec $(, $level: $level_ty)?);
+    };
+    ($codec:ident $(, $level:ident: $level_ty:ty)?;) => {};
+    ($codec:ident $(, $level:ident: $level_ty:ty)?; $extra:ident $(($($arg:tt)*))? $(, $($rest:tt)*)?) => {
+        $crate::make_codec_extras!(@$extra $codec $(, $level: $level_ty)? $(; $($arg)*)?);
+        $crate::make_codec_extras!($codec $(, $level: $level_ty)?; $($($rest)*)?);
+    };
+}
+
+/// Compression with `codec`, one of the codec module names, ie. "zstd", at `level` or the codec's
+/// default; for choosing the codec from configuration rather than importing its module. Unknown
+/// codecs raise a `ValueError` listing the supported ones.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.compress("zstd", b'some bytes here', level=7)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, level=None))]
+pub fn compress(py: Python, codec: &str, data: BytesType, level: Option<i32>) -> PyResult<RustyBuffer> {
+    crate::codecs::check_codec(codec)?;
+    crate::codecs::check_level(codec, level)?;
+    let bytes = &*data.read_bytes()?;
+    let mut out = vec![];
+    py.allow_threads(|| crate::codecs::compress_with(codec, bytes, &mut out, level))
+        .map_err(CompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
+/// Decompression with `codec`, one of the codec module names, ie. "gzip"; see `compress`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress("gzip", compressed_bytes)
+/// ```
+#[pyfunction]
+pub fn decompress(py: Python, codec: &str, data: BytesType) -> PyResult<RustyBuffer> {
+    crate::codecs::check_codec(codec)?;
+    let bytes = &*data.read_bytes()?;
+    let mut out = vec![];
+    py.allow_threads(|| crate::codecs::decompress_with(codec, bytes, &mut out))
+        .map_err(DecompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
 #[pymodule]
 mod cramjam {
     use super::*;
@@ -376,6 +971,7 @@ mod cramjam {
     #[pymodule_init]
     fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
         m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+        crate::exceptions::add_context_defaults(m.py())?;
         m.add_class::<crate::io::RustyFile>()?;
         m.add_class::<crate::io::RustyBuffer>()?;
         Ok(())
@@ -387,6 +983,9 @@ mod cramjam {
     #[pymodule_export]
     use crate::DecompressionError;
 
+    #[pymodule_export]
+    use crate::{compress, decompress};
+
     #[pymodule_export]
     use crate::snappy::snappy;
 
@@ -411,6 +1010,42 @@ mod cramjam {
     #[pymodule_export]
     use crate::gzip::gzip;
 
+    #[pymodule_export]
+    use crate::zlib::zlib;
+
     #[pymodule_export]
     use crate::experimental::experimental;
+
+    #[pymodule_export]
+    use crate::text::text;
+
+    #[pymodule_export]
+    use crate::detect::{guess_codec, looks_compressed};
+
+    #[pymodule_export]
+    use crate::checksum::{adler32, crc32};
+
+    #[pymodule_export]
+    use crate::armor::{armor, dearmor};
+
+    #[pymodule_export]
+    use crate::info::build_info;
+
+    #[pymodule_export]
+    use crate::io::{acquire_buffer, get_copy_buffer_size, release_buffer, set_copy_buffer_size};
+
+    #[pymodule_export]
+    use crate::transcode::Transcoder;
+
+    #[pymodule_export]
+    use crate::object::{compress_object, decompress_object};
+
+    #[pymodule_export]
+    use crate::tagged::{
+        compress_envelope, compress_tagged, compress_with_tag, decompress_envelope, decompress_tagged,
+        decompress_tagged_bytes,
+    };
+
+    #[pymodule_export]
+    use crate::filters::{bitshuffle, bitunshuffle, delta_decode, delta_encode, shuffle, unshuffle};
 }
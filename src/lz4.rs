@@ -17,6 +17,8 @@ pub mod lz4 {
 
     /// LZ4 compression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
@@ -24,14 +26,30 @@ pub mod lz4 {
     /// >>> cramjam.lz4.decompress(compressed_bytes, output_len=Optional[int])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::lz4::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = crate::generic!(
+            py,
+            libcramjam::lz4::decompress[data],
+            output_len = output_len,
+            max_output_len = max_output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// LZ4 compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
@@ -39,21 +57,25 @@ pub mod lz4 {
     /// >>> cramjam.lz4.compress(b'some bytes here', output_len=Optional[int])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, return_len=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
-    ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::lz4::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        return_len: bool,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = crate::generic!(py, libcramjam::lz4::compress[data], output_len = output_len, level)
+            .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::lz4::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
@@ -247,6 +269,12 @@ pub mod lz4 {
             })
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -257,6 +285,26 @@ pub mod lz4 {
         }
     }
 
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(lz4, u32, 0, 12);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            lz4, level: u32;
+            checked, reproducible, bench, strict(b"\x04\x22\x4d\x18"), prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records, decompress_strict,
+    };
+
     mod _decompressor {
         use super::*;
         crate::make_decompressor!(lz4);
@@ -0,0 +1,74 @@
+//! Compression of Python objects, serializing them around any of the codecs.
+use crate::codecs::{check_codec, compress_with, decompress_with};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::RustyBuffer;
+use crate::BytesType;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+
+/// Serializers accepted, by the name of the Python module doing the serializing
+const SERIALIZERS: [&str; 2] = ["json", "pickle"];
+
+/// Serialize `obj` with `serializer`, "json" or "pickle", and compress it with `codec`, one of the
+/// codec module names, ie. "gzip". Serializing runs in Python, via its `json`/`pickle` modules.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> compressed = cramjam.compress_object({'some': ['data', 1]}, 'gzip', serializer='json')  # serializer defaults to json
+/// ```
+#[pyfunction]
+#[pyo3(signature = (obj, codec, serializer="json"))]
+pub fn compress_object(py: Python, obj: &Bound<'_, PyAny>, codec: &str, serializer: &str) -> PyResult<RustyBuffer> {
+    check_codec(codec)?;
+    check_serializer(serializer)?;
+    let serialized = py.import_bound(serializer)?.call_method1("dumps", (obj,))?;
+    let bytes = match serialized.downcast::<PyString>() {
+        Ok(text) => text.to_str()?.as_bytes(),
+        Err(_) => serialized.downcast::<PyBytes>()?.as_bytes(),
+    };
+    let mut out = vec![];
+    py.allow_threads(|| compress_with(codec, bytes, &mut out, None))
+        .map_err(CompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
+/// Decompress `data` with `codec` and deserialize it with `serializer`, reversing `compress_object`.
+///
+/// **NB** Unpickling runs arbitrary code, only use the "pickle" serializer on trusted data.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress_object(compressed, 'gzip', serializer='json')
+/// {'some': ['data', 1]}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, codec, serializer="json"))]
+pub fn decompress_object<'py>(
+    py: Python<'py>,
+    data: BytesType,
+    codec: &str,
+    serializer: &str,
+) -> PyResult<Bound<'py, PyAny>> {
+    check_codec(codec)?;
+    check_serializer(serializer)?;
+    let bytes = &*data.read_bytes()?;
+    let mut out = vec![];
+    py.allow_threads(|| decompress_with(codec, bytes, &mut out))
+        .map_err(DecompressionError::from_err)?;
+    py.import_bound(serializer)?
+        .call_method1("loads", (PyBytes::new_bound(py, &out),))
+}
+
+/// Raise `ValueError` if `serializer` isn't one of `SERIALIZERS`
+fn check_serializer(serializer: &str) -> PyResult<()> {
+    if SERIALIZERS.contains(&serializer) {
+        return Ok(());
+    }
+    Err(PyValueError::new_err(format!(
+        "Unknown serializer '{}', expected one of {:?}",
+        serializer, SERIALIZERS
+    )))
+}
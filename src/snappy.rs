@@ -13,6 +13,8 @@ pub mod snappy {
 
     /// Snappy decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
@@ -20,14 +22,30 @@ pub mod snappy {
     /// >>> cramjam.snappy.decompress(compressed_bytes, output_len=Optional[None])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::snappy::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = crate::generic!(
+            py,
+            libcramjam::snappy::decompress[data],
+            output_len = output_len,
+            max_output_len = max_output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// Snappy compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
@@ -35,10 +53,11 @@ pub mod snappy {
     /// >>> _ = cramjam.snappy.compress(bytearray(b'this avoids double allocation in rust side, and thus faster!'))  # <- use bytearray where possible
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn compress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::snappy::compress[data], output_len = output_len)
-            .map_err(CompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, return_len=false))]
+    pub fn compress(py: Python, data: BytesType, output_len: Option<usize>, return_len: bool) -> PyResult<PyObject> {
+        let buffer = crate::generic!(py, libcramjam::snappy::compress[data], output_len = output_len)
+            .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Snappy decompression, raw
@@ -146,6 +165,12 @@ pub mod snappy {
             crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
         }
 
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -153,6 +178,19 @@ pub mod snappy {
         }
     }
 
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            snappy;
+            checked, reproducible, bench, strict(b"\xff\x06\x00\x00sNaPpY"), prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records, decompress_strict,
+    };
+
     mod _decompressor {
         use super::*;
         crate::make_decompressor!(snappy);
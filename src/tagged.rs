@@ -0,0 +1,284 @@
+//! Self-describing compression, keeping how data was compressed alongside it.
+use crate::codecs::{check_codec, check_level, compress_with, decompress_with};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::RustyBuffer;
+use crate::BytesType;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Codec ids of `compress_with_tag`; these are part of the stored format, so must never change.
+const CODEC_TAGS: [(u8, &str); 9] = [
+    (0x01, "snappy"),
+    (0x02, "brotli"),
+    (0x03, "bzip2"),
+    (0x04, "lz4"),
+    (0x05, "gzip"),
+    (0x06, "deflate"),
+    (0x07, "zstd"),
+    (0x08, "xz"),
+    (0x09, "zlib"),
+];
+
+/// Compress `data` with `codec`, one of the codec module names, ie. "gzip", returning the
+/// compressed buffer and a tag `dict` describing it:
+/// ```bash
+/// codec: the codec used
+/// level: the level given, None for the codec's default
+/// input_len: bytes before compression
+/// output_len: bytes after compression
+/// ```
+/// The tag is plain data, ie. to be stored as JSON next to the buffer, and is all
+/// `decompress_tagged` needs to decompress it.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> compressed, tag = cramjam.compress_tagged('zstd', b'some bytes here', level=3)
+/// >>> tag['codec'], tag['level'], tag['input_len']
+/// ('zstd', 3, 15)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, level=None))]
+pub fn compress_tagged<'py>(
+    py: Python<'py>,
+    codec: &str,
+    data: BytesType,
+    level: Option<i32>,
+) -> PyResult<(RustyBuffer, Bound<'py, PyDict>)> {
+    check_codec(codec)?;
+    check_level(codec, level)?;
+    let bytes = &*data.read_bytes()?;
+    let mut out = vec![];
+    py.allow_threads(|| compress_with(codec, bytes, &mut out, level))
+        .map_err(CompressionError::from_err)?;
+    let tag = PyDict::new_bound(py);
+    tag.set_item("codec", codec)?;
+    tag.set_item("level", level)?;
+    tag.set_item("input_len", bytes.len())?;
+    tag.set_item("output_len", out.len())?;
+    Ok((RustyBuffer::from(out), tag))
+}
+
+/// Decompress `data` using the `tag` returned with it by `compress_tagged`. A `DecompressionError`
+/// is raised if the lengths recorded in the tag don't match, ie. buffers and tags were mixed up.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress_tagged(compressed, tag)
+/// ```
+#[pyfunction]
+pub fn decompress_tagged(py: Python, data: BytesType, tag: &Bound<'_, PyDict>) -> PyResult<RustyBuffer> {
+    let field = |name: &str| {
+        tag.get_item(name)?
+            .ok_or_else(|| PyValueError::new_err(format!("Tag is missing '{}'", name)))
+    };
+    let codec = field("codec")?.extract::<String>()?;
+    let input_len = field("input_len")?.extract::<usize>()?;
+    let output_len = field("output_len")?.extract::<usize>()?;
+    check_codec(&codec)?;
+
+    let bytes = &*data.read_bytes()?;
+    if bytes.len() != output_len {
+        return Err(DecompressionError::new_err(format!(
+            "Compressed data is {} bytes, but its tag records {}",
+            bytes.len(),
+            output_len
+        )));
+    }
+    let mut out = vec![];
+    py.allow_threads(|| decompress_with(&codec, bytes, &mut out))
+        .map_err(DecompressionError::from_err)?;
+    if out.len() != input_len {
+        return Err(DecompressionError::new_err(format!(
+            "Decompressed data is {} bytes, but its tag records {}",
+            out.len(),
+            input_len
+        )));
+    }
+    Ok(RustyBuffer::from(out))
+}
+
+/// Compress `data` with `codec`, prefixed by a single byte identifying the codec, so buffers of
+/// mixed codecs can be stored alike and decompressed by `decompress_tagged_bytes` alone.
+///
+/// The codec ids are:
+/// ```bash
+/// 0x01: snappy
+/// 0x02: brotli
+/// 0x03: bzip2
+/// 0x04: lz4
+/// 0x05: gzip
+/// 0x06: deflate
+/// 0x07: zstd
+/// 0x08: xz
+/// 0x09: zlib
+/// ```
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> compressed = cramjam.compress_with_tag('gzip', b'some bytes here', level=6)
+/// >>> bytes(compressed)[0]
+/// 5
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, level=None))]
+pub fn compress_with_tag(py: Python, codec: &str, data: BytesType, level: Option<i32>) -> PyResult<RustyBuffer> {
+    check_codec(codec)?;
+    check_level(codec, level)?;
+    let (id, _) = CODEC_TAGS.iter().find(|(_, name)| *name == codec).unwrap();
+    let bytes = &*data.read_bytes()?;
+    let mut out = vec![*id];
+    py.allow_threads(|| compress_with(codec, bytes, &mut out, level))
+        .map_err(CompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
+/// Decompress the output of `compress_with_tag`, picking the codec from its leading byte.
+/// A `DecompressionError` is raised for an unknown codec id.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress_tagged_bytes(compressed)
+/// ```
+#[pyfunction]
+pub fn decompress_tagged_bytes(py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+    let bytes = data.read_bytes()?;
+    let (id, body) = bytes
+        .split_first()
+        .ok_or_else(|| DecompressionError::new_err("Empty input has no codec tag"))?;
+    let (_, codec) = CODEC_TAGS
+        .iter()
+        .find(|(tag, _)| tag == id)
+        .ok_or_else(|| DecompressionError::new_err(format!("Unknown codec tag {:#04x}", id)))?;
+    let mut out = vec![];
+    py.allow_threads(|| decompress_with(codec, body, &mut out))
+        .map_err(DecompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
+/// Leading bytes of `compress_envelope` output.
+const ENVELOPE_MAGIC: &[u8; 4] = b"CJEV";
+/// Envelope format version written by `compress_envelope`, and the newest `decompress_envelope` reads.
+const ENVELOPE_VERSION: u8 = 1;
+const FIELD_END: u8 = 0x00;
+const FIELD_CODEC: u8 = 0x01;
+const FIELD_FLAGS: u8 = 0x02;
+const FIELD_CRAMJAM_VERSION: u8 = 0x03;
+const FIELD_LEVEL: u8 = 0x04;
+
+/// Compress `data` with `codec` into a self-describing envelope for long-lived storage, recording
+/// which codec and cramjam version wrote it so future readers can adapt. The layout is:
+/// ```bash
+/// magic: 4 bytes, b"CJEV"
+/// version: 1 byte, the envelope format version, currently 1
+/// fields: type (1 byte), length (u16 little endian) and value records, ended by type 0x00 with length 0
+/// body: the compressed data, to the end of the envelope
+/// ```
+/// With the fields:
+/// ```bash
+/// 0x01 codec: 1 byte, the codec id as in `compress_with_tag`
+/// 0x02 flags: 1 byte, none defined yet so always 0; readers reject flags they don't know
+/// 0x03 cramjam version: UTF-8, the version of cramjam which wrote the envelope
+/// 0x04 level: i32 little endian, the compression level, only present when one was given
+/// ```
+/// Readers skip field types they don't know, so fields can be added without a new version.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> envelope = cramjam.compress_envelope('zstd', b'some bytes here', level=3)
+/// >>> cramjam.decompress_envelope(envelope)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, level=None))]
+pub fn compress_envelope(py: Python, codec: &str, data: BytesType, level: Option<i32>) -> PyResult<RustyBuffer> {
+    check_codec(codec)?;
+    check_level(codec, level)?;
+    let (id, _) = CODEC_TAGS.iter().find(|(_, name)| *name == codec).unwrap();
+    let mut out = ENVELOPE_MAGIC.to_vec();
+    out.push(ENVELOPE_VERSION);
+    let mut field = |kind: u8, value: &[u8]| {
+        out.push(kind);
+        out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        out.extend_from_slice(value);
+    };
+    field(FIELD_CODEC, &[*id]);
+    field(FIELD_FLAGS, &[0]);
+    field(FIELD_CRAMJAM_VERSION, env!("CARGO_PKG_VERSION").as_bytes());
+    if let Some(level) = level {
+        field(FIELD_LEVEL, &level.to_le_bytes());
+    }
+    field(FIELD_END, &[]);
+
+    let bytes = &*data.read_bytes()?;
+    py.allow_threads(|| compress_with(codec, bytes, &mut out, level))
+        .map_err(CompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
+
+/// Decompress an envelope written by `compress_envelope`, dispatching to the codec it records.
+///
+/// A `DecompressionError` is raised if `data` isn't an envelope, its version is newer than this
+/// cramjam reads, or it records an unknown codec or flag.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress_envelope(envelope)
+/// ```
+#[pyfunction]
+pub fn decompress_envelope(py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+    let bytes = &*data.read_bytes()?;
+    let rest = bytes
+        .strip_prefix(&ENVELOPE_MAGIC[..])
+        .ok_or_else(|| DecompressionError::new_err("Not a cramjam envelope"))?;
+    let (version, mut rest) = rest
+        .split_first()
+        .ok_or_else(|| DecompressionError::new_err("Envelope is truncated"))?;
+    if *version != ENVELOPE_VERSION {
+        return Err(DecompressionError::new_err(format!(
+            "Unsupported envelope version {}, this cramjam reads version {}",
+            version, ENVELOPE_VERSION
+        )));
+    }
+
+    let mut codec = None;
+    loop {
+        if rest.len() < 3 {
+            return Err(DecompressionError::new_err("Envelope is truncated"));
+        }
+        let kind = rest[0];
+        let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+        let value = rest
+            .get(3..3 + len)
+            .ok_or_else(|| DecompressionError::new_err("Envelope is truncated"))?;
+        rest = &rest[3 + len..];
+        match kind {
+            FIELD_END => break,
+            FIELD_CODEC => {
+                let (_, name) = CODEC_TAGS
+                    .iter()
+                    .find(|(id, _)| value == [*id])
+                    .ok_or_else(|| DecompressionError::new_err(format!("Unknown envelope codec {:?}", value)))?;
+                codec = Some(*name);
+            }
+            FIELD_FLAGS if value.iter().any(|b| *b != 0) => {
+                return Err(DecompressionError::new_err(format!(
+                    "Unknown envelope flags {:?}",
+                    value
+                )))
+            }
+            _ => (), // informational, or a field added after this version of cramjam
+        }
+    }
+    let codec = codec.ok_or_else(|| DecompressionError::new_err("Envelope doesn't record a codec"))?;
+
+    let mut out = vec![];
+    py.allow_threads(|| decompress_with(codec, rest, &mut out))
+        .map_err(DecompressionError::from_err)?;
+    Ok(RustyBuffer::from(out))
+}
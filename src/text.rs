@@ -0,0 +1,85 @@
+//! str de/compression interface, encoding and decoding text around any of the codecs
+use pyo3::prelude::*;
+
+/// str de/compression interface, encoding and decoding text around any of the codecs
+#[pymodule]
+pub mod text {
+    use crate::codecs::{check_codec, compress_with, decompress_with};
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::RustyBuffer;
+    use crate::BytesType;
+    use pyo3::exceptions::PyUnicodeDecodeError;
+    use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyString};
+    use pyo3::PyResult;
+    use std::borrow::Cow;
+
+    /// Encode `text` and compress it with `codec`, one of the codec module names, ie. "gzip".
+    ///
+    /// UTF-8 is encoded without going through Python; other encodings use Python's codecs.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.text.compress('some text here', 'gzip', encoding='utf-8')  # encoding defaults to utf-8
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (text, codec, encoding="utf-8"))]
+    pub fn compress(py: Python, text: &Bound<'_, PyString>, codec: &str, encoding: &str) -> PyResult<RustyBuffer> {
+        check_codec(codec)?;
+        let bytes = encode(text, encoding)?;
+        let mut out = vec![];
+        py.allow_threads(|| compress_with(codec, &bytes, &mut out, None))
+            .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// Decompress `data` with `codec` and decode it to `str`, raising `UnicodeDecodeError`
+    /// if it's not valid in `encoding`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.text.decompress(compressed_bytes, 'gzip', encoding='utf-8')  # encoding defaults to utf-8
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, codec, encoding="utf-8"))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        codec: &str,
+        encoding: &str,
+    ) -> PyResult<Bound<'py, PyString>> {
+        check_codec(codec)?;
+        let bytes = &*data.read_bytes()?;
+        let mut out = vec![];
+        py.allow_threads(|| decompress_with(codec, bytes, &mut out))
+            .map_err(DecompressionError::from_err)?;
+        decode(py, out, encoding)
+    }
+
+    fn is_utf8(encoding: &str) -> bool {
+        matches!(encoding.to_ascii_lowercase().as_str(), "utf-8" | "utf8" | "utf_8")
+    }
+
+    fn encode<'a>(text: &'a Bound<'_, PyString>, encoding: &str) -> PyResult<Cow<'a, [u8]>> {
+        if is_utf8(encoding) {
+            return text.to_str().map(|s| Cow::Borrowed(s.as_bytes()));
+        }
+        let encoded = text.call_method1("encode", (encoding,))?;
+        Ok(Cow::Owned(encoded.downcast::<PyBytes>()?.as_bytes().to_vec()))
+    }
+
+    fn decode<'py>(py: Python<'py>, bytes: Vec<u8>, encoding: &str) -> PyResult<Bound<'py, PyString>> {
+        if is_utf8(encoding) {
+            return match String::from_utf8(bytes) {
+                Ok(text) => Ok(PyString::new_bound(py, &text)),
+                Err(err) => {
+                    let err = PyUnicodeDecodeError::new_utf8_bound(py, err.as_bytes(), err.utf8_error())?;
+                    Err(PyErr::from_value_bound(err.into_any()))
+                }
+            };
+        }
+        PyString::from_object_bound(&PyBytes::new_bound(py, &bytes), encoding, "strict")
+    }
+}
@@ -0,0 +1,217 @@
+//! Streaming conversion of compressed data from one codec to another.
+use crate::codecs::{check_codec, check_level};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::RustyBuffer;
+use crate::BytesType;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::io::{Error, ErrorKind, Write};
+use std::sync::{Arc, Mutex};
+
+/// Codecs whose streams can be decoded as they arrive, in pieces; lz4 and snappy frames can only be read whole.
+const DECODABLE: [&str; 7] = ["gzip", "deflate", "zlib", "zstd", "bzip2", "xz", "brotli"];
+const BROTLI_BUF_SIZE: usize = 1 << 17;
+const BROTLI_LGWIN: u32 = 22;
+
+/// Streaming conversion of compressed data from `from_codec` to `to_codec`, both codec module
+/// names, ie. "gzip"; `level` is that of `to_codec`, its default if `None`. Compressed input is
+/// decoded as it's written, in pieces of any size, and the converted output returned as it's
+/// produced; so a large stream can be proxied without holding all of it.
+///
+/// Concatenated gzip members, zstd frames and xz streams are converted as one stream. lz4 and
+/// snappy can only be written to, as their frames are decoded whole; see `cramjam.lz4.decompress`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> transcoder = cramjam.Transcoder('gzip', 'zstd', level=3)
+/// >>> for chunk in response.iter_content(1 << 16):
+/// ...     upload.write(bytes(transcoder.write(chunk)))
+/// >>> upload.write(bytes(transcoder.finish()))
+/// ```
+#[pyclass]
+pub struct Transcoder {
+    stages: Option<(Box<dyn Stage>, Box<dyn Stage>)>,
+    decoded: Sink,
+    encoded: Sink,
+}
+
+#[pymethods]
+impl Transcoder {
+    /// Initialize a new `Transcoder` instance.
+    #[new]
+    #[pyo3(signature = (from_codec, to_codec, level=None))]
+    pub fn __init__(from_codec: &str, to_codec: &str, level: Option<i32>) -> PyResult<Self> {
+        check_codec(from_codec)?;
+        check_codec(to_codec)?;
+        check_level(to_codec, level)?;
+        if !DECODABLE.contains(&from_codec) {
+            return Err(PyValueError::new_err(format!(
+                "Can't decode '{}' incrementally, expected one of {:?}",
+                from_codec, DECODABLE
+            )));
+        }
+        let (decoded, encoded) = (Sink::default(), Sink::default());
+        let decoder = new_decoder(from_codec, decoded.clone()).map_err(DecompressionError::from_err)?;
+        let encoder = new_encoder(to_codec, encoded.clone(), level).map_err(CompressionError::from_err)?;
+        Ok(Self {
+            stages: Some((decoder, encoder)),
+            decoded,
+            encoded,
+        })
+    }
+
+    /// Decode this piece of the compressed input, returning the output converted so far; which may
+    /// be empty while `to_codec` buffers.
+    pub fn write(&mut self, py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+        let (decoder, encoder) = self.stages.as_mut().ok_or_else(|| {
+            CompressionError::new_err(
+                "Transcoder looks to have been consumed via `finish()`. \
+                please create a new transcoder instance.",
+            )
+        })?;
+        let bytes = &*data.read_bytes()?;
+        py.allow_threads(|| decoder.write_all(bytes))
+            .map_err(DecompressionError::from_err)?;
+        let decoded = self.decoded.take();
+        py.allow_threads(|| encoder.write_all(&decoded))
+            .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(self.encoded.take()))
+    }
+
+    /// End the conversion, returning the rest of the converted output. A `DecompressionError` is
+    /// raised if the input ends part way through a stream, where the `from_codec` can tell.
+    /// **NB** The Transcoder will not be usable after this method is called.
+    pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+        let (decoder, mut encoder) = match self.stages.take() {
+            Some(stages) => stages,
+            None => return Ok(RustyBuffer::from(vec![])),
+        };
+        py.allow_threads(|| decoder.end())
+            .map_err(DecompressionError::from_err)?;
+        let decoded = self.decoded.take();
+        py.allow_threads(|| -> std::io::Result<()> {
+            encoder.write_all(&decoded)?;
+            encoder.end()
+        })
+        .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(self.encoded.take()))
+    }
+}
+
+/// Output of a stage, drained by the `Transcoder` as it's produced
+#[derive(Clone, Default)]
+struct Sink(Arc<Mutex<Vec<u8>>>);
+
+impl Sink {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A streaming decoder or encoder writing into a `Sink`, ended once all its input is written
+trait Stage: Write + Send {
+    fn end(self: Box<Self>) -> std::io::Result<()>;
+}
+
+macro_rules! impl_stage {
+    ($ty:ty, $stage:pat => $end:expr) => {
+        impl Stage for $ty {
+            fn end(self: Box<Self>) -> std::io::Result<()> {
+                let $stage = *self;
+                $end.map(|_| ())
+            }
+        }
+    };
+}
+
+impl_stage!(libcramjam::gzip::flate2::write::MultiGzDecoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::deflate::flate2::write::DeflateDecoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::deflate::flate2::write::ZlibDecoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::zstd::zstd::stream::write::Decoder<'static, Sink>, mut stage => stage.flush());
+impl_stage!(libcramjam::bzip2::bzip2::write::BzDecoder<Sink>, mut stage => stage.finish());
+impl_stage!(libcramjam::xz::xz2::write::XzDecoder<Sink>, mut stage => stage.finish());
+impl_stage!(libcramjam::brotli::brotli::DecompressorWriter<Sink>, mut stage => stage.close());
+impl_stage!(libcramjam::gzip::flate2::write::GzEncoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::deflate::flate2::write::DeflateEncoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::deflate::flate2::write::ZlibEncoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::zstd::zstd::stream::write::Encoder<'static, Sink>, stage => stage.finish());
+impl_stage!(libcramjam::bzip2::bzip2::write::BzEncoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::xz::xz2::write::XzEncoder<Sink>, stage => stage.finish());
+impl_stage!(libcramjam::brotli::brotli::CompressorWriter<Sink>, stage => Ok::<_, Error>(stage.into_inner()));
+impl_stage!(libcramjam::lz4::lz4::Encoder<Sink>, stage => stage.finish().1);
+impl_stage!(libcramjam::snappy::snap::write::FrameEncoder<Sink>, mut stage => stage.flush());
+
+fn new_decoder(codec: &str, sink: Sink) -> std::io::Result<Box<dyn Stage>> {
+    let decoder: Box<dyn Stage> = match codec {
+        "gzip" => Box::new(libcramjam::gzip::flate2::write::MultiGzDecoder::new(sink)),
+        "deflate" => Box::new(libcramjam::deflate::flate2::write::DeflateDecoder::new(sink)),
+        "zlib" => Box::new(libcramjam::deflate::flate2::write::ZlibDecoder::new(sink)),
+        "zstd" => Box::new(libcramjam::zstd::zstd::stream::write::Decoder::new(sink)?),
+        "bzip2" => Box::new(libcramjam::bzip2::bzip2::write::BzDecoder::new(sink)),
+        "xz" => Box::new(libcramjam::xz::xz2::write::XzDecoder::new_multi_decoder(sink)),
+        "brotli" => Box::new(libcramjam::brotli::brotli::DecompressorWriter::new(
+            sink,
+            BROTLI_BUF_SIZE,
+        )),
+        _ => unreachable!("codec checked against `DECODABLE`"),
+    };
+    Ok(decoder)
+}
+
+fn new_encoder(codec: &str, sink: Sink, level: Option<i32>) -> std::io::Result<Box<dyn Stage>> {
+    let unsigned = |default: u32| {
+        level
+            .map(u32::try_from)
+            .transpose()
+            .map(|level| level.unwrap_or(default))
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("Invalid {} level {:?}", codec, level)))
+    };
+    let encoder: Box<dyn Stage> = match codec {
+        "gzip" => Box::new(libcramjam::gzip::flate2::write::GzEncoder::new(
+            sink,
+            libcramjam::gzip::flate2::Compression::new(unsigned(6)?),
+        )),
+        "deflate" => Box::new(libcramjam::deflate::flate2::write::DeflateEncoder::new(
+            sink,
+            libcramjam::deflate::flate2::Compression::new(unsigned(6)?),
+        )),
+        "zlib" => Box::new(libcramjam::deflate::flate2::write::ZlibEncoder::new(
+            sink,
+            libcramjam::deflate::flate2::Compression::new(unsigned(6)?),
+        )),
+        "zstd" => Box::new(libcramjam::zstd::zstd::stream::write::Encoder::new(
+            sink,
+            level.unwrap_or(0),
+        )?),
+        "bzip2" => Box::new(libcramjam::bzip2::bzip2::write::BzEncoder::new(
+            sink,
+            libcramjam::bzip2::bzip2::Compression::new(unsigned(6)?),
+        )),
+        "xz" => Box::new(libcramjam::xz::xz2::write::XzEncoder::new(sink, unsigned(6)?)),
+        "brotli" => Box::new(libcramjam::brotli::brotli::CompressorWriter::new(
+            sink,
+            BROTLI_BUF_SIZE,
+            unsigned(11)?,
+            BROTLI_LGWIN,
+        )),
+        "lz4" => Box::new(
+            libcramjam::lz4::lz4::EncoderBuilder::new()
+                .level(unsigned(4)?)
+                .build(sink)?,
+        ),
+        "snappy" => Box::new(libcramjam::snappy::snap::write::FrameEncoder::new(sink)),
+        _ => unreachable!("codec checked by `check_codec`"),
+    };
+    Ok(encoder)
+}
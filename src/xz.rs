@@ -9,22 +9,34 @@ pub mod xz {
     use pyo3::PyResult;
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IncrementalDecoder, RustyBuffer};
     use crate::BytesType;
-    use pyo3::exceptions::PyNotImplementedError;
-    use std::io::Cursor;
+    use libcramjam::xz::xz2::stream::{Stream, CONCATENATED, TELL_ANY_CHECK};
+    use pyo3::exceptions::{PyNotImplementedError, PyValueError};
+    use std::io::{Cursor, Read, Write};
 
     /// LZMA compression.
     ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> _ = cramjam.xz.compress(b'some bytes here')
     /// >>> # Defaults to XZ format, you can use the deprecated LZMA format like this:
     /// >>> _ = cramjam.xz.compress(b'some bytes here', format=cramjam.xz.Format.ALONE)
+    /// >>> # A delta filter ahead of lzma2, same as `xz --delta=dist=2 --lzma2`
+    /// >>> chain = cramjam.xz.FilterChain()
+    /// >>> chain.append_filter(cramjam.xz.FilterChainItem(cramjam.xz.Filter.Delta, cramjam.xz.Options().set_dist(2)))
+    /// >>> chain.append_filter(cramjam.xz.FilterChainItem(cramjam.xz.Filter.Lzma2))
+    /// >>> _ = cramjam.xz.compress(b'some bytes here', filters=chain)
     /// ```
+    ///
+    /// With `filters`, `options` are the defaults of the chain's filters' own options. The RAW format
+    /// is the bare output of the filter chain, with no header recording it, so is only decoded by
+    /// `decompress` given the same `filters`. The ALONE format takes no filter chain.
     #[pyfunction]
-    #[pyo3(signature = (data, preset=None, format=None, check=None, filters=None, options=None, output_len=None))]
+    #[pyo3(signature = (data, preset=None, format=None, check=None, filters=None, options=None, output_len=None, return_len=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
@@ -34,18 +46,33 @@ pub mod xz {
         filters: Option<FilterChain>,
         options: Option<Options>,
         output_len: Option<usize>,
-    ) -> PyResult<RustyBuffer> {
-        crate::generic!(
-            py,
-            libcramjam::xz::compress[data],
-            output_len = output_len,
-            preset,
-            format,
-            check,
-            filters,
-            options
-        )
-        .map_err(CompressionError::from_err)
+        return_len: bool,
+    ) -> PyResult<PyObject> {
+        _level::check_level(preset)?;
+        let buffer = if uses_chain(&format, &filters) {
+            crate::generic!(
+                py,
+                chain::compress[data],
+                output_len = output_len,
+                preset,
+                format,
+                check,
+                filters,
+                options
+            )
+        } else {
+            crate::generic!(
+                py,
+                compress_unfiltered[data],
+                output_len = output_len,
+                preset,
+                format,
+                check,
+                options
+            )
+        }
+        .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
     }
 
     /// Compress directly into an output buffer
@@ -61,12 +88,72 @@ pub mod xz {
         filters: Option<FilterChain>,
         options: Option<Options>,
     ) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::xz::compress[input, output], preset, format, check, filters, options)
+        _level::check_level(preset)?;
+        if uses_chain(&format, &filters) {
+            return crate::generic!(py, chain::compress[input, output], preset, format, check, filters, options)
+                .map_err(CompressionError::from_err);
+        }
+        crate::generic!(py, compress_unfiltered[input, output], preset, format, check, options)
             .map_err(CompressionError::from_err)
     }
 
+    /// Whether compression goes through `chain::compress`; the ALONE format takes no filter chain
+    fn uses_chain(format: &Option<Format>, filters: &Option<FilterChain>) -> bool {
+        match format {
+            Some(Format::ALONE) => false,
+            Some(Format::RAW) => true,
+            _ => filters.is_some(),
+        }
+    }
+
+    /// XZ compression with byte-stable output, suitable for comparing against golden files.
+    ///
+    /// The format, check and filters are pinned to the defaults of `compress`, the XZ format with
+    /// a CRC64 check and lzma2 at `preset`, compressed on a single thread; so the same data and
+    /// preset always give the same bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.xz.compress_reproducible(b'some bytes here', preset=6)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, preset=None))]
+    pub fn compress_reproducible(py: Python, data: BytesType, preset: Option<u32>) -> PyResult<RustyBuffer> {
+        _level::check_level(preset)?;
+        let (format, check, options) = (Some(Format::XZ), Some(Check::Crc64), None);
+        crate::generic!(
+            py,
+            compress_unfiltered[data],
+            output_len = None,
+            preset,
+            format,
+            check,
+            options
+        )
+        .map_err(CompressionError::from_err)
+    }
+
+    /// `libcramjam::xz::compress` with no filter chain, for formats `chain::compress` doesn't handle
+    fn compress_unfiltered<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        preset: Option<u32>,
+        format: Option<Format>,
+        check: Option<Check>,
+        options: Option<Options>,
+    ) -> std::io::Result<usize> {
+        let filters: Option<libcramjam::xz::Filters> = None;
+        libcramjam::xz::compress(input, output, preset, format, check, filters, options)
+    }
+
     /// LZMA decompression.
     ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
+    /// Other formats are detected from `data`, but the RAW format records nothing and is decoded
+    /// with `format=Format.RAW` and the `filters` it was compressed with.
+    ///
     /// Python Example
     /// --------------
     /// ```python
@@ -74,10 +161,46 @@ pub mod xz {
     /// >>> cramjam.xz.decompress(compressed_bytes, output_len=Optional[None])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::xz::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None, format=None, filters=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+        format: Option<Format>,
+        filters: Option<FilterChain>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = match (format, filters) {
+            (Some(Format::RAW), Some(filters)) => crate::generic!(
+                py,
+                chain::decompress[data],
+                output_len = output_len,
+                max_output_len = max_output_len,
+                filters
+            ),
+            (Some(Format::RAW), None) => {
+                return Err(PyValueError::new_err(
+                    "The RAW format is only decoded given its filters",
+                ))
+            }
+            (_, Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "filters are only given for the RAW format, others record their filter chain",
+                ))
+            }
+            _ => crate::generic!(
+                py,
+                libcramjam::xz::decompress[data],
+                output_len = output_len,
+                max_output_len = max_output_len
+            ),
+        }
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
     }
 
     /// Decompress directly into an output buffer
@@ -97,6 +220,7 @@ pub mod xz {
         #[new]
         #[pyo3(signature = (preset=None))]
         pub fn __init__(preset: Option<u32>) -> PyResult<Self> {
+            _level::check_level(preset)?;
             let preset = preset.unwrap_or(5);
             let inner = libcramjam::xz::xz2::write::XzEncoder::new(Cursor::new(vec![]), preset);
             Ok(Self { inner: Some(inner) })
@@ -114,6 +238,12 @@ pub mod xz {
             ))
         }
 
+        /// Flush modes supported by `flush`; none for XZ/LZMA, use `.finish()` instead.
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec![]
+        }
+
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
@@ -121,13 +251,77 @@ pub mod xz {
         }
     }
 
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(xz, u32, 0, 9);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(xz; bench, strict(b"\xfd7zXZ\x00"), prefix, records);
+    }
+    #[pymodule_export]
+    use _extras::{decompress_bench, decompress_prefix, decompress_records, decompress_strict};
+
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(xz);
+        crate::make_decompressor!(xz, decoder = StreamDecoder::default());
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
 
+    /// Incremental decoder of concatenated xz streams, or an lzma stream, as `decompress` detects
+    #[derive(Default)]
+    struct StreamDecoder {
+        /// Created on the first input, its format detected from it
+        writer: Option<libcramjam::xz::xz2::write::XzDecoder<Vec<u8>>>,
+    }
+
+    impl IncrementalDecoder for StreamDecoder {
+        fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            if input.is_empty() {
+                return Ok(());
+            }
+            let writer = match self.writer.as_mut() {
+                Some(writer) => writer,
+                None => {
+                    let stream = Stream::new_auto_decoder(u64::MAX, TELL_ANY_CHECK | CONCATENATED)?;
+                    let writer = libcramjam::xz::xz2::write::XzDecoder::new_stream(vec![], stream);
+                    self.writer.insert(writer)
+                }
+            };
+            // each write decodes a buffer's worth of output at most, so it's checked as it's decoded
+            let (start, mut pos) = (output.len(), 0);
+            while pos < input.len() {
+                match writer.write(&input[pos..])? {
+                    0 => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        ))
+                    }
+                    n_bytes => pos += n_bytes,
+                }
+                output.append(writer.get_mut());
+                crate::io::check_output_len(output.len() - start, max_len)?;
+            }
+            writer.flush()?;
+            output.append(writer.get_mut());
+            crate::io::check_output_len(output.len() - start, max_len)
+        }
+
+        fn finish(&mut self, output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            if let Some(mut writer) = self.writer.take() {
+                let rest = writer.finish()?;
+                crate::io::check_output_len(rest.len(), max_len)?;
+                output.extend_from_slice(&rest);
+            }
+            Ok(())
+        }
+    }
+
     /// Available Filter IDs
     #[derive(Clone, Debug, PartialEq)]
     #[pyclass(eq, eq_int)]
@@ -135,6 +329,7 @@ pub mod xz {
     pub enum Filter {
         Arm,
         ArmThumb,
+        Delta,
         Ia64,
         Lzma1,
         Lzma2,
@@ -191,7 +386,7 @@ pub mod xz {
 
     /// FilterChain, similar to the default Python XZ filter chain which is a list of
     /// dicts.
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, Default)]
     #[pyclass]
     pub struct FilterChain(Vec<FilterChainItem>);
 
@@ -207,25 +402,6 @@ pub mod xz {
         }
     }
 
-    impl Into<libcramjam::xz::Filters> for FilterChain {
-        fn into(self) -> libcramjam::xz::Filters {
-            let mut filters = libcramjam::xz::Filters::new();
-            for filter in self.0 {
-                match filter.filter {
-                    Filter::Lzma1 => filters.lzma1(&filter.try_into().unwrap()),
-                    Filter::Lzma2 => filters.lzma2(&filter.try_into().unwrap()),
-                    Filter::Arm => filters.arm(),
-                    Filter::ArmThumb => filters.arm_thumb(),
-                    Filter::Ia64 => filters.ia64(),
-                    Filter::PowerPC => filters.powerpc(),
-                    Filter::Sparc => filters.sparc(),
-                    Filter::X86 => filters.x86(),
-                };
-            }
-            filters
-        }
-    }
-
     /// FilterChainItem. In Python's lzma module, this represents a single dict in the
     /// filter chain list. To be added to the `FilterChain`
     #[derive(Clone, Debug, Default)]
@@ -261,6 +437,25 @@ pub mod xz {
         nice_len: Option<usize>,
         mf: Option<MatchFinder>,
         depth: Option<usize>,
+        dist: Option<u32>,
+    }
+
+    impl Options {
+        /// These options, with those not set taken from `defaults`
+        fn or(&self, defaults: &Options) -> Options {
+            Options {
+                preset: self.preset.or(defaults.preset),
+                dict_size: self.dict_size.or(defaults.dict_size),
+                lc: self.lc.or(defaults.lc),
+                lp: self.lp.or(defaults.lp),
+                pb: self.pb.or(defaults.pb),
+                mode: self.mode.clone().or_else(|| defaults.mode.clone()),
+                nice_len: self.nice_len.or(defaults.nice_len),
+                mf: self.mf.clone().or_else(|| defaults.mf.clone()),
+                depth: self.depth.or(defaults.depth),
+                dist: self.dist.or(defaults.dist),
+            }
+        }
     }
 
     impl Into<libcramjam::xz::LzmaOptions> for FilterChainItem {
@@ -327,6 +522,11 @@ pub mod xz {
             self.depth = Some(depth);
             self.clone()
         }
+        /// Distance in bytes for the Delta filter, defaults to 1
+        pub fn set_dist(&mut self, dist: u32) -> Self {
+            self.dist = Some(dist);
+            self.clone()
+        }
     }
 
     /// Possible formats
@@ -381,4 +581,234 @@ pub mod xz {
             }
         }
     }
+
+    /// Encoding of filter chains xz2's `Filters` can't represent, (ie. ones with a Delta filter)
+    /// by driving liblzma directly.
+    mod chain {
+        use super::{Check, Filter, FilterChain, Format, Options};
+        use std::io::{self, Read, Write};
+        use std::os::raw::{c_uint, c_void};
+        use std::{mem, ptr};
+
+        const BUF_SIZE: usize = 1 << 16;
+        const LZMA_FILTER_DELTA: lzma_sys::lzma_vli = 0x03;
+        const LZMA_DELTA_TYPE_BYTE: c_uint = 0;
+
+        /// liblzma's `lzma_options_delta`, which lzma-sys doesn't define
+        #[repr(C)]
+        struct LzmaOptionsDelta {
+            type_: c_uint,
+            dist: u32,
+            reserved_int: [u32; 8],
+            reserved_ptr: [*mut c_void; 2],
+        }
+
+        /// A filter chain as liblzma takes it, with the filters' options boxed so they stay put for as
+        /// long as the chain is referenced by a coder.
+        #[allow(clippy::vec_box)]
+        struct RawChain {
+            filters: Vec<lzma_sys::lzma_filter>,
+            _deltas: Vec<Box<LzmaOptionsDelta>>,
+            _lzmas: Vec<Box<lzma_sys::lzma_options_lzma>>,
+        }
+
+        impl RawChain {
+            /// `options` and `preset` are the defaults of the chain's lzma filters' own options.
+            fn new(filters: FilterChain, preset: Option<u32>, options: &Options) -> io::Result<Self> {
+                let mut deltas = vec![];
+                let mut lzmas = vec![];
+                let mut raw_filters = vec![];
+                for item in filters.0 {
+                    let (id, options) = match item.filter {
+                        Filter::Delta => {
+                            let mut opts = Box::new(LzmaOptionsDelta {
+                                type_: LZMA_DELTA_TYPE_BYTE,
+                                dist: item.options.dist.or(options.dist).unwrap_or(1),
+                                reserved_int: [0; 8],
+                                reserved_ptr: [ptr::null_mut(); 2],
+                            });
+                            let options = &mut *opts as *mut LzmaOptionsDelta as *mut c_void;
+                            deltas.push(opts);
+                            (LZMA_FILTER_DELTA, options)
+                        }
+                        Filter::Lzma1 | Filter::Lzma2 => {
+                            let mut opts = Box::new(lzma_options(&item.options.or(options), preset)?);
+                            let options = &mut *opts as *mut lzma_sys::lzma_options_lzma as *mut c_void;
+                            lzmas.push(opts);
+                            match item.filter {
+                                Filter::Lzma1 => (lzma_sys::LZMA_FILTER_LZMA1, options),
+                                _ => (lzma_sys::LZMA_FILTER_LZMA2, options),
+                            }
+                        }
+                        Filter::Arm => (lzma_sys::LZMA_FILTER_ARM, ptr::null_mut()),
+                        Filter::ArmThumb => (lzma_sys::LZMA_FILTER_ARMTHUMB, ptr::null_mut()),
+                        Filter::Ia64 => (lzma_sys::LZMA_FILTER_IA64, ptr::null_mut()),
+                        Filter::PowerPC => (lzma_sys::LZMA_FILTER_POWERPC, ptr::null_mut()),
+                        Filter::Sparc => (lzma_sys::LZMA_FILTER_SPARC, ptr::null_mut()),
+                        Filter::X86 => (lzma_sys::LZMA_FILTER_X86, ptr::null_mut()),
+                    };
+                    raw_filters.push(lzma_sys::lzma_filter { id, options });
+                }
+                raw_filters.push(lzma_sys::lzma_filter {
+                    id: lzma_sys::LZMA_VLI_UNKNOWN,
+                    options: ptr::null_mut(),
+                });
+                Ok(Self {
+                    filters: raw_filters,
+                    _deltas: deltas,
+                    _lzmas: lzmas,
+                })
+            }
+        }
+
+        /// Compress `input` through `filters`, into an xz stream or, with the RAW format, the bare
+        /// output of the chain, which only `decompress` given the same chain can decode.
+        pub(super) fn compress<W: Write + ?Sized, R: Read>(
+            mut input: R,
+            output: &mut W,
+            preset: Option<u32>,
+            format: Option<Format>,
+            check: Option<Check>,
+            filters: Option<FilterChain>,
+            options: Option<Options>,
+        ) -> io::Result<usize> {
+            let filters = match (format.as_ref(), filters) {
+                (_, Some(filters)) => filters,
+                (Some(Format::RAW), None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "The RAW format needs a filter chain",
+                    ))
+                }
+                (_, None) => FilterChain::default(),
+            };
+            let chain = RawChain::new(filters, preset, &options.unwrap_or_default())?;
+            let mut stream: lzma_sys::lzma_stream = unsafe { mem::zeroed() };
+            let ret = match format.unwrap_or_default() {
+                Format::AUTO | Format::XZ => {
+                    let check: libcramjam::xz::Check = check.unwrap_or(Check::Crc64).into();
+                    let check: libcramjam::xz::xz2::stream::Check = check.into();
+                    unsafe { lzma_sys::lzma_stream_encoder(&mut stream, chain.filters.as_ptr(), check as _) }
+                }
+                Format::RAW => {
+                    if check.is_some_and(|check| check != Check::None) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "The RAW format has no integrity check, use XZ for one",
+                        ));
+                    }
+                    unsafe { lzma_sys::lzma_raw_encoder(&mut stream, chain.filters.as_ptr()) }
+                }
+                Format::ALONE => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "The ALONE format does not support filter chains, use XZ or RAW",
+                    ))
+                }
+            };
+            if ret != lzma_sys::LZMA_OK {
+                return Err(lzma_error(ret));
+            }
+            let result = code(&mut stream, &mut input, output);
+            unsafe { lzma_sys::lzma_end(&mut stream) };
+            result
+        }
+
+        /// Decompress the RAW format output of `compress` with the same `filters`.
+        pub(super) fn decompress<W: Write + ?Sized, R: Read>(
+            mut input: R,
+            output: &mut W,
+            filters: FilterChain,
+        ) -> io::Result<usize> {
+            let chain = RawChain::new(filters, None, &Options::default())?;
+            let mut stream: lzma_sys::lzma_stream = unsafe { mem::zeroed() };
+            let ret = unsafe { lzma_sys::lzma_raw_decoder(&mut stream, chain.filters.as_ptr()) };
+            if ret != lzma_sys::LZMA_OK {
+                return Err(lzma_error(ret));
+            }
+            let result = code(&mut stream, &mut input, output);
+            unsafe { lzma_sys::lzma_end(&mut stream) };
+            result
+        }
+
+        /// Run `stream`, an encoder or decoder, over all of `input`
+        fn code<W: Write + ?Sized, R: Read>(
+            stream: &mut lzma_sys::lzma_stream,
+            input: &mut R,
+            output: &mut W,
+        ) -> io::Result<usize> {
+            let mut inbuf = vec![0; BUF_SIZE];
+            let mut outbuf = vec![0; BUF_SIZE];
+            let mut action = lzma_sys::LZMA_RUN;
+            let mut n_bytes = 0;
+            loop {
+                if stream.avail_in == 0 && action == lzma_sys::LZMA_RUN {
+                    let n = input.read(&mut inbuf)?;
+                    if n == 0 {
+                        action = lzma_sys::LZMA_FINISH;
+                    }
+                    stream.next_in = inbuf.as_ptr();
+                    stream.avail_in = n;
+                }
+                stream.next_out = outbuf.as_mut_ptr();
+                stream.avail_out = outbuf.len();
+                let ret = unsafe { lzma_sys::lzma_code(stream, action) };
+
+                let produced = outbuf.len() - stream.avail_out;
+                output.write_all(&outbuf[..produced])?;
+                n_bytes += produced;
+                match ret {
+                    lzma_sys::LZMA_OK => continue,
+                    lzma_sys::LZMA_STREAM_END => return Ok(n_bytes),
+                    err => return Err(lzma_error(err)),
+                }
+            }
+        }
+
+        fn lzma_options(options: &Options, preset: Option<u32>) -> io::Result<lzma_sys::lzma_options_lzma> {
+            let mut raw: lzma_sys::lzma_options_lzma = unsafe { mem::zeroed() };
+            let preset = options.preset.or(preset).unwrap_or(6);
+            if unsafe { lzma_sys::lzma_lzma_preset(&mut raw, preset) } != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unsupported preset: {}", preset),
+                ));
+            }
+            if let Some(dict_size) = options.dict_size {
+                raw.dict_size = dict_size;
+            }
+            if let Some(lc) = options.lc {
+                raw.lc = lc;
+            }
+            if let Some(lp) = options.lp {
+                raw.lp = lp;
+            }
+            if let Some(pb) = options.pb {
+                raw.pb = pb;
+            }
+            if let Some(mode) = options.mode.clone() {
+                raw.mode = Into::<libcramjam::xz::Mode>::into(mode) as _;
+            }
+            if let Some(nice_len) = options.nice_len {
+                raw.nice_len = nice_len as _;
+            }
+            if let Some(mf) = options.mf.clone() {
+                raw.mf = Into::<libcramjam::xz::MatchFinder>::into(mf) as _;
+            }
+            if let Some(depth) = options.depth {
+                raw.depth = depth as _;
+            }
+            Ok(raw)
+        }
+
+        fn lzma_error(ret: lzma_sys::lzma_ret) -> io::Error {
+            let msg = match ret {
+                lzma_sys::LZMA_MEM_ERROR => "Cannot allocate memory",
+                lzma_sys::LZMA_OPTIONS_ERROR => "Unsupported filter chain or options",
+                lzma_sys::LZMA_UNSUPPORTED_CHECK => "Unsupported integrity check",
+                _ => "Unexpected liblzma error",
+            };
+            io::Error::other(format!("{} (lzma_ret={})", msg, ret))
+        }
+    }
 }
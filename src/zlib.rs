@@ -0,0 +1,254 @@
+//! zlib de/compression interface
+use pyo3::prelude::*;
+
+/// zlib de/compression interface
+///
+/// Deflate streams wrapped in a zlib header and Adler-32 trailer, as written and read by Python's
+/// stdlib `zlib` module; `cramjam.deflate` is the same compression without the wrapper.
+#[pymodule]
+pub mod zlib {
+
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::{IncrementalDecoder, RustyBuffer};
+    use crate::{AsBytes, BytesType};
+    use libcramjam::deflate::flate2::{Compression, Decompress, FlushDecompress, Status};
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+    use pyo3::PyResult;
+    use std::io::{Cursor, Read, Write};
+
+    const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+    const DECOMPRESS_CHUNK_LEN: usize = 32 * 1024;
+    const FLUSH_MODES: [&str; 1] = ["sync"];
+
+    /// Zlib decompression; concatenated zlib streams are decompressed one after another.
+    ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.decompress(compressed_bytes, output_len=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None, reject_nested=false, max_input_len=None, max_output_len=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        let buffer = crate::generic!(
+            py,
+            decompress_with[data],
+            output_len = output_len,
+            max_output_len = max_output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::detect::check_nested(buffer, reject_nested)
+    }
+
+    /// Zlib compression.
+    ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.compress(b'some bytes here', level=5, output_len=Optional[int])  # level defaults to 6
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, level=None, output_len=None, return_len=false))]
+    pub fn compress(
+        py: Python,
+        data: BytesType,
+        level: Option<u32>,
+        output_len: Option<usize>,
+        return_len: bool,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        let buffer = crate::generic!(py, compress_with[data], output_len = output_len, level)
+            .map_err(CompressionError::from_err)?;
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
+    }
+
+    /// Compress directly into an output buffer
+    #[pyfunction]
+    #[pyo3(signature = (input, output, level=None))]
+    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
+        _level::check_level(level)?;
+        crate::generic!(py, compress_with[input, output], level).map_err(CompressionError::from_err)
+    }
+
+    /// Decompress directly into an output buffer
+    #[pyfunction]
+    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
+        crate::generic!(py, decompress_with[input, output]).map_err(DecompressionError::from_err)
+    }
+
+    /// Zlib compression with byte-stable output, suitable for comparing against golden files; the
+    /// zlib header records only the level, so the same data and level always give the same bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.compress_reproducible(b'some bytes here', level=6)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, level=None))]
+    pub fn compress_reproducible(py: Python, data: BytesType, level: Option<u32>) -> PyResult<RustyBuffer> {
+        _level::check_level(level)?;
+        crate::generic!(py, compress_with[data], output_len = None, level).map_err(CompressionError::from_err)
+    }
+
+    /// Zlib Compressor object for streaming compression
+    #[pyclass]
+    pub struct Compressor {
+        inner: Option<libcramjam::deflate::flate2::write::ZlibEncoder<Cursor<Vec<u8>>>>,
+    }
+
+    #[pymethods]
+    impl Compressor {
+        /// Initialize a new `Compressor` instance.
+        #[new]
+        #[pyo3(signature = (level=None))]
+        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+            let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+            let inner =
+                libcramjam::deflate::flate2::write::ZlibEncoder::new(Cursor::new(vec![]), Compression::new(level));
+            Ok(Self { inner: Some(inner) })
+        }
+
+        /// Compress input into the current compressor's stream.
+        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
+            crate::io::stream_compress(&mut self.inner, input)
+        }
+
+        /// Flush and return current compressed stream.
+        ///
+        /// `mode` may only be "sync", zlib's Z_SYNC_FLUSH, aligning to a byte boundary with an
+        /// empty stored block; see `cramjam.deflate.Compressor` for the full and partial flushes.
+        #[pyo3(signature = (mode=None))]
+        pub fn flush(&mut self, mode: Option<&str>) -> PyResult<RustyBuffer> {
+            if let Some(other) = mode.filter(|mode| !FLUSH_MODES.contains(mode)) {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown flush mode '{}', expected one of {:?}",
+                    other, FLUSH_MODES
+                )));
+            }
+            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        }
+
+        #[doc = crate::flush_modes_doc!()]
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            FLUSH_MODES.to_vec()
+        }
+
+        /// Consume the current compressor state and return the compressed stream
+        /// **NB** The compressor will not be usable after this method is called.
+        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
+            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+        }
+    }
+
+    pub(crate) mod _level {
+        use super::*;
+        crate::make_level_range!(zlib, u32, 0, 9);
+    }
+    #[pymodule_export]
+    use _level::{max_level, min_level};
+
+    mod _decompressor {
+        use super::*;
+        crate::make_decompressor!(zlib, decoder = StreamDecoder::new(true));
+    }
+    #[pymodule_export]
+    use _decompressor::Decompressor;
+
+    /// Zlib compression of everything read from `input`
+    pub(crate) fn compress_with<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<u32>,
+    ) -> std::io::Result<usize> {
+        let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+        let mut encoder = libcramjam::deflate::flate2::read::ZlibEncoder::new(input, Compression::new(level));
+        let n_bytes = crate::io::copy(&mut encoder, output)?;
+        Ok(n_bytes as usize)
+    }
+
+    /// Zlib decompression of everything read from `input`, which must end on a stream boundary
+    pub(crate) fn decompress_with<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> std::io::Result<usize> {
+        let mut compressed = vec![];
+        input.read_to_end(&mut compressed)?;
+        let mut decoder = StreamDecoder::new(true);
+        let (mut out, mut n_bytes) = (vec![], 0);
+        // Fed a chunk at a time so the output is written, (and limited by `max_output_len`) as it's decoded
+        for chunk in compressed.chunks(DECOMPRESS_CHUNK_LEN) {
+            decoder.feed(chunk, &mut out, None)?;
+            output.write_all(&out)?;
+            n_bytes += out.len();
+            out.clear();
+        }
+        decoder.finish(&mut out, None)?;
+        output.write_all(&out)?;
+        Ok(n_bytes + out.len())
+    }
+
+    /// Incremental decoder of concatenated zlib, or with `zlib_header=false` raw deflate, streams
+    pub(crate) struct StreamDecoder {
+        inflate: Decompress,
+        zlib_header: bool,
+        /// Whether the input so far ends part way through a stream
+        in_stream: bool,
+    }
+
+    impl StreamDecoder {
+        pub(crate) fn new(zlib_header: bool) -> Self {
+            Self {
+                inflate: Decompress::new(zlib_header),
+                zlib_header,
+                in_stream: false,
+            }
+        }
+    }
+
+    impl IncrementalDecoder for StreamDecoder {
+        fn feed(&mut self, input: &[u8], output: &mut Vec<u8>, max_len: Option<usize>) -> std::io::Result<()> {
+            let (start, mut pos) = (output.len(), 0);
+            while pos < input.len() {
+                let (total_in, produced) = (self.inflate.total_in(), output.len());
+                output.reserve(DECOMPRESS_CHUNK_LEN);
+                let status = self
+                    .inflate
+                    .decompress_vec(&input[pos..], output, FlushDecompress::None)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                pos += (self.inflate.total_in() - total_in) as usize;
+                crate::io::check_output_len(output.len() - start, max_len)?;
+                self.in_stream = true;
+                if status == Status::StreamEnd {
+                    self.inflate.reset(self.zlib_header);
+                    self.in_stream = false;
+                } else if self.inflate.total_in() == total_in && output.len() == produced {
+                    break;
+                }
+            }
+            Ok(())
+        }
+
+        fn finish(&mut self, _output: &mut Vec<u8>, _max_len: Option<usize>) -> std::io::Result<()> {
+            if self.in_stream {
+                let format = if self.zlib_header { "zlib" } else { "deflate" };
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("Input ends part way through a {} stream", format),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
@@ -4,78 +4,1540 @@ use pyo3::prelude::*;
 /// zstd de/compression interface
 #[pymodule]
 pub mod zstd {
-    use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::exceptions::{with_zstd_code, CompressionError, DecompressionError};
+    use crate::io::{LimitedWriter, PythonReader, PythonWriter, RustyBuffer};
     use crate::{AsBytes, BytesType};
+    use libcramjam::deflate::flate2::Crc;
+    use libcramjam::zstd::zstd::dict::EncoderDictionary;
+    use libcramjam::zstd::zstd::stream::read::{Decoder, Encoder};
+    use libcramjam::zstd::zstd::zstd_safe::{
+        get_error_name, CCtx, CParameter, DCtx, DParameter, FrameFormat, InBuffer, OutBuffer,
+    };
+    use pyo3::exceptions::{PyException, PyValueError};
     use pyo3::prelude::*;
+    use pyo3::types::{PyByteArray, PyDict, PyIterator};
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::collections::{BTreeMap, HashMap};
+    use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
 
     const DEFAULT_COMPRESSION_LEVEL: i32 = 0;
+    const DEFAULT_WINDOW_LOG_MAX: u32 = 27; // ZSTD_WINDOWLOG_LIMIT_DEFAULT
+    const ZSTD_ERROR_WINDOW_TOO_LARGE: usize = 16; // ZSTD_error_frameParameter_windowTooLarge
+    const RECORD_PREFIX_LEN: usize = std::mem::size_of::<u32>();
+    const SEEKABLE_FRAME_SIZE: usize = 1 << 20;
+    const SEEKABLE_MAGIC: u32 = 0x8F92EAB1;
+    const SEEK_TABLE_SKIPPABLE_MAGIC: u32 = 0x184D2A5E;
+    const SEEK_TABLE_FOOTER_LEN: usize = 9;
+    const FRAME_MAGIC: u32 = 0xFD2FB528;
+    const ARRAYS_MAGIC: &[u8; 4] = b"CJAR";
+    const PREALLOCATE_MAX_RATIO: usize = 64;
+    const PROGRESS_CHUNK_LEN: usize = 1 << 20;
+    const PADDING_SKIPPABLE_MAGIC: u32 = 0x184D2A50;
+    const SKIPPABLE_HEADER_LEN: usize = 8;
+
+    /// Dictionaries given to `register_dictionary`, by id
+    static DICTIONARIES: Mutex<BTreeMap<u32, Vec<u8>>> = Mutex::new(BTreeMap::new());
 
     /// ZSTD decompression.
     ///
+    /// `magicless` expects frames written with `compress(..., magicless=True)`, which omit
+    /// the 4-byte magic number; frames must be decompressed with the same setting they were compressed with.
+    /// Likewise `dictionary` must be the one the data was compressed with. Frames compressed with a trained
+    /// dictionary record its id, and giving another dictionary raises a `DecompressionError`; raw content
+    /// dictionaries have no id, so a mismatch is only caught if the data was compressed with `checksum=True`.
+    ///
+    /// `params` sets advanced decompression parameters by name, see `DecompressParams`;
+    /// a `ValueError` is raised for unknown names.
+    ///
+    #[doc = crate::decompress_guards_doc!()]
+    ///
+    /// Without `output_len`, the output is preallocated from the decompressed size recorded in the frame
+    /// headers, when they record one, see `decompressed_size`, capped to `max_output_len`.
+    ///
+    /// With `require_exact=True`, `data` must be exactly one frame: a `DecompressionError` is raised if
+    /// any bytes, be they another frame or anything else, follow it. Not supported with `magicless`.
+    ///
+    /// `prefix` must be the one given to `compress(..., prefix=...)`; it only applies to the first frame
+    /// of `data` and can't be combined with `dictionary`.
+    ///
+    /// `window_log_max` raises the largest window, as a power of 2, the decoder will allocate for a frame
+    /// from its default of 2^27 bytes; ie. for frames compressed with a larger `window_log`. Frames
+    /// needing a larger window than allowed raise a `DecompressionError` saying so. A larger window
+    /// means more memory is allocated to decompress such frames.
+    ///
+    /// With `return_padding_info=True`, a tuple of the output and a `dict` describing the skippable
+    /// frames passed over is returned, ie. the padding of `compress(..., min_output_len=...)`:
+    /// ```bash
+    /// skippable_frames: number of skippable frames in `data`
+    /// skippable_bytes: their total length in bytes, headers included
+    /// ```
+    /// Not supported with `magicless` or for File input.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.zstd.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.zstd.decompress(compressed_bytes, output_len=Optional[int], magicless=False, dictionary=None)
+    /// >>> cramjam.zstd.decompress(compressed_bytes, params={"windowLogMax": 27})
+    /// >>> output, padding = cramjam.zstd.decompress(compressed_bytes, return_padding_info=True)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::zstd::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, magicless=false, params=None, dictionary=None, reject_nested=false, max_input_len=None, require_exact=false, prefix=None, window_log_max=None, max_output_len=None, return_padding_info=false))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        magicless: bool,
+        params: Option<DecompressParams>,
+        dictionary: Option<BytesType>,
+        reject_nested: bool,
+        max_input_len: Option<usize>,
+        require_exact: bool,
+        prefix: Option<BytesType>,
+        window_log_max: Option<u32>,
+        max_output_len: Option<usize>,
+        return_padding_info: bool,
+    ) -> PyResult<PyObject> {
+        crate::io::check_input_len(&data, max_input_len)?;
+        if prefix.is_some() && dictionary.is_some() {
+            return Err(PyValueError::new_err("prefix can't be combined with dictionary"));
+        }
+        if require_exact {
+            if magicless {
+                return Err(PyValueError::new_err("require_exact can't be combined with magicless"));
+            }
+            crate::io::check_exact(&data, frame_len)?;
+        }
+        let padding = match (return_padding_info, &data) {
+            (false, _) => None,
+            (true, _) if magicless => {
+                return Err(PyValueError::new_err(
+                    "return_padding_info can't be combined with magicless",
+                ))
+            }
+            (true, BytesType::RustyFile(_)) => {
+                return Err(PyValueError::new_err(
+                    "return_padding_info isn't supported for File input",
+                ))
+            }
+            (true, _) => Some(skippable_frames(data.as_bytes())),
+        };
+        let output_len = output_len.or_else(|| match (&data, magicless) {
+            (BytesType::RustyFile(_), _) | (_, true) => None,
+            _ => preallocate_len(data.as_bytes()),
+        });
+        let mut params = params.unwrap_or_default();
+        if magicless {
+            params.0.push(DParameter::Format(FrameFormat::Magicless));
+        }
+        if let Some(window_log_max) = window_log_max {
+            params.0.push(DParameter::WindowLogMax(window_log_max));
+        }
+        let buffer = if let Some(prefix) = prefix.as_ref() {
+            let params = &params;
+            let prefix = &*prefix.read_bytes()?;
+            crate::generic!(
+                py,
+                decompress_with_prefix[data],
+                output_len = output_len,
+                max_output_len = max_output_len,
+                params,
+                prefix
+            )
+        } else if !params.is_empty() || dictionary.is_some() {
+            let params = &params;
+            let dictionary = dictionary.as_ref().map(|d| d.read_bytes()).transpose()?;
+            let dictionary = dictionary.as_deref().unwrap_or_default();
+            crate::generic!(
+                py,
+                decompress_with[data],
+                output_len = output_len,
+                max_output_len = max_output_len,
+                params,
+                dictionary
+            )
+        } else {
+            crate::generic!(
+                py,
+                libcramjam::zstd::decompress[data],
+                output_len = output_len,
+                max_output_len = max_output_len
+            )
+        }
+        .map_err(|err| window_too_large(err, window_log_max))?;
+        let buffer = crate::detect::check_nested(buffer, reject_nested)?;
+        match padding {
+            Some((n_frames, n_bytes)) => {
+                let info = PyDict::new_bound(py);
+                info.set_item("skippable_frames", n_frames)?;
+                info.set_item("skippable_bytes", n_bytes)?;
+                Ok((buffer, info).into_py(py))
+            }
+            None => Ok(buffer.into_py(py)),
+        }
+    }
+
+    /// Number and total length of the skippable frames in `data`, stopping at the first invalid frame;
+    /// which is left for decompression to raise.
+    fn skippable_frames(data: &[u8]) -> (usize, usize) {
+        let (mut n_frames, mut n_bytes, mut pos) = (0, 0, 0);
+        while pos < data.len() {
+            let remaining = &data[pos..];
+            let frame_len = match libcramjam::zstd::zstd::zstd_safe::find_frame_compressed_size(remaining) {
+                Ok(frame_len) => frame_len,
+                Err(_) => break,
+            };
+            // Skippable frames have magic numbers 0x184D2A50..=0x184D2A5F
+            if u32::from_le_bytes(remaining[..4].try_into().unwrap()) >> 4 == PADDING_SKIPPABLE_MAGIC >> 4 {
+                n_frames += 1;
+                n_bytes += frame_len;
+            }
+            pos += frame_len;
+        }
+        (n_frames, n_bytes)
+    }
+
+    /// `DecompressionError` for `err`, explaining how to decode the frame if it needs a larger window
+    /// than the decoder allows
+    fn window_too_large(err: std::io::Error, window_log_max: Option<u32>) -> PyErr {
+        let code = 0usize.wrapping_sub(ZSTD_ERROR_WINDOW_TOO_LARGE);
+        if err.to_string() != get_error_name(code) {
+            return DecompressionError::from_err(err);
+        }
+        let limit = window_log_max.unwrap_or(DEFAULT_WINDOW_LOG_MAX);
+        let pyerr = with_zstd_code(
+            DecompressionError::new_err(format!(
+                "Frame needs a window larger than the decoder's limit of 2^{} bytes; \
+                pass a larger window_log_max to decompress it",
+                limit
+            )),
+            code,
+        );
+        Python::with_gil(|py| pyerr.set_cause(py, Some(DecompressionError::from_err(err))));
+        pyerr
     }
 
     /// ZSTD compression.
     ///
+    /// `magicless` omits the 4-byte magic number from the frame, for containers which already delimit
+    /// frames; such frames can only be read by `decompress(..., magicless=True)`.
+    ///
+    /// `dictionary` is raw content or a trained zstd dictionary, which must then also be given to `decompress`.
+    ///
+    /// `checksum=True` appends a checksum of the content to the frame, verified on decompression. It's
+    /// off by default and the frame's checksum flag is then never set, as some peers reject frames
+    /// carrying one. Either way, output is byte-stable: the same input, level and options always give
+    /// the same bytes for a given version of the bundled zstd library.
+    ///
+    #[doc = crate::compress_return_len_doc!()]
+    ///
+    /// `progress` is called as `progress(input_consumed, output_produced)` after each 1MiB of `data`
+    /// is compressed, and once more with the totals when done; ie. to report upload progress. It's
+    /// called with the GIL held, while the compression in between releases it. Exceptions it raises
+    /// abort the compression. It can't be combined with `scratch`.
+    ///
+    /// `prefix` is raw content, ie. a typical message, which the start of `data` is compressed against
+    /// as if it had come just before it; unlike a `dictionary` it needs no training or digesting, so
+    /// suits seeding small structured messages with a known template. It must also be given to
+    /// `decompress`, only applies to the single frame written, and can't be combined with `dictionary`,
+    /// `scratch` or `progress`.
+    ///
+    /// `workers` compresses with that many threads, for large inputs where compression at high levels
+    /// is the bottleneck; the output remains a standard zstd frame, decoded as any other. `None`, `0`
+    /// or `1` compress on the calling thread, as before.
+    ///
+    /// `enable_ldm=True` turns on long distance matching, which finds repeats far further back than
+    /// the window of the level, ie. in multi-megabyte logs; `window_log` sets that window to 2^n bytes.
+    /// A larger window raises the memory use of both compression and decompression, and frames with a
+    /// window over 2^27 bytes need `decompress(..., window_log_max=n)` to be read.
+    ///
+    /// `deterministic=True` makes the output byte-identical across runs and machines whatever `workers`
+    /// is, for reproducible pipelines, by compressing on the calling thread; so it gives up the speed-up
+    /// of `workers`, taking as long as a single-threaded `compress`.
+    ///
+    /// `min_output_len` pads the output to at least that many bytes with a skippable frame of zeros
+    /// after the compressed frame, which decoders pass over; ie. so message sizes don't give away
+    /// the content of short messages. As a skippable frame is at least 8 bytes, output a few bytes
+    /// short of the floor may be padded a little past it. It can't be combined with `magicless`,
+    /// whose decoder doesn't recognise skippable frames.
+    ///
+    /// `time_limit_ms` fails the compression with a `CompressionError` if it takes longer than that many
+    /// milliseconds, rather than returning late; `data` is compressed a 1MiB chunk at a time as for
+    /// `progress`, the limit being checked after each, so it can be overrun by the time one chunk takes.
+    /// It can't be combined with `scratch` or `prefix`.
+    ///
+    /// `scratch` is a `cramjam.Buffer` whose allocation is reused for the output, avoiding a new
+    /// allocation per call once it has grown large enough. Its contents are replaced, and it is itself
+    /// what's returned; so a result is only valid until `scratch` is next passed in, copy it out
+    /// (ie. `bytes(result)`) to keep it. `data` can't be `scratch`.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.zstd.compress(b'some bytes here', level=0, output_len=Optional[int])  # level defaults to 11
+    /// >>> cramjam.zstd.compress(b'some bytes here', level=0, output_len=Optional[int], magicless=False, dictionary=None)  # level defaults to 11
+    /// >>> cramjam.zstd.compress(b'yes', min_output_len=256)  # padded to at least 256 bytes
+    /// >>> cramjam.zstd.compress(row_group, level=19, workers=4)
+    /// >>> scratch = cramjam.Buffer()
+    /// >>> for record in records:
+    /// ...     sock.sendall(cramjam.zstd.compress(record, scratch=scratch))
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, magicless=false, dictionary=None, return_len=false, scratch=None, checksum=false, progress=None, min_output_len=None, prefix=None, workers=None, deterministic=false, enable_ldm=false, window_log=None, time_limit_ms=None))]
+    #[allow(clippy::too_many_arguments)]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<i32>,
         output_len: Option<usize>,
+        magicless: bool,
+        dictionary: Option<BytesType>,
+        return_len: bool,
+        scratch: Option<Bound<'_, RustyBuffer>>,
+        checksum: bool,
+        progress: Option<Bound<'_, PyAny>>,
+        min_output_len: Option<usize>,
+        prefix: Option<BytesType>,
+        workers: Option<u32>,
+        deterministic: bool,
+        enable_ldm: bool,
+        window_log: Option<u32>,
+        time_limit_ms: Option<u64>,
+    ) -> PyResult<PyObject> {
+        _level::check_level(level)?;
+        check_conflicts(&[
+            ("prefix", prefix.is_some()),
+            ("dictionary", dictionary.is_some()),
+            ("scratch", scratch.is_some()),
+            ("progress", progress.is_some()),
+            ("time_limit_ms", time_limit_ms.is_some()),
+            ("magicless", magicless),
+            ("min_output_len", min_output_len.is_some()),
+        ])?;
+        let options = CompressOptions {
+            level,
+            output_len,
+            magicless,
+            checksum,
+            // a single worker would only move compression off the calling thread, not speed it up
+            workers: workers.filter(|n| *n > 1 && !deterministic).unwrap_or(0),
+            params: window_params(enable_ldm, window_log),
+            min_output_len: min_output_len.unwrap_or(0),
+            time_limit: time_limit_ms.map(std::time::Duration::from_millis),
+        };
+        if progress.is_some() || options.time_limit.is_some() {
+            let buffer = compress_progress(py, data, dictionary, progress.as_ref(), &options)?;
+            return Ok(crate::io::maybe_with_len(py, buffer, return_len));
+        }
+        if let Some(scratch) = scratch {
+            return compress_scratch(py, data, scratch, dictionary, return_len, &options);
+        }
+        let CompressOptions {
+            level,
+            magicless,
+            checksum,
+            workers,
+            ..
+        } = options;
+        let params = &options.params[..];
+        let mut buffer = if let Some(prefix) = prefix.as_ref() {
+            let prefix = &*prefix.read_bytes()?;
+            crate::generic!(
+                py,
+                compress_with_prefix[data],
+                output_len = output_len,
+                level,
+                magicless,
+                checksum,
+                workers,
+                params,
+                prefix
+            )
+        } else if magicless || checksum || dictionary.is_some() || workers > 0 || !params.is_empty() {
+            let dictionary = dictionary.map(|d| d.read_bytes().map(|d| prepare_dictionary(&d, level)));
+            let dictionary = dictionary.transpose()?;
+            let dictionary = dictionary.as_ref();
+            crate::generic!(
+                py,
+                compress_with[data],
+                output_len = output_len,
+                level,
+                magicless,
+                checksum,
+                workers,
+                params,
+                dictionary
+            )
+        } else {
+            crate::generic!(py, libcramjam::zstd::compress[data], output_len = output_len, level)
+        }
+        .map_err(CompressionError::from_err)?;
+        pad_to_len(buffer.inner.get_mut(), options.min_output_len);
+        Ok(crate::io::maybe_with_len(py, buffer, return_len))
+    }
+
+    /// Arguments of `compress` which can't be given together
+    const COMPRESS_CONFLICTS: [(&str, &str); 7] = [
+        ("prefix", "dictionary"),
+        ("prefix", "scratch"),
+        ("prefix", "progress"),
+        ("prefix", "time_limit_ms"),
+        ("scratch", "progress"),
+        ("scratch", "time_limit_ms"),
+        ("magicless", "min_output_len"),
+    ];
+
+    /// Raise `ValueError` if any pair of `COMPRESS_CONFLICTS` is given, by whether each argument is
+    fn check_conflicts(given: &[(&str, bool)]) -> PyResult<()> {
+        let is_given = |name: &str| given.iter().any(|(arg, is_given)| *arg == name && *is_given);
+        match COMPRESS_CONFLICTS.iter().find(|(a, b)| is_given(a) && is_given(b)) {
+            Some((a, b)) => Err(PyValueError::new_err(format!("{} can't be combined with {}", a, b))),
+            None => Ok(()),
+        }
+    }
+
+    /// Settings of a `compress` call which apply whichever way it compresses, see `compress`
+    struct CompressOptions {
+        level: Option<i32>,
+        output_len: Option<usize>,
+        magicless: bool,
+        checksum: bool,
+        /// Number of threads compressing, 0 to compress on the calling thread
+        workers: u32,
+        params: Vec<CParameter>,
+        min_output_len: usize,
+        time_limit: Option<std::time::Duration>,
+    }
+
+    /// Compression parameters for `enable_ldm` and `window_log`, see `compress`
+    fn window_params(enable_ldm: bool, window_log: Option<u32>) -> Vec<CParameter> {
+        let mut params = vec![];
+        if enable_ldm {
+            params.push(CParameter::EnableLongDistanceMatching(true));
+        }
+        if let Some(window_log) = window_log {
+            params.push(CParameter::WindowLog(window_log));
+        }
+        params
+    }
+
+    /// Append a skippable frame of zeros to `out` if it's shorter than `min_len`, bringing it to at
+    /// least `min_len` bytes.
+    fn pad_to_len(out: &mut Vec<u8>, min_len: usize) {
+        if out.len() >= min_len {
+            return;
+        }
+        let payload_len = (min_len - out.len()).saturating_sub(SKIPPABLE_HEADER_LEN);
+        out.extend_from_slice(&PADDING_SKIPPABLE_MAGIC.to_le_bytes());
+        out.extend_from_slice(&(payload_len as u32).to_le_bytes());
+        out.resize(out.len() + payload_len, 0);
+    }
+
+    /// `compress` a `PROGRESS_CHUNK_LEN` of `data` at a time, reporting to `progress` after each and
+    /// failing once `options.time_limit` has passed.
+    fn compress_progress(
+        py: Python,
+        data: BytesType,
+        dictionary: Option<BytesType>,
+        progress: Option<&Bound<'_, PyAny>>,
+        options: &CompressOptions,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::zstd::compress[data], output_len = output_len, level)
-            .map_err(CompressionError::from_err)
+        let CompressOptions {
+            level,
+            output_len,
+            magicless,
+            checksum,
+            workers,
+            time_limit,
+            ..
+        } = *options;
+        let start = std::time::Instant::now();
+        let check_time_limit = || match time_limit {
+            Some(limit) if start.elapsed() > limit => Err(CompressionError::new_err("time limit exceeded")),
+            _ => Ok(()),
+        };
+        let dictionary = dictionary.map(|d| d.read_bytes().map(|d| prepare_dictionary(&d, level)));
+        let dictionary = dictionary.transpose()?;
+        let out = Vec::with_capacity(output_len.unwrap_or(0));
+        let mut encoder = match &dictionary {
+            Some(dictionary) => {
+                libcramjam::zstd::zstd::stream::write::Encoder::with_prepared_dictionary(out, dictionary)
+            }
+            None => libcramjam::zstd::zstd::stream::write::Encoder::new(out, level.unwrap_or(DEFAULT_COMPRESSION_LEVEL)),
+        }
+        .map_err(CompressionError::from_err)?;
+        encoder
+            .include_magicbytes(!magicless)
+            .map_err(CompressionError::from_err)?;
+        encoder.include_checksum(checksum).map_err(CompressionError::from_err)?;
+        if workers > 0 {
+            encoder.multithread(workers).map_err(CompressionError::from_err)?;
+        }
+        for param in options.params.iter() {
+            encoder.set_parameter(*param).map_err(CompressionError::from_err)?;
+        }
+
+        let mut consumed = 0;
+        for chunk in data.read_bytes()?.chunks(PROGRESS_CHUNK_LEN) {
+            py.allow_threads(|| encoder.write_all(chunk))
+                .map_err(CompressionError::from_err)?;
+            consumed += chunk.len();
+            check_time_limit()?;
+            if let Some(progress) = progress {
+                progress.call1((consumed, encoder.get_ref().len()))?;
+            }
+        }
+        let mut out = py
+            .allow_threads(|| encoder.finish())
+            .map_err(CompressionError::from_err)?;
+        check_time_limit()?;
+        if let Some(progress) = progress {
+            progress.call1((consumed, out.len()))?;
+        }
+        pad_to_len(&mut out, options.min_output_len);
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// `compress` into the reused allocation of `scratch`, returning `scratch` itself.
+    fn compress_scratch(
+        py: Python,
+        data: BytesType,
+        scratch: Bound<'_, RustyBuffer>,
+        dictionary: Option<BytesType>,
+        return_len: bool,
+        options: &CompressOptions,
+    ) -> PyResult<PyObject> {
+        let CompressOptions {
+            level,
+            output_len,
+            magicless,
+            checksum,
+            workers,
+            min_output_len,
+            ..
+        } = *options;
+        if let BytesType::RustyBuffer(buffer) = &data {
+            if buffer.is(&scratch) {
+                return Err(PyValueError::new_err("scratch can't also be the data being compressed"));
+            }
+        }
+        scratch.try_borrow()?.check_not_exported()?;
+        let dictionary = dictionary.map(|d| d.read_bytes().map(|d| prepare_dictionary(&d, level)));
+        let dictionary = dictionary.transpose()?;
+        let bytes = &*data.read_bytes()?;
+        let mut out = std::mem::take(scratch.try_borrow_mut()?.inner.get_mut());
+        out.clear();
+        out.reserve(output_len.unwrap_or(0));
+        let result = py.allow_threads(|| {
+            compress_with(
+                bytes,
+                &mut out,
+                level,
+                magicless,
+                checksum,
+                workers,
+                &options.params,
+                dictionary.as_ref(),
+            )
+            .map(|_| pad_to_len(&mut out, min_output_len))
+        });
+        let len = out.len();
+        scratch.try_borrow_mut()?.inner = Cursor::new(out);
+        result.map_err(CompressionError::from_err)?;
+        Ok(match return_len {
+            true => (scratch, len).into_py(py),
+            false => scratch.into_py(py),
+        })
+    }
+
+    /// ZSTD compression of many inputs in parallel, each into its own frame, returned in the same order.
+    ///
+    /// The GIL is released for the whole batch, and when given, `dictionary` is digested once
+    /// and shared by all inputs; the output of each is the same as `compress(input, level, dictionary=...)`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.compress_batch([b'first doc', b'second doc'], level=3, dictionary=dictionary)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (inputs, level=None, dictionary=None))]
+    pub fn compress_batch(
+        py: Python,
+        inputs: Vec<BytesType>,
+        level: Option<i32>,
+        dictionary: Option<BytesType>,
+    ) -> PyResult<Vec<RustyBuffer>> {
+        let inputs = inputs
+            .iter()
+            .map(|input| input.read_bytes())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let dictionary = dictionary.as_ref().map(|d| d.read_bytes()).transpose()?;
+        let outputs = py
+            .allow_threads(|| {
+                let dictionary = dictionary.as_deref().map(|d| prepare_dictionary(d, level));
+                let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+                let chunk_len = inputs.len().div_ceil(n_threads).max(1);
+                std::thread::scope(|scope| {
+                    let workers = inputs
+                        .chunks(chunk_len)
+                        .map(|chunk| {
+                            let dictionary = dictionary.as_ref();
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .map(|input| {
+                                        let mut out = vec![];
+                                        compress_with(&input[..], &mut out, level, false, false, 0, &[], dictionary)
+                                            .map(|_| out)
+                                    })
+                                    .collect::<std::io::Result<Vec<_>>>()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    workers
+                        .into_iter()
+                        .map(|worker| worker.join().expect("zstd batch worker panicked"))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+            })
+            .map_err(CompressionError::from_err)?;
+        Ok(outputs.into_iter().flatten().map(RustyBuffer::from).collect())
+    }
+
+    /// ZSTD compression of `data` with whichever of `dictionaries` compresses it smallest, returning
+    /// the output and the index of the chosen dictionary in `dictionaries`; which must then be given
+    /// to `decompress`. On a tie, the first is chosen.
+    ///
+    /// Each dictionary is tried in turn on all of `data`, so this costs one compression per dictionary;
+    /// for large inputs, choose on a sample first and `compress` with its dictionary.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed, index = cramjam.zstd.compress_best_dict(record, [json_dict, csv_dict], level=3)
+    /// >>> cramjam.zstd.decompress(compressed, dictionary=[json_dict, csv_dict][index])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, dictionaries, level=None))]
+    pub fn compress_best_dict(
+        py: Python,
+        data: BytesType,
+        dictionaries: Vec<BytesType>,
+        level: Option<i32>,
+    ) -> PyResult<(RustyBuffer, usize)> {
+        if dictionaries.is_empty() {
+            return Err(PyValueError::new_err("dictionaries must not be empty"));
+        }
+        let bytes = &*data.read_bytes()?;
+        let dictionaries = dictionaries
+            .iter()
+            .map(|d| d.read_bytes())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let (index, out) = py
+            .allow_threads(|| -> std::io::Result<_> {
+                let mut best: Option<(usize, Vec<u8>)> = None;
+                for (index, dictionary) in dictionaries.iter().enumerate() {
+                    let prepared = prepare_dictionary(dictionary, level);
+                    let mut out = vec![];
+                    compress_with(bytes, &mut out, level, false, false, 0, &[], Some(&prepared))?;
+                    if best.as_ref().is_none_or(|(_, best)| out.len() < best.len()) {
+                        best = Some((index, out));
+                    }
+                }
+                Ok(best.expect("dictionaries checked not to be empty"))
+            })
+            .map_err(CompressionError::from_err)?;
+        Ok((RustyBuffer::from(out), index))
+    }
+
+    /// ZSTD compression of `fileobj`, any object with a `read(n)` method, ie. a file opened in binary
+    /// mode; it's read in chunks as they're compressed, rather than first loaded whole as `compress`
+    /// would need. The GIL is released between calls to `read`.
+    ///
+    /// An exception raised by `read` is raised as a `CompressionError`, caused by the original.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open('data.txt', 'rb') as f:
+    /// ...     cramjam.zstd.compress_file(f, level=3)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (fileobj, level=None))]
+    pub fn compress_file(py: Python, fileobj: PyObject, level: Option<i32>) -> PyResult<RustyBuffer> {
+        let mut reader = PythonReader::new(fileobj);
+        let mut output = vec![];
+        py.allow_threads(|| libcramjam::zstd::compress(&mut reader, &mut output, level))
+            .map_err(|err| {
+                let err = CompressionError::from_err(err);
+                // interrupts, (ie. KeyboardInterrupt) aren't `Exception`s and are raised as-is
+                if !err.is_instance_of::<PyException>(py) || err.is_instance_of::<CompressionError>(py) {
+                    return err;
+                }
+                let wrapped = CompressionError::new_err(format!("Failed to read from fileobj: {}", err));
+                wrapped.set_cause(py, Some(err));
+                wrapped
+            })?;
+        Ok(RustyBuffer::from(output))
+    }
+
+    /// ZSTD decompression of the `length` compressed bytes at `offset` in the file at `path`, ie. one
+    /// of many blocks kept in a single file at known offsets. The file is opened, read and decoded
+    /// with the GIL released, and only the range is read.
+    ///
+    /// `output_len` is as for `decompress`. An `OSError` is raised if the file can't be read, and a
+    /// `DecompressionError` if the range extends past its end or isn't valid zstd.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.decompress_file_range('blocks.bin', offset=4096, length=1234)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (path, offset, length, output_len=None))]
+    pub fn decompress_file_range(
+        py: Python,
+        path: PathBuf,
+        offset: u64,
+        length: usize,
+        output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        let compressed = py.allow_threads(|| -> std::io::Result<_> {
+            let mut file = std::fs::File::open(&path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut compressed = Vec::with_capacity(length);
+            file.take(length as u64).read_to_end(&mut compressed)?;
+            Ok(compressed)
+        })?;
+        if compressed.len() < length {
+            return Err(DecompressionError::new_err(format!(
+                "Range of {} bytes at offset {} extends past the end of {}",
+                length,
+                offset,
+                path.display()
+            )));
+        }
+        let output_len = output_len.or_else(|| preallocate_len(&compressed));
+        let mut output = Vec::with_capacity(output_len.unwrap_or(0));
+        py.allow_threads(|| libcramjam::zstd::decompress(&compressed[..], &mut output))
+            .map_err(DecompressionError::from_err)?;
+        Ok(RustyBuffer::from(output))
+    }
+
+    /// ZSTD compression of `data` as independent frames of `frame_size` decompressed bytes, compressed
+    /// in parallel by `n_workers` threads and concatenated in order; so downstream, frames can also
+    /// be decompressed in parallel. `frame_size` defaults to 1MiB and `n_workers` to the number of CPUs.
+    ///
+    /// The output is regular multi-frame zstd, read by `decompress` or any zstd decoder. With
+    /// `seek_table=True` the seek table of the zstd seekable format is appended, as `SeekableWriter`
+    /// writes, so `SeekableReader` can decode a range of it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.compress_parallel(data, frame_size=1 << 20, level=3, n_workers=4)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, frame_size=None, level=None, n_workers=None, seek_table=false))]
+    pub fn compress_parallel(
+        py: Python,
+        data: BytesType,
+        frame_size: Option<usize>,
+        level: Option<i32>,
+        n_workers: Option<usize>,
+        seek_table: bool,
+    ) -> PyResult<RustyBuffer> {
+        let frame_size = frame_size.unwrap_or(SEEKABLE_FRAME_SIZE);
+        if frame_size == 0 || frame_size > u32::MAX as usize {
+            return Err(PyValueError::new_err("frame_size must be between 1 and u32::MAX"));
+        }
+        if n_workers == Some(0) {
+            return Err(PyValueError::new_err("n_workers must be greater than 0"));
+        }
+        let bytes = &*data.read_bytes()?;
+        let mut frames = bytes.chunks(frame_size).collect::<Vec<_>>();
+        if frames.is_empty() {
+            frames.push(&[]); // still a valid stream, a single empty frame
+        }
+        let outputs = py
+            .allow_threads(|| {
+                let n_workers = n_workers.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+                let chunk_len = frames.len().div_ceil(n_workers).max(1);
+                std::thread::scope(|scope| {
+                    let workers = frames
+                        .chunks(chunk_len)
+                        .map(|chunk| {
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .map(|frame| {
+                                        let mut out = vec![];
+                                        libcramjam::zstd::compress(*frame, &mut out, level).map(|_| out)
+                                    })
+                                    .collect::<std::io::Result<Vec<_>>>()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    workers
+                        .into_iter()
+                        .map(|worker| worker.join().expect("zstd parallel worker panicked"))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+            })
+            .map_err(CompressionError::from_err)?;
+
+        let outputs = outputs.into_iter().flatten().collect::<Vec<_>>();
+        let mut out = Vec::with_capacity(outputs.iter().map(|o| o.len()).sum());
+        let mut sizes = Vec::with_capacity(outputs.len());
+        for (compressed, frame) in outputs.iter().zip(frames.iter()) {
+            let compressed_size = u32::try_from(compressed.len())
+                .map_err(|_| CompressionError::new_err("Compressed frame exceeds u32::MAX bytes"))?;
+            sizes.push((compressed_size, frame.len() as u32));
+            out.extend_from_slice(compressed);
+        }
+        if seek_table {
+            write_seek_table(&mut out, &sizes);
+        }
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// ZSTD compression of `data` to at most `target_bytes`, at the lowest level between `min_level`
+    /// and `max_level` whose output fits, or `None` if even `max_level` can't reach the target.
+    ///
+    /// Levels are binary searched on the compressed length, so a handful of compressions are done
+    /// rather than one per level; as output size isn't strictly monotonic in the level, a level
+    /// below the one found may occasionally also have fit. With `long_distance=True`, a `max_level`
+    /// which doesn't fit is retried with long distance matching before giving up.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed = cramjam.zstd.compress_to_size(data, 1 << 20, min_level=1, max_level=19)
+    /// >>> if compressed is None:
+    /// ...     raise ValueError("doesn't fit")
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, target_bytes, min_level=1, max_level=19, long_distance=false))]
+    pub fn compress_to_size(
+        py: Python,
+        data: BytesType,
+        target_bytes: usize,
+        min_level: i32,
+        max_level: i32,
+        long_distance: bool,
+    ) -> PyResult<Option<RustyBuffer>> {
+        if min_level > max_level {
+            return Err(PyValueError::new_err("min_level must not be greater than max_level"));
+        }
+        let bytes = &*data.read_bytes()?;
+        let found = py
+            .allow_threads(|| {
+                let fits = |level, long_distance| {
+                    compress_at_level(bytes, level, long_distance)
+                        .map(|out| Some(out).filter(|o| o.len() <= target_bytes))
+                };
+                let mut best = match fits(max_level, false)? {
+                    Some(out) => out,
+                    None if long_distance => return fits(max_level, true),
+                    None => return Ok(None),
+                };
+                // lowest fitting level in [lo, hi), `best` being the output at hi
+                let (mut lo, mut hi) = (min_level, max_level);
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    match fits(mid, false)? {
+                        Some(out) => {
+                            best = out;
+                            hi = mid;
+                        }
+                        None => lo = mid + 1,
+                    }
+                }
+                Ok(Some(best))
+            })
+            .map_err(CompressionError::from_err)?;
+        Ok(found.map(RustyBuffer::from))
+    }
+
+    fn compress_at_level(data: &[u8], level: i32, long_distance: bool) -> std::io::Result<Vec<u8>> {
+        let mut encoder = Encoder::new(data, level)?;
+        encoder.long_distance_matching(long_distance)?;
+        let mut out = vec![];
+        crate::io::copy(&mut encoder, &mut out)?;
+        Ok(out)
+    }
+
+    /// Register `dictionary` for `decompress_dict_tagged` to find by id, returning that id;
+    /// registering a dictionary again, or another with the same id, replaces it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.register_dictionary(dictionary)
+    /// ```
+    #[pyfunction]
+    pub fn register_dictionary(dictionary: BytesType) -> PyResult<u32> {
+        let dictionary = dictionary.read_bytes()?;
+        let id = dictionary_id(&dictionary);
+        let mut registry = DICTIONARIES.lock().unwrap_or_else(|e| e.into_inner());
+        registry.insert(id, dictionary.into_owned());
+        Ok(id)
+    }
+
+    /// ZSTD compression with `dictionary`, prefixed by a 4 byte little endian id of the dictionary so
+    /// `decompress_dict_tagged` can pick the right one. The id is the one recorded in a trained zstd
+    /// dictionary, or for raw content dictionaries, which have none, the CRC32 of their content.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed = cramjam.zstd.compress_with_dict_tagged(b'some bytes here', dictionary, level=3)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, dictionary, level=None))]
+    pub fn compress_with_dict_tagged(
+        py: Python,
+        data: BytesType,
+        dictionary: BytesType,
+        level: Option<i32>,
+    ) -> PyResult<RustyBuffer> {
+        let dictionary = &*dictionary.read_bytes()?;
+        let mut out = dictionary_id(dictionary).to_le_bytes().to_vec();
+        let prepared = prepare_dictionary(dictionary, level);
+        let bytes = &*data.read_bytes()?;
+        py.allow_threads(|| compress_with(bytes, &mut out, level, false, false, 0, &[], Some(&prepared)))
+            .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// Decompress the output of `compress_with_dict_tagged`. The dictionary is looked up by the id
+    /// it was tagged with among those given to `register_dictionary`, or when `dictionary` is given,
+    /// that one is checked to have the id. Either way a `DecompressionError` is raised before any
+    /// decoding if the dictionary can't be found or doesn't match.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.register_dictionary(dictionary)
+    /// >>> cramjam.zstd.decompress_dict_tagged(compressed)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, dictionary=None))]
+    pub fn decompress_dict_tagged(py: Python, data: BytesType, dictionary: Option<BytesType>) -> PyResult<RustyBuffer> {
+        let bytes = &*data.read_bytes()?;
+        if bytes.len() < 4 {
+            return Err(DecompressionError::new_err(
+                "Input is too short to hold a dictionary id",
+            ));
+        }
+        let (id, body) = bytes.split_at(4);
+        let id = u32::from_le_bytes(id.try_into().unwrap());
+        let dictionary = match dictionary {
+            Some(dictionary) => {
+                let dictionary = dictionary.read_bytes()?;
+                if dictionary_id(&dictionary) != id {
+                    return Err(DecompressionError::new_err(format!(
+                        "Data was compressed with dictionary id {:#010x}, but the given dictionary's id is {:#010x}",
+                        id,
+                        dictionary_id(&dictionary)
+                    )));
+                }
+                dictionary.into_owned()
+            }
+            None => {
+                let registry = DICTIONARIES.lock().unwrap_or_else(|e| e.into_inner());
+                registry.get(&id).cloned().ok_or_else(|| {
+                    DecompressionError::new_err(format!("No dictionary registered with id {:#010x}", id))
+                })?
+            }
+        };
+        let mut out = vec![];
+        py.allow_threads(|| decompress_with(body, &mut out, &DecompressParams::default(), &dictionary))
+            .map_err(DecompressionError::from_err)?;
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// Id of `dictionary`, see `compress_with_dict_tagged`
+    fn dictionary_id(dictionary: &[u8]) -> u32 {
+        match libcramjam::zstd::zstd::zstd_safe::get_dict_id_from_dict(dictionary) {
+            Some(id) => id.get(),
+            None => {
+                let mut crc = Crc::new();
+                crc.update(dictionary);
+                crc.sum()
+            }
+        }
+    }
+
+    fn prepare_dictionary(dictionary: &[u8], level: Option<i32>) -> EncoderDictionary<'static> {
+        EncoderDictionary::copy(dictionary, level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))
+    }
+
+    /// Compress a list of numpy arrays as one zstd stream, preceded by a small header recording each
+    /// array's dtype and shape so `decompress_arrays` can rebuild the list.
+    ///
+    /// Arrays which aren't C contiguous are copied into C order first, and arrays of Python objects
+    /// are rejected with a `ValueError`. Only the dtype's `str` is kept, so structured dtypes come
+    /// back as raw void (`|V<n>`) arrays.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed = cramjam.zstd.compress_arrays([np.zeros((2, 3)), np.ones((4, 3))], level=3)
+    /// >>> arrays = cramjam.zstd.decompress_arrays(compressed)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (arrays, level=None))]
+    pub fn compress_arrays(py: Python, arrays: Vec<Bound<'_, PyAny>>, level: Option<i32>) -> PyResult<RustyBuffer> {
+        let numpy = py.import_bound("numpy")?;
+        let mut header = ARRAYS_MAGIC.to_vec();
+        header.extend_from_slice(&(arrays.len() as u32).to_le_bytes());
+        let mut buffers = Vec::with_capacity(arrays.len());
+        for array in arrays {
+            let array = numpy.call_method1("ascontiguousarray", (array,))?;
+            let dtype = array.getattr("dtype")?;
+            if dtype.getattr("hasobject")?.is_truthy()? {
+                return Err(PyValueError::new_err("Arrays of Python objects can't be compressed"));
+            }
+            let descr = dtype.getattr("str")?.extract::<String>()?;
+            let shape = array.getattr("shape")?.extract::<Vec<u64>>()?;
+            let data = array.extract::<BytesType>()?;
+            header.push(descr.len() as u8);
+            header.extend_from_slice(descr.as_bytes());
+            header.push(shape.len() as u8);
+            shape
+                .iter()
+                .for_each(|dim| header.extend_from_slice(&dim.to_le_bytes()));
+            header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            buffers.push(data);
+        }
+        let slices = buffers.iter().map(|b| b.as_bytes()).collect::<Vec<_>>();
+        let out = py
+            .allow_threads(|| {
+                let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+                let mut encoder = libcramjam::zstd::zstd::stream::write::Encoder::new(vec![], level)?;
+                encoder.write_all(&header)?;
+                for slice in slices {
+                    encoder.write_all(slice)?;
+                }
+                encoder.finish()
+            })
+            .map_err(CompressionError::from_err)?;
+        Ok(RustyBuffer::from(out))
+    }
+
+    /// Decompress a stream written by `compress_arrays`, returning the list of numpy arrays with
+    /// their original dtypes and shapes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> arrays = cramjam.zstd.decompress_arrays(compressed)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_arrays<'py>(py: Python<'py>, data: BytesType) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        let bytes = &*data.read_bytes()?;
+        let mut out = vec![];
+        py.allow_threads(|| libcramjam::zstd::decompress(bytes, &mut out))
+            .map_err(DecompressionError::from_err)?;
+
+        let mut pos = 0;
+        let mut take = |n: usize| -> PyResult<&[u8]> {
+            let field = out.get(pos..pos + n).ok_or_else(|| {
+                DecompressionError::new_err("Invalid arrays header, data not written by `compress_arrays`")
+            })?;
+            pos += n;
+            Ok(field)
+        };
+        if take(ARRAYS_MAGIC.len())? != ARRAYS_MAGIC {
+            return Err(DecompressionError::new_err("Data not written by `compress_arrays`"));
+        }
+        let n_arrays = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        let mut layouts = Vec::with_capacity(n_arrays);
+        for _ in 0..n_arrays {
+            let descr_len = take(1)?[0] as usize;
+            let descr = String::from_utf8_lossy(take(descr_len)?).into_owned();
+            let ndim = take(1)?[0] as usize;
+            let shape = (0..ndim)
+                .map(|_| take(8).map(|dim| u64::from_le_bytes(dim.try_into().unwrap())))
+                .collect::<PyResult<Vec<_>>>()?;
+            let nbytes = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+            layouts.push((descr, shape, nbytes));
+        }
+        let numpy = py.import_bound("numpy")?;
+        layouts
+            .into_iter()
+            .map(|(descr, shape, nbytes)| {
+                let buffer = PyByteArray::new_bound(py, take(nbytes)?);
+                numpy
+                    .call_method1("frombuffer", (buffer, descr))?
+                    .call_method1("reshape", (shape,))
+            })
+            .collect()
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
     #[pyo3(signature = (input, output, level=None))]
     pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<i32>) -> PyResult<usize> {
+        _level::check_level(level)?;
         crate::generic!(py, libcramjam::zstd::compress[input, output], level).map_err(CompressionError::from_err)
     }
 
-    /// Decompress directly into an output buffer
+    /// Worst case length of `compress_into` output for `n` bytes of input, (`ZSTD_compressBound`)
+    /// for sizing its output buffer. An upper limit, not the actual compressed length, which is
+    /// usually far less. Raises `ValueError` for `n` beyond what zstd can compress in one frame.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> output = bytearray(cramjam.zstd.compress_bound(len(data)))
+    /// >>> n_bytes = cramjam.zstd.compress_into(data, output)
+    /// ```
+    #[pyfunction]
+    pub fn compress_bound(n: usize) -> PyResult<usize> {
+        let bound = libcramjam::zstd::zstd::zstd_safe::compress_bound(n);
+        // an error code rather than a length for `n` of ZSTD_MAX_INPUT_SIZE or more; Safety: Just FFI
+        if unsafe { libcramjam::zstd::zstd::zstd_safe::zstd_sys::ZSTD_isError(bound) } != 0 {
+            return Err(PyValueError::new_err(format!(
+                "{} bytes is more than zstd can compress in one frame",
+                n
+            )));
+        }
+        Ok(bound)
+    }
+
+    /// Decompress directly into an output buffer.
+    ///
+    /// A fixed size output, (ie. a numpy array) too small for the decompressed data raises a
+    /// `DecompressionError` with `zstd_name` of "ZSTD_error_dstSize_tooSmall".
     #[pyfunction]
     pub fn decompress_into<'a>(py: Python<'a>, input: BytesType<'a>, mut output: BytesType<'a>) -> PyResult<usize> {
+        if matches!(output, BytesType::PyBuffer(_)) && !matches!(input, BytesType::RustyFile(_)) {
+            // Decompressed in one go by libzstd, which reports the error codes of a too small output
+            let src = input.as_bytes();
+            let dst = output.as_bytes_mut()?;
+            return py
+                .allow_threads(|| DCtx::create().decompress(dst, src))
+                .map_err(|code| {
+                    with_zstd_code(
+                        DecompressionError::new_err(format!("zstd decompression failed: {}", get_error_name(code))),
+                        code,
+                    )
+                });
+        }
         crate::generic!(py, libcramjam::zstd::decompress[input, output]).map_err(DecompressionError::from_err)
     }
 
+    /// ZSTD compression, also returning statistics of the compressed frame for tuning.
+    ///
+    /// The statistics are read back from the frame itself:
+    /// ```bash
+    /// input_size: decompressed bytes
+    /// output_size: compressed bytes
+    /// blocks: number of blocks, split by type into raw_blocks, rle_blocks and compressed_blocks
+    /// literals: literal bytes of the compressed blocks
+    /// sequences: number of sequences, ie. matches, in the compressed blocks
+    /// matched_bytes: bytes reproduced by matches
+    /// ```
+    /// All fields are exact for the frame produced; `matched_bytes` is derived as the bytes not stored
+    /// in raw/rle blocks or as literals. None reflect the encoder's search effort, ie. `sequences` counts
+    /// the matches kept, not those considered, as that isn't recoverable from the frame.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed, stats = cramjam.zstd.compress_debug(b'some bytes here', level=3)
+    /// >>> stats['sequences']
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, level=None))]
+    pub fn compress_debug<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        level: Option<i32>,
+    ) -> PyResult<(RustyBuffer, Bound<'py, PyDict>)> {
+        let bytes = &*data.read_bytes()?;
+        let mut compressed = vec![];
+        py.allow_threads(|| libcramjam::zstd::compress(bytes, &mut compressed, level))
+            .map_err(CompressionError::from_err)?;
+        let frame = frame_stats(&compressed).map_err(CompressionError::new_err)?;
+
+        let stats = PyDict::new_bound(py);
+        stats.set_item("input_size", bytes.len())?;
+        stats.set_item("output_size", compressed.len())?;
+        stats.set_item("blocks", frame.raw_blocks + frame.rle_blocks + frame.compressed_blocks)?;
+        stats.set_item("raw_blocks", frame.raw_blocks)?;
+        stats.set_item("rle_blocks", frame.rle_blocks)?;
+        stats.set_item("compressed_blocks", frame.compressed_blocks)?;
+        stats.set_item("literals", frame.literals)?;
+        stats.set_item("sequences", frame.sequences)?;
+        stats.set_item(
+            "matched_bytes",
+            bytes.len().saturating_sub(frame.literals + frame.stored_bytes),
+        )?;
+        Ok((RustyBuffer::from(compressed), stats))
+    }
+
+    /// Iterate over the frames of a multi-frame stream, yielding each frame's decompressed data.
+    /// The GIL is released while decoding each frame, and control returns to Python between frames,
+    /// so other threads, (ie. the asyncio event loop when iterating from a thread) can run in between.
+    /// Skippable frames are passed over.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for frame in cramjam.zstd.decompress_frames(compressed_bytes):
+    /// ...     process(frame)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_frames(mut data: BytesType) -> PyResult<Frames> {
+        let mut buf = vec![];
+        data.read_to_end(&mut buf)?;
+        Ok(Frames { data: buf, pos: 0 })
+    }
+
+    /// Compressed length in bytes of the zstd frame at the start of `data`, without decompressing it;
+    /// ie. to index frames embedded among other data. A `DecompressionError` is raised if the frame
+    /// is invalid or incomplete. A `File` is read and left at the same position.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.frame_compressed_size(compressed_bytes + b'trailing data')
+    /// ```
+    #[pyfunction]
+    pub fn frame_compressed_size(data: BytesType) -> PyResult<usize> {
+        frame_len(&data.peek(usize::MAX)?)
+    }
+
+    fn frame_len(data: &[u8]) -> PyResult<usize> {
+        libcramjam::zstd::zstd::zstd_safe::find_frame_compressed_size(data).map_err(|code| {
+            with_zstd_code(
+                DecompressionError::new_err(format!("Invalid zstd frame: {}", get_error_name(code))),
+                code,
+            )
+        })
+    }
+
+    /// Decompressed size of `data` as recorded in its frame headers, without decompressing it; summed
+    /// over all frames when there are several. Pass it as `decompress(data, output_len=n)` to decompress
+    /// into a single exactly sized allocation. `None` is returned if any frame doesn't record its size,
+    /// as with frames written by streaming compressors, (including `compress` here, which streams its
+    /// input) and a `DecompressionError` is raised if `data` isn't a sequence of complete zstd frames.
+    /// A `File` is read and left at the same position.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> n = cramjam.zstd.decompressed_size(compressed_bytes)
+    /// >>> cramjam.zstd.decompress(compressed_bytes, output_len=n)
+    /// ```
+    #[pyfunction]
+    pub fn decompressed_size(data: BytesType) -> PyResult<Option<u64>> {
+        libcramjam::zstd::zstd::zstd_safe::find_decompressed_size(&data.peek(usize::MAX)?)
+            .map_err(|_| DecompressionError::new_err("Invalid zstd frames, can't read their decompressed size"))
+    }
+
+    /// Output length to preallocate when decompressing `data` without an `output_len`: the size recorded
+    /// in its frame headers, bounded relative to the input since headers aren't trusted until decoded.
+    fn preallocate_len(data: &[u8]) -> Option<usize> {
+        let recorded = libcramjam::zstd::zstd::zstd_safe::find_decompressed_size(data).ok()??;
+        let bound = data.len().saturating_mul(PREALLOCATE_MAX_RATIO);
+        Some(usize::try_from(recorded).map_or(bound, |recorded| recorded.min(bound)))
+    }
+
+    /// Iterator of decompressed frames, see `decompress_frames`
+    #[pyclass]
+    pub struct Frames {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    #[pymethods]
+    impl Frames {
+        fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            while self.pos < self.data.len() {
+                let remaining = &self.data[self.pos..];
+                let frame_len =
+                    libcramjam::zstd::zstd::zstd_safe::find_frame_compressed_size(remaining).map_err(|code| {
+                        let msg = format!("Invalid frame at offset {}: {}", self.pos, get_error_name(code));
+                        with_zstd_code(DecompressionError::new_err(msg), code)
+                    })?;
+                let frame = &remaining[..frame_len];
+                self.pos += frame_len;
+                // Skippable frames have magic numbers 0x184D2A50..=0x184D2A5F
+                if u32::from_le_bytes(frame[..4].try_into().unwrap()) >> 4 == SEEK_TABLE_SKIPPABLE_MAGIC >> 4 {
+                    continue;
+                }
+                let mut out = vec![];
+                py.allow_threads(|| libcramjam::zstd::decompress(frame, &mut out))
+                    .map_err(DecompressionError::from_err)?;
+                return Ok(Some(RustyBuffer::from(out)));
+            }
+            Ok(None)
+        }
+    }
+
+    /// Streaming compression with backpressure: returns an iterator yielding compressed chunks of
+    /// the bytes-like items from `input_iter`, compressed as a single zstd frame.
+    ///
+    /// Input is pulled lazily, only while less than `max_buffered_bytes` of compressed output is
+    /// pending; once that much is buffered it's yielded and no more input is read until the consumer
+    /// asks for the next chunk. Pending output is thus bounded by `max_buffered_bytes` plus the
+    /// compressed size of a single input item. An empty `input_iter` still yields a valid frame,
+    /// decompressing to `b""`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for chunk in cramjam.zstd.compress_pipe(read_chunks(), max_buffered_bytes=1 << 20):
+    /// ...     sock.sendall(chunk)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (input_iter, max_buffered_bytes, level=None))]
+    pub fn compress_pipe(
+        input_iter: &Bound<'_, PyAny>,
+        max_buffered_bytes: usize,
+        level: Option<i32>,
+    ) -> PyResult<Pipe> {
+        if max_buffered_bytes == 0 {
+            return Err(PyValueError::new_err("max_buffered_bytes must be greater than 0"));
+        }
+        let encoder =
+            libcramjam::zstd::zstd::stream::write::Encoder::new(vec![], level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))?;
+        Ok(Pipe {
+            input: input_iter.iter()?.unbind(),
+            inner: Some(encoder),
+            max_buffered_bytes,
+        })
+    }
+
+    /// Compress `data`, writing each compressed chunk to every object in `writers` as it's produced;
+    /// ie. sending it downstream and to a cache file at once, without compressing twice. Returns the
+    /// total compressed bytes, which each writer receives in full.
+    ///
+    /// Writers need a `write(b)` method, as file objects have. Short writes are continued with the
+    /// remainder, and a `write` returning `None` took all of `b`, as with `io.BufferedWriter`; one
+    /// accepting nothing raises a `CompressionError` rather than being retried.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open('cache.zst', 'wb') as cache:
+    /// ...     cramjam.zstd.compress_tee(b'some bytes here', [sock.makefile('wb'), cache], level=3)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, writers, level=None))]
+    pub fn compress_tee(py: Python, data: BytesType, writers: Vec<PyObject>, level: Option<i32>) -> PyResult<usize> {
+        let bytes = &*data.read_bytes()?;
+        let mut encoder = Encoder::new(bytes, level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))?;
+        let mut writers = writers.into_iter().map(PythonWriter::new).collect::<Vec<_>>();
+        py.allow_threads(|| -> std::io::Result<usize> {
+            let mut chunk = vec![0u8; CCtx::out_size()];
+            let mut total = 0;
+            loop {
+                let n_bytes = encoder.read(&mut chunk)?;
+                if n_bytes == 0 {
+                    return Ok(total);
+                }
+                for writer in writers.iter_mut() {
+                    writer.write_all(&chunk[..n_bytes])?;
+                }
+                total += n_bytes;
+            }
+        })
+        .map_err(CompressionError::from_err)
+    }
+
+    /// Iterator of compressed chunks, see `compress_pipe`
+    #[pyclass]
+    pub struct Pipe {
+        input: Py<PyIterator>,
+        inner: Option<libcramjam::zstd::zstd::stream::write::Encoder<'static, Vec<u8>>>,
+        max_buffered_bytes: usize,
+    }
+
+    #[pymethods]
+    impl Pipe {
+        fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            let Some(encoder) = self.inner.as_mut() else {
+                return Ok(None);
+            };
+            while encoder.get_ref().len() < self.max_buffered_bytes {
+                let mut input = self.input.bind(py).clone();
+                match input.next() {
+                    Some(item) => {
+                        let item = item?;
+                        let data = item.extract::<BytesType>()?;
+                        let bytes = &*data.read_bytes()?;
+                        py.allow_threads(|| encoder.write_all(bytes))
+                            .map_err(CompressionError::from_err)?;
+                    }
+                    None => {
+                        let encoder = self.inner.take().unwrap();
+                        let out = py
+                            .allow_threads(|| encoder.finish())
+                            .map_err(CompressionError::from_err)?;
+                        return Ok(Some(RustyBuffer::from(out)));
+                    }
+                }
+            }
+            Ok(Some(RustyBuffer::from(std::mem::take(encoder.get_mut()))))
+        }
+    }
+
     /// ZSTD Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
+        // references `dictionary`, so is declared, and dropped, before it
         inner: Option<libcramjam::zstd::zstd::stream::write::Encoder<'static, Cursor<Vec<u8>>>>,
+        level: i32,
+        finished: Option<RustyBuffer>,
+        // bytes returned by `flush` so far
+        flushed: usize,
+        dictionary: Option<EncoderDictionary<'static>>,
+        // options of `__init__` also applied to each `compress_frame` frame
+        checksum: bool,
+        workers: u32,
+        params: Vec<CParameter>,
+        /// Whether `compress_frame` reuses what it can of the previous frame, see `__init__`
+        #[pyo3(get)]
+        reuse_entropy: bool,
+        // content of the last `compress_frame` frame, with `reuse_entropy`
+        previous: Vec<u8>,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `target_block_size` asks zstd to cut compressed blocks of roughly that many bytes,
+        /// so a consumer sees complete, decodable blocks sooner in low-latency streams. Smaller
+        /// blocks carry more header and entropy table overhead, lowering the compression ratio.
+        /// Values below 1340 are raised to it; above 128 KiB a `ValueError` is raised.
+        ///
+        /// `dictionary` is raw content or a trained zstd dictionary used for the stream, which must
+        /// then also be given to `decompress`; see `compress_raw_frame` for frames without it.
+        ///
+        /// `workers` compresses with that many threads, `checksum=True` appends a checksum of the
+        /// content to the frame, and `enable_ldm` / `window_log` set long distance matching and the
+        /// window size, all as with `cramjam.zstd.compress`.
+        ///
+        /// `reuse_entropy=True` shrinks a sequence of similar small frames from `compress_frame`.
+        /// zstd's API has no way to carry entropy tables from one frame into the next, (each frame
+        /// describes its own, and `refCDict` needs a dictionary fixed up front) so what's reused is
+        /// the previous frame's content, referenced as a prefix (`ZSTD_CCtx_refPrefix`); making what
+        /// recurs between messages cheap matches, and with them the literals left to entropy code
+        /// fewer. Each frame then only decodes given the previous frame's content, as
+        /// `decompress(frame, prefix=previous)`; so frames must be decoded in order and none may be
+        /// lost, the first needing no prefix. It can't be combined with `dictionary`, which already
+        /// seeds each frame. `chain_prefix` is an alias of it.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<i32>) -> PyResult<Self> {
-            let inner = libcramjam::zstd::zstd::stream::write::Encoder::new(
-                Cursor::new(vec![]),
-                level.unwrap_or(DEFAULT_COMPRESSION_LEVEL),
-            )?;
-            Ok(Self { inner: Some(inner) })
+        #[pyo3(signature = (level=None, target_block_size=None, dictionary=None, workers=None, checksum=false, enable_ldm=false, window_log=None, reuse_entropy=false, chain_prefix=false))]
+        #[allow(clippy::too_many_arguments)]
+        pub fn __init__(
+            level: Option<i32>,
+            target_block_size: Option<u32>,
+            dictionary: Option<BytesType>,
+            workers: Option<u32>,
+            checksum: bool,
+            enable_ldm: bool,
+            window_log: Option<u32>,
+            reuse_entropy: bool,
+            chain_prefix: bool,
+        ) -> PyResult<Self> {
+            let reuse_entropy = reuse_entropy || chain_prefix;
+            if reuse_entropy && dictionary.is_some() {
+                return Err(PyValueError::new_err("reuse_entropy can't be combined with dictionary"));
+            }
+            let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+            // prepared once, for both the stream and `compress_frame`
+            let dictionary = dictionary.map(|d| d.read_bytes().map(|d| prepare_dictionary(&d, Some(level))));
+            let dictionary = dictionary.transpose()?;
+            let mut inner = match dictionary.as_ref() {
+                Some(dictionary) => libcramjam::zstd::zstd::stream::write::Encoder::with_prepared_dictionary(
+                    Cursor::new(vec![]),
+                    dictionary,
+                )?,
+                None => libcramjam::zstd::zstd::stream::write::Encoder::new(Cursor::new(vec![]), level)?,
+            };
+            let workers = workers.filter(|n| *n > 1).unwrap_or(0);
+            if workers > 0 {
+                inner.multithread(workers)?;
+            }
+            inner.include_checksum(checksum)?;
+            let mut params = window_params(enable_ldm, window_log);
+            for param in params.iter() {
+                inner
+                    .set_parameter(*param)
+                    .map_err(|e| PyValueError::new_err(format!("Invalid {:?}: {}", param, e)))?;
+            }
+            if let Some(size) = target_block_size {
+                inner
+                    .set_parameter(CParameter::TargetCBlockSize(size))
+                    .map_err(|e| PyValueError::new_err(format!("Invalid target_block_size {}: {}", size, e)))?;
+                params.push(CParameter::TargetCBlockSize(size));
+            }
+            Ok(Self {
+                inner: Some(inner),
+                level,
+                finished: None,
+                flushed: 0,
+                dictionary,
+                checksum,
+                workers,
+                params,
+                reuse_entropy,
+                previous: vec![],
+            })
+        }
+
+        /// Compress `data` into a standalone frame at this compressor's level but without its
+        /// dictionary, ie. a handshake the peer must read before the dictionary is agreed on.
+        /// The frame is returned on its own rather than added to the stream, whose state is unaffected.
+        pub fn compress_raw_frame(&self, py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+            let bytes = &*data.read_bytes()?;
+            let mut out = vec![];
+            py.allow_threads(|| libcramjam::zstd::compress(bytes, &mut out, Some(self.level)))
+                .map_err(CompressionError::from_err)?;
+            Ok(RustyBuffer::from(out))
+        }
+
+        /// Compress `data` into a frame of its own with this compressor's level, dictionary and other
+        /// options, for a sequence of messages each sent as a frame; with `reuse_entropy`, see `__init__`,
+        /// it references the previous one. The frame is returned rather than added to the stream.
+        pub fn compress_frame(&mut self, py: Python, data: BytesType) -> PyResult<RustyBuffer> {
+            let bytes = &*data.read_bytes()?;
+            let level = Some(self.level);
+            let (checksum, workers, params) = (self.checksum, self.workers, &self.params[..]);
+            let (dictionary, previous) = (self.dictionary.as_ref(), &self.previous);
+            let mut out = vec![];
+            py.allow_threads(|| match previous.is_empty() {
+                true => compress_with(bytes, &mut out, level, false, checksum, workers, params, dictionary),
+                false => compress_with_prefix(bytes, &mut out, level, false, checksum, workers, params, previous),
+            })
+            .map_err(CompressionError::from_err)?;
+            if self.reuse_entropy {
+                self.previous = bytes.to_vec();
+            }
+            Ok(RustyBuffer::from(out))
+        }
+
+        /// Alias of `reuse_entropy`
+        #[getter]
+        pub fn chain_prefix(&self) -> bool {
+            self.reuse_entropy
         }
 
         /// Compress input into the current compressor's stream.
@@ -83,22 +1545,765 @@ pub mod zstd {
             crate::io::stream_compress(&mut self.inner, input)
         }
 
-        /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        /// Flush and return current compressed stream.
+        ///
+        /// With `return_total_len=True`, a tuple of the flushed buffer and the total length of all
+        /// output flushed so far is returned; ie. to track how much of the stream has been sent.
+        #[pyo3(signature = (return_total_len=false))]
+        pub fn flush(&mut self, py: Python, return_total_len: bool) -> PyResult<PyObject> {
+            let buffer = crate::io::stream_flush(&mut self.inner, |e| e.get_mut())?;
+            self.flushed += buffer.len();
+            Ok(match return_total_len {
+                true => (buffer, self.flushed).into_py(py),
+                false => buffer.into_py(py),
+            })
+        }
+
+        #[doc = crate::flush_modes_doc!()]
+        ///
+        /// Ending the frame (zstd's ZSTD_e_end) isn't one, the encoder can't continue past it; that's
+        /// `finish`.
+        #[staticmethod]
+        pub fn flush_modes() -> Vec<&'static str> {
+            vec!["flush"]
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
+            if let Some(buffer) = self.finished.take() {
+                return Ok(buffer);
+            }
             crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|v| v.into_inner()))
         }
+
+        /// Enter a `with` block; on leaving it the stream is finished, see `__exit__`.
+        fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        /// Finish the stream on leaving a `with` block, even if it raised, the compressed stream then
+        /// being returned by the next `finish()` call.
+        fn __exit__(
+            &mut self,
+            exc_type: &Bound<'_, PyAny>,
+            _exc_value: &Bound<'_, PyAny>,
+            _traceback: &Bound<'_, PyAny>,
+        ) -> PyResult<bool> {
+            let finished = self.finish();
+            self.finished = crate::io::stream_exit(finished, !exc_type.is_none())?;
+            Ok(false)
+        }
+    }
+
+    /// Writer of length-prefixed records; each record is compressed into its own zstd frame,
+    /// preceded by the frame's length as a little-endian u32. Read back with `RecordReader`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> writer = cramjam.zstd.RecordWriter(level=3)
+    /// >>> writer.append(b'first record')
+    /// >>> writer.append(b'second record')
+    /// >>> log = writer.finish()
+    /// ```
+    #[pyclass]
+    pub struct RecordWriter {
+        inner: Option<Vec<u8>>,
+        level: Option<i32>,
+    }
+
+    #[pymethods]
+    impl RecordWriter {
+        /// Initialize a new `RecordWriter` instance.
+        #[new]
+        #[pyo3(signature = (level=None))]
+        pub fn __init__(level: Option<i32>) -> Self {
+            Self {
+                inner: Some(vec![]),
+                level,
+            }
+        }
+
+        /// Compress a record as its own frame, returning the number of bytes appended, including the length prefix.
+        pub fn append(&mut self, py: Python, record: BytesType) -> PyResult<usize> {
+            let level = self.level;
+            match self.inner.as_mut() {
+                Some(inner) => {
+                    let bytes = &*record.read_bytes()?;
+                    let mut frame = vec![];
+                    py.allow_threads(|| libcramjam::zstd::compress(bytes, &mut frame, level))
+                        .map_err(CompressionError::from_err)?;
+                    let len = u32::try_from(frame.len())
+                        .map_err(|_| CompressionError::new_err("Compressed record exceeds u32::MAX bytes"))?;
+                    inner.extend_from_slice(&len.to_le_bytes());
+                    inner.extend_from_slice(&frame);
+                    Ok(RECORD_PREFIX_LEN + frame.len())
+                }
+                None => Err(CompressionError::new_err(
+                    "RecordWriter looks to have been consumed via `finish()`. \
+                    please create a new instance.",
+                )),
+            }
+        }
+
+        /// Consume the current writer and return all records written.
+        /// **NB** The writer will not be usable after this method is called.
+        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
+            Ok(RustyBuffer::from(std::mem::take(&mut self.inner).unwrap_or_default()))
+        }
+    }
+
+    /// Iterate over records written by `RecordWriter`, yielding each decompressed record in order.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for record in cramjam.zstd.RecordReader(log):
+    /// ...     print(bytes(record))
+    /// ```
+    #[pyclass]
+    pub struct RecordReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    #[pymethods]
+    impl RecordReader {
+        /// Initialize a new `RecordReader` over the output of `RecordWriter.finish()`
+        #[new]
+        pub fn __init__(mut data: BytesType) -> PyResult<Self> {
+            let mut buf = vec![];
+            data.read_to_end(&mut buf)?;
+            Ok(Self { data: buf, pos: 0 })
+        }
+
+        fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            let remaining = &self.data[self.pos..];
+            if remaining.is_empty() {
+                return Ok(None);
+            }
+            let frame = remaining
+                .get(..RECORD_PREFIX_LEN)
+                .map(|prefix| u32::from_le_bytes(prefix.try_into().unwrap()) as usize)
+                .and_then(|len| remaining.get(RECORD_PREFIX_LEN..RECORD_PREFIX_LEN + len))
+                .ok_or_else(|| DecompressionError::new_err(format!("Truncated record at offset {}", self.pos)))?;
+            let mut out = vec![];
+            py.allow_threads(|| libcramjam::zstd::decompress(frame, &mut out))
+                .map_err(DecompressionError::from_err)?;
+            self.pos += RECORD_PREFIX_LEN + frame.len();
+            Ok(Some(RustyBuffer::from(out)))
+        }
+    }
+
+    /// Writer of the zstd seekable format; input is compressed into independent frames of
+    /// `frame_size` decompressed bytes, followed by a seek table in a trailing skippable frame.
+    /// Any zstd decoder can still decompress the output, `SeekableReader` can decode only a range of it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> writer = cramjam.zstd.SeekableWriter(frame_size=1024)
+    /// >>> writer.compress(b'some bytes here')
+    /// >>> compressed = writer.finish()
+    /// ```
+    #[pyclass]
+    pub struct SeekableWriter {
+        inner: Option<Vec<u8>>,
+        pending: Vec<u8>,
+        frames: Vec<(u32, u32)>,
+        frame_size: usize,
+        level: Option<i32>,
+    }
+
+    #[pymethods]
+    impl SeekableWriter {
+        /// Initialize a new `SeekableWriter` instance, `frame_size` defaults to 1MiB.
+        #[new]
+        #[pyo3(signature = (frame_size=None, level=None))]
+        pub fn __init__(frame_size: Option<usize>, level: Option<i32>) -> PyResult<Self> {
+            let frame_size = frame_size.unwrap_or(SEEKABLE_FRAME_SIZE);
+            if frame_size == 0 || frame_size > u32::MAX as usize {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "frame_size must be between 1 and u32::MAX",
+                ));
+            }
+            Ok(Self {
+                inner: Some(vec![]),
+                pending: vec![],
+                frames: vec![],
+                frame_size,
+                level,
+            })
+        }
+
+        /// Compress input into the current writer's stream, returning the number of bytes consumed.
+        pub fn compress(&mut self, py: Python, input: BytesType) -> PyResult<usize> {
+            if self.inner.is_none() {
+                return Err(CompressionError::new_err(
+                    "SeekableWriter looks to have been consumed via `finish()`. \
+                    please create a new instance.",
+                ));
+            }
+            let bytes = &*input.read_bytes()?;
+            self.pending.extend_from_slice(bytes);
+            while self.pending.len() >= self.frame_size {
+                let frame = self.pending.drain(..self.frame_size).collect::<Vec<u8>>();
+                self.write_frame(py, &frame)?;
+            }
+            Ok(bytes.len())
+        }
+
+        /// Consume the current writer state and return the compressed stream, including its seek table.
+        /// **NB** The writer will not be usable after this method is called.
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            if self.inner.is_none() {
+                return Ok(RustyBuffer::from(vec![]));
+            }
+            if !self.pending.is_empty() {
+                let frame = std::mem::take(&mut self.pending);
+                self.write_frame(py, &frame)?;
+            }
+            let mut out = std::mem::take(&mut self.inner).unwrap_or_default();
+            write_seek_table(&mut out, &self.frames);
+            Ok(RustyBuffer::from(out))
+        }
+    }
+
+    /// Append the seek table of the zstd seekable format, in a skippable frame, for `frames` of
+    /// (compressed size, decompressed size).
+    fn write_seek_table(out: &mut Vec<u8>, frames: &[(u32, u32)]) {
+        let table_len = frames.len() * 8 + SEEK_TABLE_FOOTER_LEN;
+        out.extend_from_slice(&SEEK_TABLE_SKIPPABLE_MAGIC.to_le_bytes());
+        out.extend_from_slice(&(table_len as u32).to_le_bytes());
+        for (compressed_size, decompressed_size) in frames.iter() {
+            out.extend_from_slice(&compressed_size.to_le_bytes());
+            out.extend_from_slice(&decompressed_size.to_le_bytes());
+        }
+        out.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+        out.push(0); // Seek_Table_Descriptor, no per-frame checksums
+        out.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+    }
+
+    impl SeekableWriter {
+        fn write_frame(&mut self, py: Python, frame: &[u8]) -> PyResult<()> {
+            let level = self.level;
+            let mut compressed = vec![];
+            py.allow_threads(|| libcramjam::zstd::compress(frame, &mut compressed, level))
+                .map_err(CompressionError::from_err)?;
+            let compressed_size = u32::try_from(compressed.len())
+                .map_err(|_| CompressionError::new_err("Compressed frame exceeds u32::MAX bytes"))?;
+            self.frames.push((compressed_size, frame.len() as u32));
+            if let Some(inner) = self.inner.as_mut() {
+                inner.extend_from_slice(&compressed);
+            }
+            Ok(())
+        }
+    }
+
+    /// Random access reader of the zstd seekable format, as written by `SeekableWriter`
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> reader = cramjam.zstd.SeekableReader(compressed)
+    /// >>> reader.read_range(offset=2048, length=100)
+    /// ```
+    #[pyclass]
+    pub struct SeekableReader {
+        data: Vec<u8>,
+        // (compressed offset, decompressed offset, compressed size, decompressed size) of each frame
+        frames: Vec<(usize, usize, usize, usize)>,
+    }
+
+    #[pymethods]
+    impl SeekableReader {
+        /// Initialize a new `SeekableReader`, parsing the seek table at the end of `data`
+        #[new]
+        pub fn __init__(mut data: BytesType) -> PyResult<Self> {
+            let mut buf = vec![];
+            data.read_to_end(&mut buf)?;
+            let frames = parse_seek_table(&buf).map_err(DecompressionError::new_err)?;
+            Ok(Self { data: buf, frames })
+        }
+
+        /// Total decompressed length
+        pub fn len(&self) -> usize {
+            self.frames.last().map(|f| f.1 + f.3).unwrap_or(0)
+        }
+
+        /// Whether the total decompressed length is zero
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Number of frames in the seek table
+        pub fn n_frames(&self) -> usize {
+            self.frames.len()
+        }
+
+        /// Decompress `length` bytes starting at decompressed `offset`, only decoding the frames needed.
+        /// The range is clamped to the end of the decompressed data. A `DecompressionError` is raised
+        /// if a frame doesn't decompress to the length its seek table entry records.
+        pub fn read_range(&self, py: Python, offset: usize, length: usize) -> PyResult<RustyBuffer> {
+            let end = offset.saturating_add(length).min(self.len());
+            let mut out = Vec::with_capacity(end.saturating_sub(offset));
+            for (i, &(c_offset, d_offset, c_size, d_size)) in self.frames.iter().enumerate() {
+                if d_offset + d_size <= offset || d_offset >= end {
+                    continue;
+                }
+                let compressed = &self.data[c_offset..c_offset + c_size];
+                let mut frame = Vec::with_capacity(d_size);
+                py.allow_threads(|| libcramjam::zstd::decompress(compressed, &mut frame))
+                    .map_err(DecompressionError::from_err)?;
+                if frame.len() != d_size {
+                    return Err(DecompressionError::new_err(format!(
+                        "Frame {} decompressed to {} bytes, but the seek table records {}",
+                        i,
+                        frame.len(),
+                        d_size
+                    )));
+                }
+                let start = offset.saturating_sub(d_offset);
+                let stop = end - d_offset;
+                out.extend_from_slice(&frame[start..stop]);
+            }
+            Ok(RustyBuffer::from(out))
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+    }
+
+    fn parse_seek_table(data: &[u8]) -> Result<Vec<(usize, usize, usize, usize)>, String> {
+        let read_u32 = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+
+        if data.len() < SEEK_TABLE_FOOTER_LEN + 8 {
+            return Err("Data too short to hold a seek table".to_string());
+        }
+        let footer = data.len() - SEEK_TABLE_FOOTER_LEN;
+        if read_u32(footer + 5) as u32 != SEEKABLE_MAGIC {
+            return Err("Seekable magic number not found, is this the zstd seekable format?".to_string());
+        }
+        let n_frames = read_u32(footer);
+        let entry_len = if data[footer + 4] & 0x80 != 0 { 12 } else { 8 };
+        let table_start = n_frames
+            .checked_mul(entry_len)
+            .and_then(|len| footer.checked_sub(len + 8))
+            .ok_or_else(|| "Seek table larger than data".to_string())?;
+        if read_u32(table_start) as u32 != SEEK_TABLE_SKIPPABLE_MAGIC {
+            return Err("Seek table skippable frame header not found".to_string());
+        }
+
+        let mut frames = Vec::with_capacity(n_frames);
+        let (mut c_offset, mut d_offset) = (0, 0);
+        for i in 0..n_frames {
+            let entry = table_start + 8 + i * entry_len;
+            let (c_size, d_size) = (read_u32(entry), read_u32(entry + 4));
+            frames.push((c_offset, d_offset, c_size, d_size));
+            c_offset += c_size;
+            d_offset += d_size;
+        }
+        if c_offset > table_start {
+            return Err("Seek table frame sizes exceed the compressed data".to_string());
+        }
+        Ok(frames)
+    }
+
+    /// Block level statistics of a single zstd frame
+    #[derive(Default)]
+    struct FrameStats {
+        raw_blocks: usize,
+        rle_blocks: usize,
+        compressed_blocks: usize,
+        // decompressed bytes held by raw and rle blocks
+        stored_bytes: usize,
+        literals: usize,
+        sequences: usize,
+    }
+
+    /// Walk the blocks of a single zstd frame, reading the literals and sequences section
+    /// headers of compressed blocks; no entropy decoding is done.
+    fn frame_stats(data: &[u8]) -> Result<FrameStats, String> {
+        let truncated = || "Truncated zstd frame".to_string();
+        let byte = |pos: usize| data.get(pos).map(|b| *b as usize).ok_or_else(truncated);
+
+        if data.len() < 4 || u32::from_le_bytes(data[..4].try_into().unwrap()) != FRAME_MAGIC {
+            return Err("zstd frame magic number not found".to_string());
+        }
+        let descriptor = byte(4)?;
+        let single_segment = descriptor & 0x20 != 0;
+        let dict_id_len = [0, 1, 2, 4][descriptor & 0x03];
+        let content_size_len = match descriptor >> 6 {
+            0 if single_segment => 1,
+            0 => 0,
+            flag => 1 << flag,
+        };
+        let mut pos = 5 + usize::from(!single_segment) + dict_id_len + content_size_len;
+
+        let mut stats = FrameStats::default();
+        loop {
+            let header = byte(pos)? | byte(pos + 1)? << 8 | byte(pos + 2)? << 16;
+            let (last, block_type, block_size) = (header & 1 == 1, (header >> 1) & 0x03, header >> 3);
+            pos += 3;
+            match block_type {
+                0 => {
+                    stats.raw_blocks += 1;
+                    stats.stored_bytes += block_size;
+                    pos += block_size;
+                }
+                1 => {
+                    stats.rle_blocks += 1;
+                    stats.stored_bytes += block_size;
+                    pos += 1;
+                }
+                2 => {
+                    stats.compressed_blocks += 1;
+                    let (literals, literals_len) = literals_section(data, pos)?;
+                    stats.literals += literals;
+                    stats.sequences += match byte(pos + literals_len)? {
+                        n if n < 128 => n,
+                        n if n < 255 => ((n - 128) << 8) + byte(pos + literals_len + 1)?,
+                        _ => byte(pos + literals_len + 1)? + (byte(pos + literals_len + 2)? << 8) + 0x7F00,
+                    };
+                    pos += block_size;
+                }
+                _ => return Err("Reserved zstd block type".to_string()),
+            }
+            if pos > data.len() {
+                return Err(truncated());
+            }
+            if last {
+                return Ok(stats);
+            }
+        }
+    }
+
+    /// Regenerated size of a compressed block's literals section starting at `pos`, and the section's length
+    fn literals_section(data: &[u8], pos: usize) -> Result<(usize, usize), String> {
+        let header = data
+            .get(pos..(pos + 5).min(data.len()))
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| "Truncated zstd literals section".to_string())?;
+        let header = (0..5).fold(0u64, |acc, i| acc | (*header.get(i).unwrap_or(&0) as u64) << (8 * i));
+        let (block_type, size_format) = (header & 0x03, (header >> 2) & 0x03);
+        let bits = |shift: u64, n: u64| ((header >> shift) & ((1 << n) - 1)) as usize;
+        match (block_type, size_format) {
+            // raw and rle literals, content is the literals themselves or a single repeated byte
+            (0 | 1, 0 | 2) => Ok((bits(3, 5), 1 + if block_type == 0 { bits(3, 5) } else { 1 })),
+            (0 | 1, 1) => Ok((bits(4, 12), 2 + if block_type == 0 { bits(4, 12) } else { 1 })),
+            (0 | 1, _) => Ok((bits(4, 20), 3 + if block_type == 0 { bits(4, 20) } else { 1 })),
+            // huffman compressed literals, sizes of regenerated and compressed literals follow
+            (_, 0 | 1) => Ok((bits(4, 10), 3 + bits(14, 10))),
+            (_, 2) => Ok((bits(4, 14), 4 + bits(18, 14))),
+            _ => Ok((bits(4, 18), 5 + bits(22, 18))),
+        }
+    }
+
+    /// Compression with the options `compress` supports beyond libcramjam's, `magicless` frames, `workers`
+    /// threads and a prepared `dictionary`, which when given also sets the compression level.
+    fn compress_with<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<i32>,
+        magicless: bool,
+        checksum: bool,
+        workers: u32,
+        params: &[CParameter],
+        dictionary: Option<&EncoderDictionary<'static>>,
+    ) -> std::io::Result<usize> {
+        let mut encoder = match dictionary {
+            Some(dictionary) => Encoder::with_prepared_dictionary(BufReader::new(input), dictionary)?,
+            None => Encoder::new(input, level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))?,
+        };
+        if magicless {
+            encoder.include_magicbytes(false)?;
+        }
+        encoder.include_checksum(checksum)?;
+        if workers > 0 {
+            encoder.multithread(workers)?;
+        }
+        for param in params {
+            encoder.set_parameter(*param)?;
+        }
+        crate::io::copy(&mut encoder, output).map(|n| n as usize)
+    }
+
+    fn compress_with_prefix<W: Write + ?Sized, R: Read>(
+        mut input: R,
+        output: &mut W,
+        level: Option<i32>,
+        magicless: bool,
+        checksum: bool,
+        workers: u32,
+        params: &[CParameter],
+        prefix: &[u8],
+    ) -> std::io::Result<usize> {
+        // only the write encoder takes a prefix
+        let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+        let mut encoder = libcramjam::zstd::zstd::stream::write::Encoder::with_ref_prefix(vec![], level, prefix)?;
+        encoder.include_magicbytes(!magicless)?;
+        encoder.include_checksum(checksum)?;
+        if workers > 0 {
+            encoder.multithread(workers)?;
+        }
+        for param in params {
+            encoder.set_parameter(*param)?;
+        }
+        crate::io::copy(&mut input, &mut encoder)?;
+        let compressed = encoder.finish()?;
+        output.write_all(&compressed)?;
+        Ok(compressed.len())
+    }
+
+    /// Advanced decompression parameters, given from Python as a dict of zstd's `ZSTD_d_*` parameter
+    /// names, without the prefix, to integer values:
+    /// ```bash
+    /// windowLogMax: largest window size, as a power of 2, the decoder will allocate for a frame
+    /// format: 0 for the standard frame format, 1 for the magicless format
+    /// forceIgnoreChecksum: 1 to skip verifying frame checksums
+    /// ```
+    #[derive(Clone, Default)]
+    pub struct DecompressParams(Vec<DParameter>);
+
+    impl<'py> FromPyObject<'py> for DecompressParams {
+        fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+            let params = ob.extract::<HashMap<String, u32>>()?;
+            params
+                .into_iter()
+                .map(|(name, value)| match name.as_str() {
+                    "windowLogMax" => Ok(DParameter::WindowLogMax(value)),
+                    "format" => match value {
+                        0 => Ok(DParameter::Format(FrameFormat::One)),
+                        1 => Ok(DParameter::Format(FrameFormat::Magicless)),
+                        _ => Err(PyValueError::new_err(format!("Unknown zstd frame format {}", value))),
+                    },
+                    "forceIgnoreChecksum" => Ok(DParameter::ForceIgnoreChecksum(value != 0)),
+                    _ => Err(PyValueError::new_err(format!(
+                        "Unknown zstd decompression parameter '{}', expected one of {:?}",
+                        name,
+                        ["windowLogMax", "format", "forceIgnoreChecksum"]
+                    ))),
+                })
+                .collect::<PyResult<Vec<_>>>()
+                .map(Self)
+        }
+    }
+
+    impl DecompressParams {
+        fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+
+    fn decompress_with<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        params: &DecompressParams,
+        dictionary: &[u8],
+    ) -> std::io::Result<usize> {
+        let mut decoder = Decoder::with_dictionary(BufReader::new(input), dictionary)?;
+        for param in params.0.iter() {
+            decoder.set_parameter(*param)?;
+        }
+        crate::io::copy(&mut decoder, output).map(|n| n as usize)
+    }
+
+    fn decompress_with_prefix<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        params: &DecompressParams,
+        prefix: &[u8],
+    ) -> std::io::Result<usize> {
+        let mut decoder = Decoder::with_ref_prefix(BufReader::new(input), prefix)?;
+        for param in params.0.iter() {
+            decoder.set_parameter(*param)?;
+        }
+        crate::io::copy(&mut decoder, output).map(|n| n as usize)
     }
 
-    mod _decompressor {
+    pub(crate) mod _level {
         use super::*;
-        crate::make_decompressor!(zstd);
+        crate::make_level_range!(
+            zstd,
+            i32,
+            libcramjam::zstd::zstd::zstd_safe::min_c_level(),
+            libcramjam::zstd::zstd::zstd_safe::max_c_level()
+        );
     }
     #[pymodule_export]
-    use _decompressor::Decompressor;
+    use _level::{max_level, min_level};
+
+    mod _extras {
+        use super::*;
+        crate::make_codec_extras!(
+            zstd, level: i32;
+            checked, reproducible, bench, strict(b"\x28\xb5\x2f\xfd"), prefix, records, ring, compressed_size
+        );
+    }
+    #[pymodule_export]
+    use _extras::{
+        compress_checked, compress_into_ring, compress_reproducible, compressed_size, decompress_bench,
+        decompress_checked, decompress_prefix, decompress_records, decompress_strict,
+    };
+
+    /// Decompressor object for streaming decompression
+    /// Unlike a single `decompress` call, input may be fed in arbitrary pieces: a frame can span
+    /// several `decompress` calls, and concatenated frames are decoded one after another.
+    ///
+    /// Output is returned by `decompress` as it's produced rather than accumulated. What's held
+    /// back, ie. output decoded by a `decompress` call before it raised, is returned by `flush`;
+    /// `len()` and the `len`/`in`/`bool` protocols are over that held back output.
+    ///
+    /// `max_output_len` raises a `DecompressionError` as soon as the total output grows past that
+    /// many bytes, as for `decompress`.
+    #[pyclass]
+    pub struct Decompressor {
+        dctx: DCtx<'static>,
+        // output decoded but not yet returned
+        buffer: Vec<u8>,
+        // whether the input so far ends part way through a frame
+        in_frame: bool,
+        // whether `finish` was called, after which the instance can't be used
+        finished: bool,
+        max_output_len: Option<usize>,
+        produced: usize,
+    }
+
+    // `__bool__` rather than `is_empty`, as for the other codecs' Decompressor
+    #[allow(clippy::len_without_is_empty)]
+    #[pymethods]
+    impl Decompressor {
+        /// Initialize a new `Decompressor` instance, `params` as for `decompress`.
+        #[new]
+        #[pyo3(signature = (params=None, max_output_len=None))]
+        pub fn __init__(params: Option<DecompressParams>, max_output_len: Option<usize>) -> PyResult<Self> {
+            let mut dctx = DCtx::create();
+            for param in params.unwrap_or_default().0 {
+                dctx.set_parameter(param).map_err(|code| {
+                    PyValueError::new_err(format!(
+                        "Invalid zstd decompression parameter: {}",
+                        get_error_name(code)
+                    ))
+                })?;
+            }
+            Ok(Self {
+                dctx,
+                buffer: vec![],
+                in_frame: false,
+                finished: false,
+                max_output_len,
+                produced: 0,
+            })
+        }
+
+        /// Length of the output held back, see `flush`.
+        pub fn len(&self) -> usize {
+            self.buffer.len()
+        }
+
+        /// Decompress this input, returning only the output it produced rather than accumulating
+        /// it; so a stream can be passed on piece by piece.
+        pub fn decompress(&mut self, py: Python, mut input: BytesType) -> PyResult<RustyBuffer> {
+            self.check_not_finished()?;
+            let mut file_bytes = vec![];
+            let bytes = match &mut input {
+                BytesType::RustyFile(_) => {
+                    input.read_to_end(&mut file_bytes)?;
+                    &file_bytes[..]
+                }
+                _ => input.as_bytes(),
+            };
+            let (dctx, in_frame) = (&mut self.dctx, &mut self.in_frame);
+            let max_len = self.max_output_len.map(|max_len| max_len.saturating_sub(self.produced));
+            let held = self.buffer.len();
+            let mut writer = LimitedWriter::new(&mut self.buffer, max_len);
+            let result = py.allow_threads(|| decompress_stream(dctx, bytes, &mut writer, in_frame));
+            self.produced += self.buffer.len() - held;
+            result.map_err(DecompressionError::from_err)?;
+            Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
+        }
+
+        /// Return the output held back, which `decompress` would otherwise have returned; ie. that
+        /// decoded before it raised.
+        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+            self.check_not_finished()?;
+            Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
+        }
+
+        /// Consume the current Decompressor state and return any output held back, raising
+        /// `DecompressionError` if the input ended part way through a frame.
+        /// **NB** The Decompressor will not be usable after this method is called.
+        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
+            self.check_not_finished()?;
+            self.finished = true;
+            if self.in_frame {
+                return Err(DecompressionError::new_err("Input ended part way through a zstd frame"));
+            }
+            Ok(RustyBuffer::from(std::mem::take(&mut self.buffer)))
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+        fn __contains__(&self, py: Python, x: BytesType) -> bool {
+            let bytes = x.as_bytes();
+            let buffer = &self.buffer;
+            py.allow_threads(|| buffer.windows(bytes.len()).any(|w| w == bytes))
+        }
+        fn __repr__(&self) -> String {
+            format!("Decompressor<len={}>", self.len())
+        }
+        fn __bool__(&self) -> bool {
+            !self.finished && self.len() > 0
+        }
+    }
+
+    impl Decompressor {
+        fn check_not_finished(&self) -> PyResult<()> {
+            match self.finished {
+                true => Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                )),
+                false => Ok(()),
+            }
+        }
+    }
+
+    /// Feed `input` through the streaming decoder `dctx`, appending the output; the decoder keeps
+    /// any partial frame state, moving onto the next frame once one ends.
+    fn decompress_stream<W: Write + ?Sized>(
+        dctx: &mut DCtx<'static>,
+        input: &[u8],
+        output: &mut W,
+        in_frame: &mut bool,
+    ) -> std::io::Result<usize> {
+        if input.is_empty() {
+            return Ok(0);
+        }
+        let mut total = 0;
+        let mut in_buffer = InBuffer::around(input);
+        let mut chunk = vec![0u8; DCtx::out_size()];
+        loop {
+            let mut out_buffer = OutBuffer::around(&mut chunk[..]);
+            let hint = dctx
+                .decompress_stream(&mut out_buffer, &mut in_buffer)
+                .map_err(|code| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, get_error_name(code).to_string())
+                })?;
+            let n_bytes = out_buffer.pos();
+            output.write_all(&chunk[..n_bytes])?;
+            total += n_bytes;
+            // hint is 0 once a frame is fully decoded and flushed
+            *in_frame = hint != 0;
+            if in_buffer.pos() == input.len() && n_bytes < chunk.len() {
+                return Ok(total);
+            }
+        }
+    }
 }
@@ -60,3 +60,18 @@ def test_obj_api(tmpdir, Obj, is_pypy):
     buf.truncate()
     buf.seek(0)
     assert buf.read() == b""
+
+
+def test_buffer_readinto_fixed_size_output():
+    buf = Buffer(b"0123456789")
+    out = bytearray(4)
+
+    chunks = []
+    while n := buf.readinto(out):
+        chunks.append(bytes(out[:n]))
+    assert chunks == [b"0123", b"4567", b"89"]
+    assert buf.tell() == 10
+    assert buf.readinto(out) == 0
+
+    buf.seek(20)
+    assert buf.readinto(out) == 0
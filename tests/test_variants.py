@@ -1,9 +1,14 @@
 import os
+import sys
 import gzip
 import pytest
 import numpy as np
 import cramjam
 import hashlib
+import io
+import json
+import warnings
+import zlib
 from datetime import timedelta
 from hypothesis import strategies as st, given, settings
 from hypothesis.extra import numpy as st_np
@@ -133,12 +138,20 @@ def test_variants_compress_into(
         output = cramjam.File(str(path))
     elif output_type == cramjam.Buffer:
         output = cramjam.Buffer()
+    elif output_type == memoryview:
+        output = memoryview(bytearray(b"0" * compressed_len))
     else:
         output = output_type(b"0" * compressed_len)
 
     if is_pypy and isinstance(output, (bytes, memoryview)):
         pytest.xfail(reason="PyPy de/compress_into w/ bytes or memoryview is a bit flaky behavior")
 
+    if output_type == bytes:
+        # immutable, so never written to
+        with pytest.raises(BufferError):
+            variant.compress_into(input, output)
+        return
+
     n_bytes = variant.compress_into(input, output)
     assert n_bytes == compressed_len
 
@@ -192,12 +205,20 @@ def test_variants_decompress_into(
         output = cramjam.File(str(path))
     elif output_type == cramjam.Buffer:
         output = cramjam.Buffer()
+    elif output_type == memoryview:
+        output = memoryview(bytearray(b"0" * len(raw_data)))
     else:
         output = output_type(b"0" * len(raw_data))
 
     if is_pypy and isinstance(output, (bytes, memoryview)):
         pytest.xfail(reason="PyPy de/compress_into w/ bytes or memoryview is a bit flaky behavior")
 
+    if output_type == bytes:
+        # immutable, so never written to
+        with pytest.raises(BufferError):
+            variant.decompress_into(input, output)
+        return
+
     n_bytes = variant.decompress_into(input, output)
     assert n_bytes == len(raw_data)
 
@@ -367,20 +388,2385 @@ def test_variants_stream_decompressors(variant_str):
 
     compressed = variant.compress(b"bytes")
     for _ in range(2):
-        n_bytes = decompressor.decompress(bytes(compressed))
-        assert n_bytes == 5
-    assert bytes(decompressor.flush()) == b"bytesbytes"
+        out = decompressor.decompress(bytes(compressed))
+        assert len(out) == 5
+        assert bytes(out) == b"bytes"
+    # output is returned as it's produced, leaving nothing held back
+    assert bytes(decompressor.flush()) == b""
     assert bytes(decompressor.flush()) == b""
 
-    decompressor.decompress(bytes(compressed))
-    assert bytes(decompressor.finish()) == b"bytes"
+    out = decompressor.decompress(bytes(compressed))
+    assert bytes(out) + bytes(decompressor.finish()) == b"bytes"
 
     # Calling .finish renders decompressor unusable after. (API consistency with other libs)
     with pytest.raises(cramjam.DecompressionError):
         decompressor.finish()
 
 
+def test_decompressor_flush_after_error():
+    data = b"some bytes here" * 100
+    compressed = gzip.compress(data)
+    corrupted = compressed[:-8] + bytes(4) + compressed[-4:]  # wrong CRC32
+
+    decompressor = cramjam.gzip.Decompressor()
+    with pytest.raises(cramjam.DecompressionError, match="CRC32"):
+        decompressor.decompress(corrupted)
+    # the output decoded before the error is held back, not lost
+    assert len(decompressor) == len(data) and decompressor
+    assert b"bytes here" in decompressor
+    assert bytes(decompressor.flush()) == data
+    assert len(decompressor) == 0 and not decompressor
+
+
 def test_buffer_cmp():
     assert cramjam.Buffer() == cramjam.Buffer()
     assert cramjam.Buffer(b"some bytes") == cramjam.Buffer(b"some bytes")
     assert cramjam.Buffer(b"some bytes") != cramjam.Buffer(b"other bytes")
+
+
+@given(data=st.binary(), start=st.integers(-20, 20), stop=st.integers(-20, 20), step=st.sampled_from([None, 1, 2, -1, -3]))
+def test_buffer_len_getitem(data, start, stop, step):
+    buffer = cramjam.Buffer(data)
+    assert len(buffer) == len(data)
+    assert buffer[start:stop:step] == data[start:stop:step]
+    for index in (start, stop):
+        if -len(data) <= index < len(data):
+            assert buffer[index] == data[index]
+        else:
+            with pytest.raises(IndexError):
+                buffer[index]
+    assert buffer.tell() == 0
+    with pytest.raises(TypeError):
+        buffer["0"]
+
+
+@given(data=st.binary())
+def test_gzip_compress_reproducible(data):
+    first = bytes(cramjam.gzip.compress_reproducible(data, level=6))
+    second = bytes(cramjam.gzip.compress_reproducible(data, level=6))
+    assert first == second
+
+    # mtime=0, no FNAME/FCOMMENT flags, OS=255
+    assert first[3] == 0
+    assert first[4:8] == b"\x00\x00\x00\x00"
+    assert first[9] == 255
+    assert gzip.decompress(first) == data
+
+
+@pytest.mark.parametrize(
+    "variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib")
+)
+def test_variants_compress_reproducible(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"".join(b"line %d of some data\n" % i for i in range(10_000))
+    if variant_str == "snappy":
+        first, second = variant.compress_reproducible(data), variant.compress_reproducible(data)
+    else:
+        level = variant.max_level()
+        first, second = variant.compress_reproducible(data, level), variant.compress_reproducible(data, level)
+        with pytest.raises(cramjam.CompressionError):
+            variant.compress_reproducible(data, variant.max_level() + 1)
+    assert bytes(first) == bytes(second)
+    assert bytes(variant.decompress(first)) == data
+
+
+@pytest.mark.parametrize("mode", ("sync", "full", "partial"))
+@given(first=st.binary(min_size=1), second=st.binary())
+def test_deflate_compressor_flush_modes(mode, first, second):
+    import zlib
+
+    compressor = cramjam.deflate.Compressor()
+    compressor.compress(first)
+    flushed = bytes(compressor.flush(mode=mode))
+
+    # Everything written before the flush is decodable by a separate decompressor
+    assert zlib.decompressobj(-zlib.MAX_WBITS).decompress(flushed) == first
+
+    compressor.compress(second)
+    out = flushed + bytes(compressor.flush(mode=mode)) + bytes(compressor.finish())
+    assert bytes(cramjam.deflate.decompress(out)) == first + second
+
+
+def test_deflate_compressor_flush_unknown_mode():
+    with pytest.raises(ValueError):
+        cramjam.deflate.Compressor().flush(mode="finish")
+
+
+@pytest.mark.parametrize(
+    "variant_str,expected",
+    (
+        ("brotli", ["flush"]),
+        ("bzip2", ["flush"]),
+        ("deflate", ["sync", "full", "partial"]),
+        ("gzip", ["sync"]),
+        ("lz4", ["flush"]),
+        ("snappy", ["flush"]),
+        ("xz", []),
+        ("zlib", ["sync"]),
+        ("zstd", ["flush"]),
+    ),
+)
+def test_compressor_flush_modes(variant_str, expected):
+    Compressor = getattr(cramjam, variant_str).Compressor
+    assert Compressor.flush_modes() == expected
+    assert Compressor().flush_modes() == expected
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zlib"))
+def test_compressor_flush_sync_mode(variant_str):
+    variant = getattr(cramjam, variant_str)
+    compressor = variant.Compressor()
+    compressor.compress(b"some bytes here")
+    flushed = bytes(compressor.flush(mode="sync"))
+    out = flushed + bytes(compressor.flush()) + bytes(compressor.finish())
+    assert bytes(variant.decompress(out)) == b"some bytes here"
+    with pytest.raises(ValueError):
+        compressor.flush(mode="full")
+
+
+@pytest.mark.parametrize("variant_str", ("deflate", "zstd"))
+def test_compressor_context_manager(variant_str):
+    variant = getattr(cramjam, variant_str)
+    with variant.Compressor() as compressor:
+        compressor.compress(b"first chunk, ")
+        compressor.compress(b"second chunk")
+    assert bytes(variant.decompress(compressor.finish())) == b"first chunk, second chunk"
+    with pytest.raises(cramjam.CompressionError, match="finish"):
+        compressor.compress(b"more")
+
+    # the stream is still finished, releasing the encoder, when the body raises
+    with pytest.raises(KeyError):
+        with variant.Compressor() as compressor:
+            compressor.compress(b"partial")
+            raise KeyError
+    assert bytes(variant.decompress(compressor.finish())) == b"partial"
+
+
+def test_shuffle_improves_float64_compression(tmp_path):
+    x = np.linspace(0, 1_000, 100_000, dtype=np.float64)
+
+    shuffled = cramjam.shuffle(x, 8)
+    assert len(cramjam.zstd.compress(shuffled)) < len(cramjam.zstd.compress(x))
+
+    unshuffled = cramjam.unshuffle(cramjam.zstd.decompress(cramjam.zstd.compress(shuffled)), 8)
+    assert np.array_equal(np.frombuffer(bytes(unshuffled), dtype=np.float64), x)
+
+    path = tmp_path / "data"
+    path.write_bytes(x.tobytes())
+    assert bytes(cramjam.shuffle(cramjam.File(str(path)), 8)) == bytes(shuffled)
+    path.write_bytes(bytes(shuffled))
+    assert bytes(cramjam.unshuffle(cramjam.File(str(path)), 8)) == x.tobytes()
+
+
+@pytest.mark.parametrize("element_size", (1, 2, 3, 8))
+@given(data=st.binary())
+def test_shuffle_roundtrip(element_size, data):
+    assert bytes(cramjam.unshuffle(cramjam.shuffle(data, element_size), element_size)) == data
+
+
+def test_bitshuffle_improves_sensor_compression(tmp_path):
+    rng = np.random.default_rng(0)
+    readings = rng.normal(1_000, 5, 100_000).astype(np.int32)
+
+    shuffled = cramjam.bitshuffle(readings, 4)
+    assert len(cramjam.zstd.compress(shuffled)) < len(cramjam.zstd.compress(readings))
+
+    unshuffled = cramjam.bitunshuffle(cramjam.zstd.decompress(cramjam.zstd.compress(shuffled)), 4)
+    assert np.array_equal(np.frombuffer(bytes(unshuffled), dtype=np.int32), readings)
+
+    path = tmp_path / "data"
+    path.write_bytes(readings.tobytes())
+    assert bytes(cramjam.bitshuffle(cramjam.File(str(path)), 4)) == bytes(shuffled)
+    path.write_bytes(bytes(shuffled))
+    assert bytes(cramjam.bitunshuffle(cramjam.File(str(path)), 4)) == readings.tobytes()
+
+
+@pytest.mark.parametrize("element_size", (1, 2, 3, 8))
+@given(data=st.binary())
+def test_bitshuffle_roundtrip(element_size, data):
+    assert bytes(cramjam.bitunshuffle(cramjam.bitshuffle(data, element_size), element_size)) == data
+
+
+def test_delta_improves_slowly_varying_signal(tmp_path):
+    signal = (np.sin(np.linspace(0, 20, 100_000)) * 10_000).astype(np.int16)
+
+    encoded = cramjam.delta_encode(signal, 2)
+    assert len(cramjam.xz.compress(encoded)) < len(cramjam.xz.compress(signal))
+
+    decoded = cramjam.delta_decode(cramjam.xz.decompress(cramjam.xz.compress(encoded)), 2)
+    assert np.array_equal(np.frombuffer(bytes(decoded), dtype=np.int16), signal)
+
+    path = tmp_path / "data"
+    path.write_bytes(signal.tobytes())
+    assert bytes(cramjam.delta_encode(cramjam.File(str(path)), 2)) == bytes(encoded)
+    path.write_bytes(bytes(encoded))
+    assert bytes(cramjam.delta_decode(cramjam.File(str(path)), 2)) == signal.tobytes()
+
+
+def test_delta_encode_matches_xz_semantics():
+    # Byte-wise difference to the byte `dist` positions back, wrapping, history starts at zero
+    assert bytes(cramjam.delta_encode(b"\x01\x02\x03\x05\x00", 1)) == b"\x01\x01\x01\x02\xfb"
+    assert bytes(cramjam.delta_encode(b"\x01\x02\x03\x05", 2)) == b"\x01\x02\x02\x03"
+
+
+@pytest.mark.parametrize("element_size", (1, 2, 4, 8))
+@given(data=st.binary())
+def test_delta_roundtrip(element_size, data):
+    assert bytes(cramjam.delta_decode(cramjam.delta_encode(data, element_size), element_size)) == data
+
+
+def test_xz_delta_filter_chain():
+    import lzma
+    import shutil
+    import subprocess
+
+    data = (np.sin(np.linspace(0, 20, 50_000)) * 10_000).astype(np.int16).tobytes()
+
+    chain = cramjam.xz.FilterChain()
+    chain.append_filter(cramjam.xz.FilterChainItem(cramjam.xz.Filter.Delta, cramjam.xz.Options().set_dist(2)))
+    chain.append_filter(cramjam.xz.FilterChainItem(cramjam.xz.Filter.Lzma2))
+    compressed = bytes(cramjam.xz.compress(data, filters=chain))
+
+    # Filter chain is embedded in the stream, so it decodes without being told about it
+    assert bytes(cramjam.xz.decompress(compressed)) == data
+    assert lzma.decompress(compressed) == data
+    assert len(compressed) < len(cramjam.xz.compress(data))
+
+    # the RAW format is the bare output of the chain, decoded given the same chain
+    raw = bytes(cramjam.xz.compress(data, format=cramjam.xz.Format.RAW, filters=chain))
+    lzma_filters = [{"id": lzma.FILTER_DELTA, "dist": 2}, {"id": lzma.FILTER_LZMA2, "preset": 6}]
+    assert lzma.decompress(raw, format=lzma.FORMAT_RAW, filters=lzma_filters) == data
+    assert bytes(cramjam.xz.decompress(raw, format=cramjam.xz.Format.RAW, filters=chain)) == data
+    with pytest.raises(ValueError):
+        cramjam.xz.decompress(raw, format=cramjam.xz.Format.RAW)
+    with pytest.raises(cramjam.CompressionError):
+        cramjam.xz.compress(data, format=cramjam.xz.Format.RAW)
+
+    # options are the defaults of the chain's own
+    small_dict = bytes(cramjam.xz.compress(data, filters=chain, options=cramjam.xz.Options().set_dict_size(1 << 12)))
+    assert lzma.decompress(small_dict) == data
+    assert small_dict != compressed
+
+    xz = shutil.which("xz")
+    if xz is None:
+        pytest.skip("xz CLI not available")
+    expected = subprocess.run([xz, "--format=xz", "--delta=dist=2", "--lzma2", "--stdout"], input=data, capture_output=True, check=True).stdout
+    assert compressed == expected
+
+
+def test_zstd_record_writer_reader(tmp_path):
+    records = [b"first", b"", b"second record" * 100, bytes(range(256))]
+
+    writer = cramjam.zstd.RecordWriter(level=3)
+    for record in records:
+        assert writer.append(record) > 4
+    path = tmp_path / "record"
+    path.write_bytes(records[0])
+    assert writer.append(cramjam.File(str(path))) > 4
+    log = writer.finish()
+
+    assert [bytes(r) for r in cramjam.zstd.RecordReader(log)] == records + records[:1]
+
+    with pytest.raises(cramjam.CompressionError):
+        writer.append(b"after finish")
+
+    with pytest.raises(cramjam.DecompressionError):
+        list(cramjam.zstd.RecordReader(bytes(log)[:-1]))
+
+
+def test_zstd_seekable_read_range(tmp_path):
+    data = b"".join(i.to_bytes(4, "little") for i in range(10_000))
+    path = tmp_path / "data"
+    path.write_bytes(data[15_000:])
+
+    writer = cramjam.zstd.SeekableWriter(frame_size=4096)
+    writer.compress(data[:15_000])
+    assert writer.compress(cramjam.File(str(path))) == len(data) - 15_000
+    compressed = writer.finish()
+
+    # Still a regular zstd stream; seek table lives in a skippable frame
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+    reader = cramjam.zstd.SeekableReader(compressed)
+    assert len(reader) == len(data)
+    assert not reader.is_empty() and reader
+    assert reader.n_frames() == 10
+    assert bytes(reader.read_range(10_000, 5_000)) == data[10_000:15_000]
+    assert bytes(reader.read_range(len(data) - 10, 100)) == data[-10:]
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.SeekableReader(cramjam.zstd.compress(data))
+
+    # a seek table entry disagreeing with its frame, here the last entry's decompressed size
+    corrupted = bytearray(bytes(compressed))
+    d_size = int.from_bytes(corrupted[-13:-9], "little")
+    corrupted[-13:-9] = (d_size + 1_000).to_bytes(4, "little")
+    reader = cramjam.zstd.SeekableReader(bytes(corrupted))
+    with pytest.raises(cramjam.DecompressionError, match="seek table"):
+        reader.read_range(len(data) - 10, 100)
+
+
+@pytest.mark.parametrize("variant_str", ("brotli", "bzip2", "deflate", "gzip", "lz4", "snappy", "zstd"))
+def test_variants_checked_detects_corruption(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+
+    checked = bytes(variant.compress_checked(data))
+    assert bytes(variant.decompress_checked(checked)) == data
+
+    path = tmp_path / "blob"
+    path.write_bytes(checked)
+    assert bytes(variant.decompress_checked(cramjam.File(str(path)))) == data
+    path.write_bytes(data)
+    assert bytes(variant.compress_checked(cramjam.File(str(path)))) == checked
+
+    # A corrupted footer is always caught; a corrupted body is caught either by
+    # the codec itself or by the CRC32 footer.
+    corrupted_footer = checked[:-1] + bytes([checked[-1] ^ 0xFF])
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress_checked(corrupted_footer)
+
+    corrupted_body = bytearray(checked)
+    corrupted_body[len(checked) // 2] ^= 0xFF
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress_checked(bytes(corrupted_body))
+
+
+def test_variants_interrupted_by_signal():
+    import _thread
+    import threading
+    import time
+
+    # Large enough that compression is still running well after the interrupt is sent
+    data = os.urandom(1 << 26)
+
+    timer = threading.Timer(0.1, _thread.interrupt_main)
+    timer.start()
+    start = time.monotonic()
+    try:
+        with pytest.raises(KeyboardInterrupt):
+            cramjam.bzip2.compress(data)
+    finally:
+        timer.cancel()
+    assert time.monotonic() - start < 10
+
+
+def test_zstd_compress_debug(tmp_path):
+    data = b"some bytes here " * 10_000 + os.urandom(1 << 18)
+    compressed, stats = cramjam.zstd.compress_debug(data, level=3)
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+    assert stats["input_size"] == len(data)
+    assert stats["output_size"] == len(compressed)
+    assert stats["blocks"] == stats["raw_blocks"] + stats["rle_blocks"] + stats["compressed_blocks"]
+    assert stats["blocks"] >= len(data) // (128 * 1024)
+    assert stats["compressed_blocks"] > 0
+    assert 0 < stats["sequences"] <= stats["matched_bytes"]
+    assert stats["literals"] + stats["matched_bytes"] <= len(data)
+
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    _, file_stats = cramjam.zstd.compress_debug(cramjam.File(str(path)), level=3)
+    assert file_stats == stats
+
+
+def test_zstd_magicless_frames():
+    data = b"some bytes here" * 1_000
+
+    compressed = bytes(cramjam.zstd.compress(data, magicless=True))
+    normal = bytes(cramjam.zstd.compress(data))
+    assert len(compressed) == len(normal) - 4
+    assert not compressed.startswith(normal[:4])
+    assert bytes(cramjam.zstd.decompress(compressed, magicless=True)) == data
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(compressed)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(normal, magicless=True)
+
+
+def test_decompression_error_context():
+    data = b"some bytes here" * 1_000
+    compressed = bytearray(cramjam.zstd.compress(data))
+    compressed[0] ^= 0xFF  # corrupt the magic number
+
+    with pytest.raises(cramjam.DecompressionError) as excinfo:
+        cramjam.zstd.decompress(bytes(compressed))
+    err = excinfo.value
+    assert err.codec == "zstd"
+    assert 0 < err.consumed <= len(compressed)
+    assert err.produced == 0
+    assert err.backend_code == 10  # ZSTD_error_prefix_unknown
+    assert err.zstd_code == 10
+    assert err.zstd_name == "ZSTD_error_prefix_unknown"
+
+    # Errors raised without context still carry the attributes
+    with pytest.raises(cramjam.DecompressionError) as excinfo:
+        cramjam.zstd.decompress_checked(b"")
+    assert excinfo.value.codec is None
+    assert excinfo.value.backend_code is None
+    assert excinfo.value.zstd_name is None
+
+
+def test_looks_compressed(tmp_path):
+    text = b"some plain, very compressible text here. " * 1_000
+    assert not cramjam.looks_compressed(text)
+    assert not cramjam.looks_compressed(b"short")
+    assert cramjam.looks_compressed(gzip.compress(text))
+    assert cramjam.looks_compressed(bytes(cramjam.zstd.compress(text)))
+    assert cramjam.looks_compressed(os.urandom(1 << 16))
+    # no magic bytes, but high entropy
+    assert cramjam.looks_compressed(bytes(cramjam.deflate.compress(os.urandom(1 << 16))))
+
+    path = tmp_path / "blob"
+    path.write_bytes(gzip.compress(text))
+    file = cramjam.File(str(path))
+    assert cramjam.looks_compressed(file)
+    assert file.tell() == 0
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip", "xz", "bzip2", "lz4", "snappy", "zlib"))
+def test_guess_codec(codec, tmp_path):
+    data = b"some plain, very compressible text here. " * 100
+    blob = bytes(getattr(cramjam, codec).compress(data))
+    assert cramjam.guess_codec(blob) == codec
+    assert bytes(getattr(cramjam, cramjam.guess_codec(blob)).decompress(blob)) == data
+
+    # a file is only peeked at
+    path = tmp_path / "blob"
+    path.write_bytes(blob)
+    file = cramjam.File(str(path))
+    assert cramjam.guess_codec(file) == codec
+    assert file.tell() == 0
+
+
+def test_guess_codec_stdlib_zlib_and_unknown():
+    for level in range(-1, 10):
+        assert cramjam.guess_codec(zlib.compress(b"some bytes here", level)) == "zlib"
+    for data in (b"", b"x", b"plain text", bytes(cramjam.brotli.compress(b"some bytes here"))):
+        assert cramjam.guess_codec(data) is None
+
+
+def test_gzip_compress_filename_comment_latin1():
+    data = b"some bytes here"
+    compressed = bytes(cramjam.gzip.compress(data, filename="data.txt", comment="café"))
+    assert gzip.decompress(compressed) == data
+
+    FNAME, FCOMMENT = 0x08, 0x10
+    assert compressed[3] & FNAME and compressed[3] & FCOMMENT
+    filename, comment, _ = compressed[10:].split(b"\x00", 2)
+    assert filename.decode("latin-1") == "data.txt"
+    assert comment == "café".encode("latin-1")
+
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress(data, filename="snowman-☃.txt")
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress(data, comment="nul\x00byte")
+
+
+def test_gzip_header_fields(tmp_path):
+    data = b"some bytes here"
+    compressed = bytes(cramjam.gzip.compress(data, filename="data.txt", comment="café", mtime=1_700_000_000))
+    assert gzip.decompress(compressed) == data
+    header = cramjam.gzip.read_header(compressed)
+    assert header == {"filename": "data.txt", "mtime": 1_700_000_000, "comment": "café", "os": header["os"]}
+
+    path = tmp_path / "data.txt.gz"
+    path.write_bytes(compressed)
+    file = cramjam.File(str(path))
+    assert cramjam.gzip.read_header(file) == header
+    assert file.tell() == 0
+
+    # stdlib's gzip writes the same header fields
+    stdlib = gzip.compress(data, mtime=1_700_000_000)
+    assert cramjam.gzip.read_header(stdlib)["mtime"] == 1_700_000_000
+
+    compressor = cramjam.gzip.Compressor(filename="stream.log", mtime=1_700_000_000)
+    compressor.compress(data)
+    streamed = bytes(compressor.finish())
+    assert gzip.decompress(streamed) == data
+    assert cramjam.gzip.read_header(streamed)["filename"] == "stream.log"
+    assert cramjam.gzip.read_header(streamed)["mtime"] == 1_700_000_000
+
+    absent = cramjam.gzip.read_header(cramjam.gzip.compress(data))
+    assert (absent["filename"], absent["mtime"], absent["comment"]) == (None, None, None)
+
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress(data, mtime=1, minimal=True)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.read_header(compressed[:12])
+
+
+def test_gzip_compress_from_sync_reader_flaky():
+    data = os.urandom(1 << 16) + b"some bytes here" * 10_000
+
+    class FlakyReader:
+        def __init__(self, data):
+            self.data = data
+            self.pos = 0
+            self.calls = 0
+
+        def read(self, n):
+            self.calls += 1
+            n = min(n, 1 + self.calls % 7)  # short reads
+            chunk = self.data[self.pos : self.pos + n]
+            self.pos += len(chunk)
+            return bytearray(chunk) if self.calls % 2 else chunk
+
+    compressed = cramjam.gzip.compress_from_sync_reader(FlakyReader(data), level=1)
+    assert gzip.decompress(bytes(compressed)) == data
+
+    class FailingReader:
+        def read(self, n):
+            raise OSError("connection reset")
+
+    with pytest.raises(OSError, match="connection reset"):
+        cramjam.gzip.compress_from_sync_reader(FailingReader())
+
+    class NonBlockingReader:
+        def read(self, n):
+            return None  # nothing available yet
+
+    with pytest.raises(BlockingIOError):
+        cramjam.gzip.compress_from_sync_reader(NonBlockingReader())
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd"))
+def test_variants_decompress_frames(variant_str):
+    variant = getattr(cramjam, variant_str)
+    parts = [os.urandom(1_000), b"some bytes here" * 1_000, b"", b"last"]
+    stream = b"".join(bytes(variant.compress(part)) for part in parts)
+
+    frames = [bytes(frame) for frame in variant.decompress_frames(stream)]
+    assert frames == parts
+    assert b"".join(frames) == bytes(variant.decompress(stream))
+
+    with pytest.raises(cramjam.DecompressionError):
+        list(variant.decompress_frames(stream + b"garbage"))
+
+
+def test_zstd_decompress_params():
+    data = os.urandom(1 << 10) * 1024  # 1MiB, needing a window larger than 2**10
+    compressed = bytes(cramjam.zstd.compress(data, level=3))
+
+    assert bytes(cramjam.zstd.decompress(compressed, params={"windowLogMax": 27})) == data
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(compressed, params={"windowLogMax": 10})
+
+    decompressor = cramjam.zstd.Decompressor(params={"windowLogMax": 10})
+    with pytest.raises(OSError):
+        decompressor.decompress(compressed)
+
+    with pytest.raises(ValueError, match="notAParameter"):
+        cramjam.zstd.decompress(compressed, params={"notAParameter": 1})
+
+
+def test_buffer_with_capacity_decompress_into():
+    data = b"some bytes here" * 10_000
+    compressed = gzip.compress(data)
+    stored_size = int.from_bytes(compressed[-4:], "little")  # gzip ISIZE trailer
+
+    buffer = cramjam.Buffer.with_capacity(stored_size)
+    assert len(buffer) == 0
+    capacity = buffer.capacity()
+    assert capacity >= stored_size
+
+    assert cramjam.gzip.decompress_into(compressed, buffer) == len(data)
+    assert buffer.capacity() == capacity  # filled without reallocating
+    buffer.seek(0)
+    assert buffer.read() == data
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_ctypes_input(variant_str):
+    import ctypes
+
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+
+    char_array = (ctypes.c_char * len(data)).from_buffer_copy(data)
+    assert bytes(variant.compress(char_array)) == bytes(variant.compress(data))
+
+    int_array = (ctypes.c_int32 * 4)(1, 2, 3, 4)
+    compressed = variant.compress(int_array)
+    assert bytes(variant.decompress(compressed)) == bytes(int_array)
+
+    with pytest.raises(TypeError):
+        variant.compress(ctypes.pointer(char_array))
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_numpy_and_array_io(variant_str):
+    import array
+
+    variant = getattr(cramjam, variant_str)
+    data = b"some sensor readings " * 100
+    compressed = bytes(variant.compress(data))
+
+    assert bytes(variant.compress(np.frombuffer(data, dtype=np.uint8))) == compressed
+    assert bytes(variant.compress(array.array("B", data))) == compressed
+
+    output = np.empty(len(data), dtype=np.uint8)
+    assert variant.decompress_into(np.frombuffer(compressed, dtype=np.uint8), output) == len(data)
+    assert output.tobytes() == data
+    output = array.array("B", bytes(len(data)))
+    assert variant.decompress_into(array.array("B", compressed), output) == len(data)
+    assert output.tobytes() == data
+
+    with pytest.raises(TypeError):
+        variant.compress(np.frombuffer(data, dtype=np.uint8)[::2])
+    with pytest.raises(TypeError):
+        variant.decompress_into(compressed, np.empty(2 * len(data), dtype=np.uint8)[::2])
+
+
+def test_zstd_compress_batch_dictionary(tmp_path):
+    dictionary = b"".join(b"common header %d, shared across documents; " % i for i in range(200))
+    inputs = [b"common header %d, document %d body " % (i % 200, i) * 10 for i in range(100)]
+
+    batch = cramjam.zstd.compress_batch(inputs, level=3, dictionary=dictionary)
+    assert len(batch) == len(inputs)
+    for data, compressed in zip(inputs, batch):
+        assert bytes(compressed) == bytes(cramjam.zstd.compress(data, level=3, dictionary=dictionary))
+        assert bytes(cramjam.zstd.decompress(compressed, dictionary=dictionary)) == data
+
+    # The dictionary pays off on small, similar documents
+    with_dict = sum(len(c) for c in batch)
+    without_dict = sum(len(c) for c in cramjam.zstd.compress_batch(inputs, level=3))
+    assert with_dict < without_dict
+
+    assert cramjam.zstd.compress_batch([]) == []
+
+    dictionary_path, input_path = tmp_path / "dictionary", tmp_path / "input"
+    dictionary_path.write_bytes(dictionary)
+    input_path.write_bytes(inputs[0])
+    compressed = cramjam.zstd.compress(
+        cramjam.File(str(input_path)), level=3, dictionary=cramjam.File(str(dictionary_path))
+    )
+    assert bytes(compressed) == bytes(batch[0])
+    assert bytes(cramjam.zstd.decompress(compressed, dictionary=cramjam.File(str(dictionary_path)))) == inputs[0]
+    [compressed] = cramjam.zstd.compress_batch(
+        [cramjam.File(str(input_path))], level=3, dictionary=cramjam.File(str(dictionary_path))
+    )
+    assert bytes(compressed) == bytes(batch[0])
+
+
+def test_zstd_compress_best_dict(tmp_path):
+    json_dict = b"".join(b'{"user_id": %d, "event": "page_view", "path": "/home"}' % i for i in range(200))
+    csv_dict = b"".join(b"%d,2024-01-01,widget,blue,12.50\n" % i for i in range(200))
+    data = b'{"user_id": 4242, "event": "page_view", "path": "/home"}'
+
+    compressed, index = cramjam.zstd.compress_best_dict(data, [csv_dict, json_dict], level=3)
+    assert index == 1
+    assert bytes(compressed) == bytes(cramjam.zstd.compress(data, level=3, dictionary=json_dict))
+    assert bytes(cramjam.zstd.decompress(compressed, dictionary=json_dict)) == data
+
+    _, index = cramjam.zstd.compress_best_dict(b"7,2024-01-01,widget,blue,12.50\n", [csv_dict, json_dict])
+    assert index == 0
+
+    (tmp_path / "data").write_bytes(data)
+    (tmp_path / "json_dict").write_bytes(json_dict)
+    files = [cramjam.File(str(tmp_path / "data")), cramjam.File(str(tmp_path / "json_dict"))]
+    _, index = cramjam.zstd.compress_best_dict(files[0], [csv_dict, files[1]], level=3)
+    assert index == 1
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress_best_dict(data, [])
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz"))
+def test_variants_compress_return_len(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+
+    buffer, length = variant.compress(data, return_len=True)
+    assert length == len(buffer)
+    assert bytes(buffer) == bytes(variant.compress(data))
+
+
+def test_decompress_reject_nested():
+    data = b"some bytes here" * 1_000
+    nested = gzip.compress(gzip.compress(data))
+
+    inner = bytes(cramjam.gzip.decompress(nested))
+    assert gzip.decompress(inner) == data
+
+    with pytest.raises(cramjam.DecompressionError, match="nested"):
+        cramjam.gzip.decompress(nested, reject_nested=True)
+    assert bytes(cramjam.gzip.decompress(inner, reject_nested=True)) == data
+
+
+def test_text_compress_decompress(tmp_path):
+    text = "some text here, ünïcödé 🐍 " * 100
+
+    compressed = cramjam.text.compress(text, "gzip")
+    assert gzip.decompress(bytes(compressed)) == text.encode("utf-8")
+    assert cramjam.text.decompress(compressed, "gzip") == text
+
+    path = tmp_path / "compressed.gz"
+    path.write_bytes(bytes(compressed))
+    assert cramjam.text.decompress(cramjam.File(str(path)), "gzip") == text
+
+    latin1 = "café " * 100
+    compressed = cramjam.text.compress(latin1, "zstd", encoding="latin-1")
+    assert cramjam.text.decompress(compressed, "zstd", encoding="latin-1") == latin1
+
+    with pytest.raises(UnicodeDecodeError):
+        cramjam.text.decompress(gzip.compress(b"\xff\xfe invalid utf-8"), "gzip")
+    with pytest.raises(ValueError):
+        cramjam.text.compress(text, "not-a-codec")
+
+
+def test_gzip_decompress_on_crc_error():
+    data = b"some bytes here" * 1_000
+    compressed = bytearray(gzip.compress(data))
+    compressed[-8] ^= 0xFF  # corrupt the CRC32 trailer
+    compressed = bytes(compressed)
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.decompress(compressed)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.decompress(compressed, on_crc_error="raise")
+
+    with pytest.warns(UserWarning, match="CRC32"):
+        assert bytes(cramjam.gzip.decompress(compressed, on_crc_error="warn")) == data
+
+    with warnings.catch_warnings():
+        warnings.simplefilter("error")
+        assert bytes(cramjam.gzip.decompress(compressed, on_crc_error="ignore")) == data
+        # intact, multi-member input doesn't warn either
+        intact = gzip.compress(data) + gzip.compress(data)
+        assert bytes(cramjam.gzip.decompress(intact, on_crc_error="warn")) == data * 2
+
+    with pytest.raises(ValueError):
+        cramjam.gzip.decompress(compressed, on_crc_error="bogus")
+
+
+def test_zstd_compress_pipe_backpressure(tmp_path):
+    item_len = 64 * 1024
+    max_buffered = 256 * 1024
+    items = [os.urandom(item_len) for _ in range(100)]  # incompressible, output ~ input
+    pulled = 0
+
+    def produce():
+        nonlocal pulled
+        for item in items:
+            pulled += len(item)
+            yield item
+
+    yielded = 0
+    chunks = []
+    # a slow consumer: the pipe must not run ahead of what it has handed out
+    for chunk in cramjam.zstd.compress_pipe(produce(), max_buffered):
+        yielded += len(chunk)
+        chunks.append(bytes(chunk))
+        # bounded by the buffer, one item, and zstd's own internal block buffer
+        assert pulled - yielded <= max_buffered + item_len + 256 * 1024
+
+    assert len(chunks) > 1
+    assert bytes(cramjam.zstd.decompress(b"".join(chunks))) == b"".join(items)
+
+    path = tmp_path / "item"
+    path.write_bytes(items[0])
+    chunks = [bytes(chunk) for chunk in cramjam.zstd.compress_pipe(iter([cramjam.File(str(path)), items[1]]), max_buffered)]
+    assert bytes(cramjam.zstd.decompress(b"".join(chunks))) == items[0] + items[1]
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress_pipe(iter([b"data"]), 0)
+
+
+def _zstd_block_count(frame):
+    # walk the block headers of a single frame; header layout per RFC 8878
+    descriptor = frame[4]
+    single_segment = descriptor & 0x20
+    pos = 5 + (0 if single_segment else 1) + [0, 1, 2, 4][descriptor & 0x03]
+    pos += [1 if single_segment else 0, 2, 4, 8][descriptor >> 6]
+    blocks = 0
+    while True:
+        header = int.from_bytes(frame[pos : pos + 3], "little")
+        block_type, size = (header >> 1) & 0x03, header >> 3
+        pos += 3 + (1 if block_type == 1 else size)
+        blocks += 1
+        if header & 0x01:
+            return blocks
+
+
+def test_zstd_compressor_target_block_size():
+    data = os.urandom(256 * 1024).hex().encode()  # compressible, but not trivially
+
+    default = cramjam.zstd.Compressor()
+    default.compress(data)
+    default = bytes(default.finish())
+
+    compressor = cramjam.zstd.Compressor(target_block_size=2048)
+    compressor.compress(data)
+    small_blocks = bytes(compressor.finish())
+
+    assert bytes(cramjam.zstd.decompress(small_blocks)) == data
+    assert _zstd_block_count(small_blocks) > _zstd_block_count(default)
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.Compressor(target_block_size=1 << 20)
+
+
+def test_zstd_compress_arrays(tmp_path):
+    arrays = [
+        np.random.random((10, 3)),
+        np.arange(24, dtype=np.float32).reshape(4, 6),
+        np.random.random((5, 7))[:, ::2],  # not C contiguous
+        np.empty((0, 2)),
+    ]
+    compressed = cramjam.zstd.compress_arrays(arrays, level=3)
+    restored = cramjam.zstd.decompress_arrays(compressed)
+
+    assert len(restored) == len(arrays)
+    for original, array in zip(arrays, restored):
+        assert array.dtype == original.dtype
+        assert array.shape == original.shape
+        np.testing.assert_array_equal(array, original)
+
+    path = tmp_path / "arrays.zst"
+    path.write_bytes(bytes(compressed))
+    restored = cramjam.zstd.decompress_arrays(cramjam.File(str(path)))
+    np.testing.assert_array_equal(restored[1], arrays[1])
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress_arrays([np.array([object()])])
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress_arrays(cramjam.zstd.compress(b"not arrays"))
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz"))
+def test_variants_decompress_bench(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    compressed = variant.compress(b"some bytes here" * 10_000)
+
+    nanos = variant.decompress_bench(compressed, iterations=5)
+    assert isinstance(nanos, float)
+    assert nanos > 0
+    # nothing was written back into the input
+    assert bytes(variant.decompress(compressed)) == b"some bytes here" * 10_000
+
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(compressed))
+    assert variant.decompress_bench(cramjam.File(str(path)), iterations=5) > 0
+
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress_bench(b"not compressed data" * 10)
+    with pytest.raises(ValueError):
+        variant.decompress_bench(compressed, iterations=0)
+
+
+@pytest.mark.skipif(sys.platform not in ("linux", "darwin"), reason="needs resource.getrusage")
+def test_zstd_decompress_bench_no_output_allocation():
+    import resource
+
+    compressor = cramjam.zstd.Compressor()
+    chunk = b"\x00" * (1 << 20)
+    for _ in range(256):
+        compressor.compress(chunk)
+    compressed = compressor.finish()  # 256 MiB once decompressed
+
+    unit = 1 if sys.platform == "darwin" else 1024  # ru_maxrss is bytes on macOS, KiB on linux
+    before = resource.getrusage(resource.RUSAGE_SELF).ru_maxrss * unit
+    assert cramjam.zstd.decompress_bench(compressed) > 0
+    after = resource.getrusage(resource.RUSAGE_SELF).ru_maxrss * unit
+    assert after - before < 64 * (1 << 20)
+
+
+def test_zstd_compress_scratch(tmp_path):
+    scratch = cramjam.Buffer()
+    records = [os.urandom(4096) + b"some bytes here" * i for i in range(50)]
+
+    cramjam.zstd.compress(max(records, key=len), scratch=scratch)
+    capacity = scratch.capacity()
+    for record in records:
+        result = cramjam.zstd.compress(record, level=3, scratch=scratch)
+        assert result is scratch
+        assert bytes(cramjam.zstd.decompress(result)) == record
+        # the allocation is reused rather than grown or replaced
+        assert scratch.capacity() == capacity
+
+    result, length = cramjam.zstd.compress(records[0], scratch=scratch, return_len=True)
+    assert result is scratch and length == len(scratch)
+
+    path = tmp_path / "record"
+    path.write_bytes(records[1])
+    (tmp_path / "dictionary").write_bytes(records[2])
+    result = cramjam.zstd.compress(
+        cramjam.File(str(path)), scratch=scratch, dictionary=cramjam.File(str(tmp_path / "dictionary"))
+    )
+    assert bytes(cramjam.zstd.decompress(result, dictionary=records[2])) == records[1]
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress(scratch, scratch=scratch)
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz"))
+def test_variants_decompress_prefix(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"".join(b"line %d of some data\n" % i for i in range(200_000))
+    compressed = bytes(variant.compress(data))
+
+    prefix = variant.decompress_prefix(compressed, 1000)
+    assert bytes(prefix) == bytes(variant.decompress(compressed))[:1000]
+    assert bytes(variant.decompress_prefix(compressed, len(data) + 10)) == data
+
+    # decoding stops at the prefix, so a truncated tail is never reached
+    truncated = compressed[: len(compressed) * 3 // 4]
+    assert bytes(variant.decompress_prefix(truncated, 1000)) == data[:1000]
+
+    path = tmp_path / "blob"
+    path.write_bytes(compressed)
+    assert bytes(variant.decompress_prefix(cramjam.File(str(path)), 1000)) == data[:1000]
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd"))
+def test_variants_compress_into_ring(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    ring = cramjam.Buffer(b"\x00" * 256)
+    messages = [b"message %d " % i * 8 for i in range(40)]
+
+    pos, written = 0, []
+    for message in messages:
+        start = pos
+        pos, n_bytes = variant.compress_into_ring(message, ring, pos)
+        assert pos == (start + n_bytes) % 256
+        written.append((start, n_bytes))
+
+    # read back the most recent outputs, which the ring still holds, wrapping around its end
+    contents = bytes(ring)
+    recent = []
+    total = 0
+    for start, n_bytes in reversed(written):
+        total += n_bytes
+        if total > 256:
+            break
+        recent.append(bytes((contents + contents)[start : start + n_bytes]))
+    assert any(start + n_bytes > 256 for start, n_bytes in written)  # some output wrapped around
+    for compressed, message in zip(recent, reversed(messages)):
+        assert bytes(variant.decompress(compressed)) == message
+
+    with pytest.raises(cramjam.CompressionError):
+        variant.compress_into_ring(os.urandom(1024), ring, pos)
+    assert bytes(ring) == contents
+    with pytest.raises(ValueError):
+        variant.compress_into_ring(b"data", ring, 256)
+
+    path = tmp_path / "message"
+    path.write_bytes(messages[0])
+    pos, n_bytes = variant.compress_into_ring(cramjam.File(str(path)), ring, 0)
+    assert bytes(variant.decompress(bytes(ring)[:n_bytes])) == messages[0]
+
+
+def test_zstd_decompressor_frames_across_feeds():
+    first, second = b"first frame " * 1_000, b"second frame " * 1_000
+    first_frame = bytes(cramjam.zstd.compress(first))
+    second_frame = bytes(cramjam.zstd.compress(second))
+    stream = first_frame + second_frame
+
+    # three chunks, the frame boundary landing inside the second
+    cut1, cut2 = len(first_frame) // 2, len(first_frame) + len(second_frame) // 2
+    decompressor = cramjam.zstd.Decompressor()
+    out = b""
+    for chunk in (stream[:cut1], stream[cut1:cut2], stream[cut2:]):
+        out += bytes(decompressor.decompress(chunk))
+    out += bytes(decompressor.finish())
+    assert out == first + second
+
+    # a stream ending part way through a frame is reported at finish
+    decompressor = cramjam.zstd.Decompressor()
+    decompressor.decompress(stream[:cut2])
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz"))
+def test_variants_decompress_max_input_len(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+    compressed = bytes(variant.compress(data))
+
+    assert bytes(variant.decompress(compressed, max_input_len=len(compressed))) == data
+    with pytest.raises(cramjam.DecompressionError, match="max_input_len"):
+        variant.decompress(compressed, max_input_len=len(compressed) - 1)
+    # rejected before decoding, so even invalid data gets the same error
+    with pytest.raises(cramjam.DecompressionError, match="max_input_len"):
+        variant.decompress(b"\x00" * 1_000, max_input_len=10)
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib"))
+def test_variants_decompress_max_output_len(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"\x00" * 1_000_000
+    compressed = bytes(variant.compress(data))
+
+    assert bytes(variant.decompress(compressed, max_output_len=len(data))) == data
+    with pytest.raises(cramjam.DecompressionError, match="decompressed output exceeded max_output_len"):
+        variant.decompress(compressed, max_output_len=len(data) - 1)
+    # even when told the output is larger
+    with pytest.raises(cramjam.DecompressionError, match="decompressed output exceeded max_output_len"):
+        variant.decompress(compressed, output_len=len(data), max_output_len=1_000)
+
+    # the streaming Decompressor checks the total output across pieces
+    decompressor = variant.Decompressor(max_output_len=len(data))
+    assert bytes(decompressor.decompress(compressed)) == data
+    decompressor = variant.Decompressor(max_output_len=len(data) - 1)
+    with pytest.raises(cramjam.DecompressionError, match="decompressed output exceeded max_output_len"):
+        decompressor.decompress(compressed)
+    # what's left of the budget carries over to the next piece
+    decompressor = variant.Decompressor(max_output_len=len(data) + 1)
+    assert bytes(decompressor.decompress(compressed)) == data
+    with pytest.raises(cramjam.DecompressionError, match="decompressed output exceeded max_output_len"):
+        decompressor.decompress(compressed)
+
+
+@pytest.mark.parametrize("codec", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib"))
+def test_compress_by_codec_name(codec, tmp_path):
+    data = b"some bytes here" * 1_000
+    compressed = cramjam.compress(codec, data)
+    assert bytes(getattr(cramjam, codec).decompress(compressed)) == data
+    assert bytes(cramjam.decompress(codec, compressed)) == data
+
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(compressed))
+    assert bytes(cramjam.decompress(codec, cramjam.File(str(path)))) == data
+    path.write_bytes(data)
+    assert bytes(cramjam.decompress(codec, cramjam.compress(codec, cramjam.File(str(path))))) == data
+    if codec != "snappy":
+        assert bytes(cramjam.decompress(codec, cramjam.compress(codec, data, level=1))) == data
+
+    with pytest.raises(ValueError, match="expected one of"):
+        cramjam.compress("not-a-codec", data)
+    with pytest.raises(ValueError, match="expected one of"):
+        cramjam.decompress("not-a-codec", compressed)
+
+
+@pytest.mark.parametrize("serializer", ("json", "pickle"))
+def test_compress_object(serializer, tmp_path):
+    obj = {"name": "some name", "values": [1, 2.5, None, True], "nested": {"more": ["a", {"b": []}]}}
+    compressed = cramjam.compress_object(obj, "gzip", serializer=serializer)
+    assert cramjam.decompress_object(compressed, "gzip", serializer=serializer) == obj
+
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(compressed))
+    assert cramjam.decompress_object(cramjam.File(str(path)), "gzip", serializer=serializer) == obj
+
+    if serializer == "json":
+        assert json.loads(gzip.decompress(bytes(compressed))) == obj
+    with pytest.raises(ValueError):
+        cramjam.compress_object(obj, "gzip", serializer="not-a-serializer")
+    with pytest.raises(ValueError):
+        cramjam.decompress_object(compressed, "gzip", serializer="not-a-serializer")
+
+
+@pytest.mark.parametrize("codec", ("gzip", "zstd"))
+def test_compress_tagged(codec, tmp_path):
+    data = b"some bytes here" * 1_000
+    compressed, tag = cramjam.compress_tagged(codec, data, level=3)
+    assert tag == {"codec": codec, "level": 3, "input_len": len(data), "output_len": len(compressed)}
+
+    assert bytes(getattr(cramjam, codec).decompress(compressed)) == data
+    assert bytes(cramjam.decompress_tagged(compressed, tag)) == data
+
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(compressed))
+    assert bytes(cramjam.decompress_tagged(cramjam.File(str(path)), tag)) == data
+    path.write_bytes(data)
+    assert cramjam.compress_tagged(codec, cramjam.File(str(path)), level=3)[1] == tag
+
+    # the tag survives a trip through JSON
+    assert bytes(cramjam.decompress_tagged(compressed, json.loads(json.dumps(tag)))) == data
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.decompress_tagged(bytes(compressed)[:-1], tag)
+    with pytest.raises(ValueError):
+        cramjam.compress_tagged("not-a-codec", data)
+
+
+@pytest.mark.parametrize("codec, tag", (("gzip", 0x05), ("zstd", 0x07), ("zlib", 0x09)))
+def test_compress_with_tag(codec, tag, tmp_path):
+    data = b"some bytes here" * 1_000
+    compressed = bytes(cramjam.compress_with_tag(codec, data, level=3))
+    assert compressed[0] == tag
+    assert bytes(getattr(cramjam, codec).decompress(compressed[1:])) == data
+    assert bytes(cramjam.decompress_tagged_bytes(compressed)) == data
+
+    path = tmp_path / "blob"
+    path.write_bytes(compressed)
+    assert bytes(cramjam.decompress_tagged_bytes(cramjam.File(str(path)))) == data
+    path.write_bytes(data)
+    assert bytes(cramjam.compress_with_tag(codec, cramjam.File(str(path)), level=3))[0] == tag
+
+    with pytest.raises(cramjam.DecompressionError, match="tag"):
+        cramjam.decompress_tagged_bytes(b"\xff" + compressed[1:])
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.decompress_tagged_bytes(b"")
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd"))
+def test_frame_compressed_size(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    compressed = bytes(variant.compress(b"some bytes here" * 1_000))
+
+    assert variant.frame_compressed_size(compressed) == len(compressed)
+    assert variant.frame_compressed_size(compressed + b"trailing junk" * 10) == len(compressed)
+    assert variant.frame_compressed_size(compressed + compressed) == len(compressed)
+
+    path = tmp_path / "compressed"
+    path.write_bytes(compressed + compressed)
+    file = cramjam.File(str(path))
+    assert variant.frame_compressed_size(file) == len(compressed)
+    assert file.tell() == 0
+
+    for truncated in (compressed[:-4], compressed[: len(compressed) // 2]):
+        with pytest.raises(cramjam.DecompressionError):
+            variant.frame_compressed_size(truncated)
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd"))
+def test_compress_empty_chunks(variant_str):
+    variant = getattr(cramjam, variant_str)
+
+    # no chunks at all still finalizes into a valid, empty stream
+    compressed = bytes(variant.Compressor().finish())
+    assert compressed
+    assert bytes(variant.decompress(compressed)) == b""
+
+    if variant_str == "zstd":
+        chunks = [bytes(chunk) for chunk in cramjam.zstd.compress_pipe([], 1024)]
+        assert len(chunks) == 1
+        assert bytes(cramjam.zstd.decompress(b"".join(chunks))) == b""
+    else:
+        assert gzip.decompress(compressed) == b""
+
+
+def test_gzip_compress_minimal():
+    data = b"some bytes here" * 1_000
+    compressed = bytes(cramjam.gzip.compress(data, minimal=True))
+
+    assert compressed[:3] == b"\x1f\x8b\x08"
+    assert compressed[3] == 0  # no FTEXT, FHCRC, FEXTRA, FNAME or FCOMMENT
+    assert compressed[4:8] == b"\x00\x00\x00\x00"  # mtime
+    assert compressed[9] == 255  # OS unknown
+    # nothing between the 10 byte header and the deflate stream
+    assert zlib.decompress(compressed[10:-8], wbits=-15) == data
+    assert gzip.decompress(compressed) == data
+    assert bytes(cramjam.gzip.decompress(compressed)) == data
+
+    named = bytes(cramjam.gzip.compress(data, filename="data.txt"))
+    assert len(compressed) < len(named)
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress(data, filename="data.txt", minimal=True)
+
+
+def test_zstd_compress_tee(tmp_path):
+    class ShortWriter(io.BytesIO):
+        # accepts at most 100 bytes per call, and sometimes all of it without saying how much
+        calls = 0
+
+        def write(self, b):
+            self.calls += 1
+            if self.calls % 3 == 0:
+                super().write(bytes(b))
+                return None
+            return super().write(bytes(b)[:100])
+
+    class FullWriter:
+        def write(self, b):
+            return 0
+
+    data = os.urandom(64 * 1024) * 4
+    first, second, short = io.BytesIO(), io.BytesIO(), ShortWriter()
+    total = cramjam.zstd.compress_tee(data, [first, second, short], level=3)
+
+    assert total == len(first.getvalue())
+    assert first.getvalue() == second.getvalue() == short.getvalue()
+    assert bytes(cramjam.zstd.decompress(first.getvalue())) == data
+
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    from_file = io.BytesIO()
+    cramjam.zstd.compress_tee(cramjam.File(str(path)), [from_file], level=3)
+    assert from_file.getvalue() == first.getvalue()
+
+    with pytest.raises(cramjam.CompressionError):
+        cramjam.zstd.compress_tee(data, [io.BytesIO(), FullWriter()])
+
+
+def test_gzip_decompressor_crc_checked_at_trailer():
+    data = b"".join(b"line %d of some data\n" % i for i in range(50_000))
+    compressed = bytearray(gzip.compress(data))
+    compressed[-8] ^= 0xFF  # corrupt the CRC32 trailer
+    chunks = [bytes(compressed[i : i + 4096]) for i in range(0, len(compressed), 4096)]
+
+    decompressor = cramjam.gzip.Decompressor()
+    # output is produced without buffering to the end
+    assert sum(len(decompressor.decompress(chunk)) for chunk in chunks[:-1]) > 0
+    with pytest.raises(cramjam.DecompressionError, match="CRC32"):
+        decompressor.decompress(chunks[-1])
+
+    # intact members split across arbitrary feeds
+    stream = gzip.compress(data) + gzip.compress(data)
+    decompressor = cramjam.gzip.Decompressor()
+    out = b"".join(bytes(decompressor.decompress(stream[i : i + 1000])) for i in range(0, len(stream), 1000))
+    assert out + bytes(decompressor.finish()) == data * 2
+
+    decompressor = cramjam.gzip.Decompressor()
+    decompressor.decompress(stream[:-3])
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+
+def test_zstd_compressor_raw_frame_without_dictionary(tmp_path):
+    dictionary = os.urandom(4096)
+    data = dictionary[100:3000]  # only compressible by referencing the dictionary
+    (tmp_path / "dictionary").write_bytes(dictionary)
+    (tmp_path / "handshake").write_bytes(b"hello, dictionary to follow")
+
+    compressor = cramjam.zstd.Compressor(level=3, dictionary=cramjam.File(str(tmp_path / "dictionary")))
+    handshake = compressor.compress_raw_frame(cramjam.File(str(tmp_path / "handshake")))
+    assert bytes(cramjam.zstd.decompress(handshake)) == b"hello, dictionary to follow"
+
+    compressor.compress(data)
+    stream = bytes(compressor.finish())
+    assert len(stream) < len(data) // 2
+    assert bytes(cramjam.zstd.decompress(stream, dictionary=dictionary)) == data
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(stream)
+
+
+def test_zstd_compress_checksum():
+    data = b"some bytes here" * 1_000
+    first = bytes(cramjam.zstd.compress(data, level=3, checksum=False))
+    second = bytes(cramjam.zstd.compress(data, level=3, checksum=False))
+    assert first == second
+    assert first == bytes(cramjam.zstd.compress(data, level=3))  # checksum=False is the default
+    assert not first[4] & 0x04  # Content_Checksum_flag of the frame header descriptor
+
+    with_checksum = bytes(cramjam.zstd.compress(data, level=3, checksum=True))
+    assert with_checksum[4] & 0x04
+    assert len(with_checksum) == len(first) + 4
+    assert bytes(cramjam.zstd.decompress(with_checksum)) == data
+
+    compressor = cramjam.zstd.Compressor(level=3, checksum=True)
+    compressor.compress(data)
+    streamed = bytes(compressor.finish())
+    assert streamed[4] & 0x04
+    assert bytes(cramjam.zstd.decompress(streamed)) == data
+
+    # a corrupted checksum fails the content's verification
+    for frame in (with_checksum, streamed):
+        corrupted = frame[:-1] + bytes([frame[-1] ^ 0xFF])
+        with pytest.raises(cramjam.DecompressionError, match="checksum"):
+            cramjam.zstd.decompress(corrupted)
+
+
+def test_buffer_pool():
+    records = [os.urandom(16 * 1024) for _ in range(20)]
+
+    capacities = set()
+    for record in records:
+        buffer = cramjam.acquire_buffer(64 * 1024)
+        assert len(buffer) == 0 and buffer.capacity() >= 64 * 1024
+        compressed = cramjam.zstd.compress(record, scratch=buffer)
+        assert compressed is buffer
+        assert bytes(cramjam.zstd.decompress(compressed)) == record
+        capacities.add(buffer.capacity())
+        cramjam.release_buffer(buffer)
+        assert len(buffer) == 0  # its allocation went back to the pool
+    # the same allocation cycled through every iteration
+    assert len(capacities) == 1
+
+    # allocations beyond the pool's cap are freed rather than retained
+    big = [cramjam.acquire_buffer(40 << 20) for _ in range(2)]
+    for buffer in big:
+        cramjam.release_buffer(buffer)
+    assert cramjam.acquire_buffer(40 << 20).capacity() >= 40 << 20
+
+
+@pytest.mark.parametrize("seek_table", (False, True))
+def test_zstd_compress_parallel(seek_table, tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(100_000))
+    frame_size = 256 * 1024
+
+    compressed = bytes(
+        cramjam.zstd.compress_parallel(data, frame_size=frame_size, level=3, n_workers=4, seek_table=seek_table)
+    )
+    single = bytes(cramjam.zstd.compress(data, level=3))
+    assert bytes(cramjam.zstd.decompress(compressed)) == bytes(cramjam.zstd.decompress(single)) == data
+
+    frames = [bytes(frame) for frame in cramjam.zstd.decompress_frames(compressed)]
+    assert len(frames) == -(-len(data) // frame_size)
+    assert b"".join(frames) == data
+
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    file = cramjam.File(str(path))
+    assert bytes(cramjam.zstd.compress_parallel(file, frame_size=frame_size, level=3, seek_table=seek_table)) == compressed
+
+    if seek_table:
+        reader = cramjam.zstd.SeekableReader(compressed)
+        assert reader.n_frames() == len(frames)
+        assert bytes(reader.read_range(frame_size - 10, 20)) == data[frame_size - 10 : frame_size + 10]
+
+    assert bytes(cramjam.zstd.decompress(cramjam.zstd.compress_parallel(b""))) == b""
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "bzip2", "lz4", "gzip", "zstd", "xz"))
+def test_variants_decompress_strict(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+    assert bytes(variant.decompress_strict(variant.compress(data))) == data
+
+    other = cramjam.gzip if variant_str == "zstd" else cramjam.zstd
+    with pytest.raises(cramjam.DecompressionError, match=f"not a {variant_str} stream"):
+        variant.decompress_strict(other.compress(data))
+
+    # a file's magic number is peeked at, leaving all of it to decompress
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(variant.compress(data)))
+    assert bytes(variant.decompress_strict(cramjam.File(str(path)))) == data
+    path.write_bytes(bytes(other.compress(data)))
+    with pytest.raises(cramjam.DecompressionError, match=f"not a {variant_str} stream"):
+        variant.decompress_strict(cramjam.File(str(path)))
+
+
+def test_gzip_decompress_strict_zstd_blob():
+    blob = cramjam.zstd.compress(b"some bytes here")
+    with pytest.raises(cramjam.DecompressionError, match="^not a gzip stream$"):
+        cramjam.gzip.decompress_strict(blob)
+
+
+def test_zstd_compress_to_size(tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(10_000))
+    target = len(cramjam.zstd.compress(data, level=19)) + 100
+
+    compressed = cramjam.zstd.compress_to_size(data, target, min_level=1, max_level=19)
+    assert compressed is not None
+    assert len(compressed) <= target
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    assert bytes(cramjam.zstd.compress_to_size(cramjam.File(str(path)), target)) == bytes(compressed)
+
+    # random bytes don't compress, so no level gets them under half their size
+    noise = os.urandom(64 * 1024)
+    assert cramjam.zstd.compress_to_size(noise, len(noise) // 2) is None
+    assert cramjam.zstd.compress_to_size(noise, len(noise) // 2, long_distance=True) is None
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress_to_size(data, target, min_level=10, max_level=3)
+
+
+def test_armor_roundtrip(tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(1_000))
+    compressed = bytes(cramjam.gzip.compress(data))
+
+    text = cramjam.armor(compressed, line_width=64)
+    path = tmp_path / "compressed.gz"
+    path.write_bytes(compressed)
+    assert cramjam.armor(cramjam.File(str(path)), line_width=64) == text
+    lines = text.splitlines()
+    assert lines[0] == "-----BEGIN CRAMJAM DATA-----"
+    assert lines[-1] == "-----END CRAMJAM DATA-----"
+    assert len(lines) > 3
+    assert all(len(line) <= 64 for line in lines[1:-1])
+
+    assert bytes(cramjam.dearmor(text)) == compressed
+    assert bytes(cramjam.gzip.decompress(cramjam.dearmor(text))) == data
+
+    # pasted into a ticket: re-indented, CRLF line endings and surrounding prose
+    pasted = "see attached:\r\n" + "\r\n".join("    " + line for line in lines) + "\r\nthanks"
+    assert bytes(cramjam.dearmor(pasted)) == compressed
+
+    assert bytes(cramjam.dearmor(cramjam.armor(b""))) == b""
+    with pytest.raises(ValueError):
+        cramjam.dearmor(text.replace("-----END CRAMJAM DATA-----", ""))
+
+
+def test_gzip_decompress_streaming():
+    chunk = b"".join(b"line %d of some data\n" % i for i in range(10_000))
+    n_chunks = 64
+    src = io.BytesIO()
+    with gzip.GzipFile(fileobj=src, mode="wb") as f:
+        for _ in range(n_chunks):
+            f.write(chunk)
+    src.write(gzip.compress(b"and a second member"))
+    src.seek(0)
+
+    dst = io.BytesIO()
+    n_bytes = cramjam.gzip.decompress_streaming(src, dst)
+    expected = chunk * n_chunks + b"and a second member"
+    assert n_bytes == len(expected)
+    assert dst.getvalue() == expected
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.decompress_streaming(io.BytesIO(b"not gzip data"), io.BytesIO())
+
+    # a sink returning None took everything, one accepting nothing fails rather than being retried
+    class ListSink:
+        def __init__(self):
+            self.chunks = []
+
+        def write(self, b):
+            self.chunks.append(bytes(b))
+
+    class FullSink:
+        def write(self, b):
+            return 0
+
+    sink = ListSink()
+    src.seek(0)
+    assert cramjam.gzip.decompress_streaming(src, sink) == len(expected)
+    assert b"".join(sink.chunks) == expected
+    src.seek(0)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.decompress_streaming(src, FullSink())
+
+
+def test_gzip_compressor_chunked_ndjson():
+    lines = [json.dumps({"id": i, "msg": "some log message"}).encode() + b"\n" for i in range(20_000)]
+    compressor = cramjam.gzip.Compressor()
+    out = b""
+    for i in range(0, len(lines), 1000):
+        compressor.compress(b"".join(lines[i : i + 1000]))
+        out += bytes(compressor.flush())
+    out += bytes(compressor.finish())
+
+    # the trailer's CRC32 and length are checked by the stdlib decoder
+    assert gzip.decompress(out) == b"".join(lines)
+
+    decompressor = cramjam.gzip.Decompressor()
+    decompressed = b"".join(bytes(decompressor.decompress(out[i : i + 4096])) for i in range(0, len(out), 4096))
+    assert decompressed + bytes(decompressor.finish()) == b"".join(lines)
+
+
+def test_build_info():
+    info = cramjam.build_info()
+    assert set(info) == {
+        "version",
+        "flate2_backend",
+        "zstd_version",
+        "zstd_multithreading",
+        "target_arch",
+        "cpu_features",
+    }
+    assert info["version"] == cramjam.__version__
+    assert isinstance(info["zstd_multithreading"], bool)
+    assert all(isinstance(feature, str) for feature in info["cpu_features"])
+
+
+def test_zstd_decompress_wrong_dictionary():
+    dictionary = b"".join(b'{"id": %d, "kind": "event", "source": "sensor"}' % i for i in range(100))
+    other = os.urandom(len(dictionary))
+    doc = b'{"id": 42, "kind": "event", "source": "sensor", "value": 1.5}'
+
+    compressed = cramjam.zstd.compress(doc, level=3, dictionary=dictionary, checksum=True)
+    assert bytes(cramjam.zstd.decompress(compressed, dictionary=dictionary)) == doc
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(compressed, dictionary=other)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(compressed)
+
+    compressor = cramjam.zstd.Compressor(level=3, dictionary=dictionary)
+    compressor.compress(doc)
+    assert bytes(cramjam.zstd.decompress(compressor.finish(), dictionary=dictionary)) == doc
+
+
+@pytest.mark.parametrize("codec", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib"))
+def test_compress_envelope_roundtrip(codec, tmp_path):
+    data = b"some bytes here" * 100
+    envelope = bytes(cramjam.compress_envelope(codec, data, level=3))
+    assert envelope[:5] == b"CJEV\x01"
+    assert bytes(cramjam.decompress_envelope(envelope)) == data
+
+    path = tmp_path / "blob"
+    path.write_bytes(envelope)
+    assert bytes(cramjam.decompress_envelope(cramjam.File(str(path)))) == data
+    path.write_bytes(data)
+    assert bytes(cramjam.decompress_envelope(cramjam.compress_envelope(codec, cramjam.File(str(path))))) == data
+
+    # readers of a version can't make sense of a newer one
+    with pytest.raises(cramjam.DecompressionError, match="version"):
+        cramjam.decompress_envelope(envelope[:4] + b"\x02" + envelope[5:])
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.decompress_envelope(getattr(cramjam, codec).compress(data))
+
+
+def test_transcoder_gzip_to_zstd_in_chunks(tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(50_000))
+    # two members, so a member boundary falls within a chunk
+    compressed = gzip.compress(data[:300_000]) + gzip.compress(data[300_000:])
+
+    transcoder = cramjam.Transcoder("gzip", "zstd", level=3)
+    converted = b"".join(bytes(transcoder.write(compressed[i : i + 1000])) for i in range(0, len(compressed), 1000))
+    converted += bytes(transcoder.finish())
+    assert bytes(cramjam.zstd.decompress(converted)) == data
+
+    path = tmp_path / "compressed.gz"
+    path.write_bytes(compressed)
+    transcoder = cramjam.Transcoder("gzip", "zstd", level=3)
+    converted = bytes(transcoder.write(cramjam.File(str(path)))) + bytes(transcoder.finish())
+    assert bytes(cramjam.zstd.decompress(converted)) == data
+
+    with pytest.raises(cramjam.CompressionError):
+        transcoder.write(compressed)
+
+
+@pytest.mark.parametrize("to_codec", ("snappy", "brotli", "bzip2", "lz4", "gzip", "deflate", "zstd", "xz", "zlib"))
+def test_transcoder_to_codec(to_codec):
+    data = b"some bytes here" * 1000
+    transcoder = cramjam.Transcoder("xz", to_codec)
+    converted = bytes(transcoder.write(cramjam.xz.compress(data))) + bytes(transcoder.finish())
+    assert bytes(getattr(cramjam, to_codec).decompress(converted)) == data
+    if to_codec == "zlib":
+        assert zlib.decompress(converted) == data
+        transcoder = cramjam.Transcoder("zlib", "xz")
+        converted = bytes(transcoder.write(zlib.compress(data))) + bytes(transcoder.finish())
+        assert bytes(cramjam.xz.decompress(converted)) == data
+
+
+def test_transcoder_invalid():
+    with pytest.raises(ValueError):
+        cramjam.Transcoder("lz4", "zstd")
+    with pytest.raises(ValueError):
+        cramjam.Transcoder("gzip", "unknown")
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.Transcoder("gzip", "zstd").write(b"not gzip data")
+
+
+def test_zstd_decompressed_size(tmp_path):
+    data = b"some bytes here" * 10
+    # single segment frame recording its 1 byte content size, holding one raw block
+    frame = b"\x28\xb5\x2f\xfd\x20" + bytes([len(data)]) + ((len(data) << 3) | 1).to_bytes(3, "little") + data
+    assert cramjam.zstd.decompressed_size(frame) == len(data)
+    assert cramjam.zstd.decompressed_size(frame + frame) == 2 * len(data)
+    assert bytes(cramjam.zstd.decompress(frame + frame)) == data * 2
+    assert bytes(cramjam.zstd.decompress(frame, output_len=cramjam.zstd.decompressed_size(frame))) == data
+
+    path = tmp_path / "frames.zst"
+    path.write_bytes(frame + frame)
+    file = cramjam.File(str(path))
+    assert cramjam.zstd.decompressed_size(file) == 2 * len(data)
+    assert bytes(cramjam.zstd.decompress(file)) == data * 2
+
+    # streamed frames don't record their size
+    assert cramjam.zstd.decompressed_size(cramjam.zstd.compress(data)) is None
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompressed_size(b"not zstd data")
+
+
+def test_gzip_scan_lines(tmp_path):
+    lines = [b"%d ERROR disk full" % i if i % 7 == 0 else b"%d INFO all good" % i for i in range(100_000)]
+    lines.append(b"x" * 200_000)  # spans several decode windows
+    compressed = cramjam.gzip.compress(b"\n".join(lines) + b"\n")
+
+    matches = []
+    n_lines = cramjam.gzip.scan_lines(compressed, lambda line: matches.append(line) if b"ERROR" in line else None)
+    assert n_lines == len(lines)
+    assert matches == [line for line in lines if b"ERROR" in line]
+
+    path = tmp_path / "log.gz"
+    path.write_bytes(bytes(compressed))
+    assert cramjam.gzip.scan_lines(cramjam.File(str(path)), lambda line: None) == len(lines)
+
+    seen = []
+    n_lines = cramjam.gzip.scan_lines(cramjam.gzip.compress(b"a\n" + b"y" * 100_000), seen.append, max_line_len=10)
+    assert n_lines == 2
+    assert seen == [b"a", b"y" * 10]
+
+    def stop(line):
+        raise KeyError(line)
+
+    with pytest.raises(KeyError):
+        cramjam.gzip.scan_lines(compressed, stop)
+
+
+def test_deflate_preset_dictionary(tmp_path):
+    dictionary = b'{"id": , "kind": "event", "source": "sensor", "value": }' * 4
+    doc = b'{"id": 42, "kind": "event", "source": "sensor", "value": 1.5}'
+
+    if cramjam.build_info()["flate2_backend"] == "miniz_oxide":
+        # built without the deflate-dictionary feature
+        with pytest.raises(cramjam.CompressionError, match="deflate-dictionary"):
+            cramjam.deflate.compress(doc, dictionary=dictionary)
+        with pytest.raises(cramjam.DecompressionError, match="deflate-dictionary"):
+            cramjam.deflate.decompress(cramjam.deflate.compress(doc), dictionary=dictionary)
+        with pytest.raises(cramjam.CompressionError, match="deflate-dictionary"):
+            cramjam.deflate.Compressor(dictionary=dictionary)
+        return
+
+    compressed = bytes(cramjam.deflate.compress(doc, level=6, dictionary=dictionary))
+    assert len(compressed) < len(bytes(cramjam.deflate.compress(doc, level=6)))
+    assert bytes(cramjam.deflate.decompress(compressed, dictionary=dictionary)) == doc
+
+    # the stream refers back into the dictionary, so can't be decoded without it
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.deflate.decompress(compressed)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.deflate.decompress(compressed[:-2], dictionary=dictionary)
+
+    compressor = cramjam.deflate.Compressor(level=6, dictionary=dictionary)
+    compressor.compress(doc)
+    assert bytes(compressor.finish()) == compressed
+
+    path = tmp_path / "dictionary"
+    path.write_bytes(dictionary)
+    assert bytes(cramjam.deflate.compress(doc, level=6, dictionary=cramjam.File(str(path)))) == compressed
+    assert bytes(cramjam.deflate.decompress(compressed, dictionary=cramjam.File(str(path)))) == doc
+    compressor = cramjam.deflate.Compressor(level=6, dictionary=cramjam.File(str(path)))
+    compressor.compress(doc)
+    assert bytes(compressor.finish()) == compressed
+
+    # decoded a chunk at a time, across many chunks
+    large = doc * 10_000
+    compressed = cramjam.deflate.compress(large, dictionary=dictionary)
+    assert bytes(cramjam.deflate.decompress(compressed, dictionary=dictionary)) == large
+
+
+@given(data=st.binary())
+def test_zlib_stdlib_interop(data):
+    assert zlib.decompress(bytes(cramjam.zlib.compress(data))) == data
+    assert bytes(cramjam.zlib.decompress(zlib.compress(data))) == data
+    assert bytes(cramjam.zlib.decompress(zlib.compress(data, 9))) == data
+
+    output = cramjam.Buffer()
+    cramjam.zlib.compress_into(data, output)
+    output.seek(0)
+    assert zlib.decompress(output.read()) == data
+
+
+def test_zlib_streaming():
+    data = b"".join(b"line %d of some data\n" % i for i in range(50_000))
+
+    compressor = cramjam.zlib.Compressor(level=6)
+    out = b""
+    for i in range(0, len(data), 100_000):
+        compressor.compress(data[i : i + 100_000])
+        out += bytes(compressor.flush())
+    out += bytes(compressor.finish())
+    assert zlib.decompress(out) == data
+
+    stream = zlib.compress(data) + zlib.compress(b"second stream")
+    decompressor = cramjam.zlib.Decompressor()
+    out = b"".join(bytes(decompressor.decompress(stream[i : i + 1000])) for i in range(0, len(stream), 1000))
+    assert out + bytes(decompressor.finish()) == data + b"second stream"
+
+    corrupt = bytearray(zlib.compress(data))
+    corrupt[-1] ^= 0xFF  # Adler-32 trailer
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zlib.decompress(bytes(corrupt))
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zlib.decompress(zlib.compress(data)[:-10])
+
+    # raw deflate is the same compression without the zlib wrapper
+    assert bytes(cramjam.deflate.decompress(zlib.compress(data)[2:-4])) == data
+
+
+def test_zstd_dict_tagged(tmp_path):
+    dictionary = b"".join(b'{"id": %d, "kind": "event", "source": "sensor"}' % i for i in range(100))
+    other = b"".join(b'{"user": %d, "action": "login"}' % i for i in range(100))
+    doc = b'{"id": 42, "kind": "event", "source": "sensor", "value": 1.5}'
+
+    compressed = bytes(cramjam.zstd.compress_with_dict_tagged(doc, dictionary, level=3))
+    dict_id = cramjam.zstd.register_dictionary(dictionary)
+    assert int.from_bytes(compressed[:4], "little") == dict_id
+    assert bytes(cramjam.zstd.decompress_dict_tagged(compressed)) == doc
+    assert bytes(cramjam.zstd.decompress_dict_tagged(compressed, dictionary=dictionary)) == doc
+
+    (tmp_path / "dictionary").write_bytes(dictionary)
+    (tmp_path / "compressed").write_bytes(compressed)
+    assert cramjam.zstd.register_dictionary(cramjam.File(str(tmp_path / "dictionary"))) == dict_id
+    tagged = cramjam.zstd.compress_with_dict_tagged(doc, cramjam.File(str(tmp_path / "dictionary")), level=3)
+    assert bytes(tagged) == compressed
+    file_dictionary = cramjam.File(str(tmp_path / "dictionary"))
+    assert bytes(cramjam.zstd.decompress_dict_tagged(cramjam.File(str(tmp_path / "compressed")), file_dictionary)) == doc
+
+    # the wrong dictionary is caught from the id, rather than decoding to garbage
+    with pytest.raises(cramjam.DecompressionError, match="dictionary id"):
+        cramjam.zstd.decompress_dict_tagged(compressed, dictionary=other)
+    unregistered = bytes(cramjam.zstd.compress_with_dict_tagged(doc, other + b"unregistered"))
+    with pytest.raises(cramjam.DecompressionError, match="No dictionary registered"):
+        cramjam.zstd.decompress_dict_tagged(unregistered)
+
+
+def test_zstd_compress_progress(tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(300_000))  # several progress chunks
+    calls = []
+    compressed = cramjam.zstd.compress(data, level=3, progress=lambda *sizes: calls.append(sizes))
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+    assert len(calls) > 2
+    assert calls[-1] == (len(data), len(compressed))
+    assert all(a[0] <= b[0] and a[1] <= b[1] for a, b in zip(calls, calls[1:]))
+
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    calls.clear()
+    compressed = cramjam.zstd.compress(cramjam.File(str(path)), level=3, progress=lambda *sizes: calls.append(sizes))
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+    assert calls[-1] == (len(data), len(compressed))
+
+    dictionary = data[:100_000]
+    (tmp_path / "dictionary").write_bytes(dictionary)
+    compressed = cramjam.zstd.compress(
+        data, level=3, dictionary=cramjam.File(str(tmp_path / "dictionary")), progress=lambda *sizes: None
+    )
+    assert bytes(cramjam.zstd.decompress(compressed, dictionary=dictionary)) == data
+
+    def abort(consumed, produced):
+        raise InterruptedError
+
+    with pytest.raises(InterruptedError):
+        cramjam.zstd.compress(data, progress=abort)
+    with pytest.raises(ValueError, match="scratch can't be combined with progress"):
+        cramjam.zstd.compress(data, progress=print, scratch=cramjam.Buffer())
+
+    # output_len preallocates the output, as without progress
+    compressed = cramjam.zstd.compress(data, level=3, output_len=1 << 20, progress=lambda *sizes: None)
+    assert compressed.capacity() >= 1 << 20
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+
+def test_zstd_compress_workers():
+    data = b"".join(b"row %d, some column values\n" % i for i in range(200_000))
+    for workers in (None, 0, 1, 4):
+        compressed = cramjam.zstd.compress(data, level=3, workers=workers)
+        assert bytes(cramjam.zstd.decompress(compressed)) == data
+    assert bytes(cramjam.zstd.compress(data, workers=1)) == bytes(cramjam.zstd.compress(data))
+
+    compressor = cramjam.zstd.Compressor(level=3, workers=4)
+    compressor.compress(data[: len(data) // 2])
+    compressor.compress(data[len(data) // 2 :])
+    assert bytes(cramjam.zstd.decompress(compressor.finish())) == data
+
+
+def test_zstd_compress_deterministic():
+    data = b"".join(b"row %d, some column values\n" % i for i in range(200_000))
+    runs = {bytes(cramjam.zstd.compress(data, level=3, workers=4, deterministic=True)) for _ in range(5)}
+    assert len(runs) == 1
+    compressed = runs.pop()
+    assert compressed == bytes(cramjam.zstd.compress(data, level=3))
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+
+
+def test_zstd_compress_prefix(tmp_path):
+    prefix = b'{"device": "sensor-0001", "temperature": 21.5, "humidity": 40, "status": "ok"}'
+    message = b'{"device": "sensor-0002", "temperature": 22.5, "humidity": 41, "status": "ok"}'
+
+    compressed = bytes(cramjam.zstd.compress(message, prefix=prefix))
+    assert len(compressed) < len(cramjam.zstd.compress(message)) // 2
+    assert bytes(cramjam.zstd.decompress(compressed, prefix=prefix)) == message
+    path = tmp_path / "prefix.json"
+    path.write_bytes(prefix)
+    assert bytes(cramjam.zstd.compress(message, prefix=cramjam.File(str(path)))) == compressed
+    assert bytes(cramjam.zstd.decompress(compressed, prefix=cramjam.File(str(path)))) == message
+    # the prefix is needed to decode
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress(compressed)
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress(message, prefix=prefix, dictionary=prefix)
+    with pytest.raises(ValueError):
+        cramjam.zstd.decompress(compressed, prefix=prefix, dictionary=prefix)
+
+
+@pytest.mark.parametrize("message", (b"", b"yes", b"no", b"a somewhat longer message " * 4))
+def test_zstd_compress_min_output_len(message):
+    compressed = cramjam.zstd.compress(message, min_output_len=256)
+    assert len(compressed) >= 256
+    assert bytes(cramjam.zstd.decompress(compressed)) == message
+
+    # a floor just past the frame still gets a whole skippable frame header
+    frame_len = len(cramjam.zstd.compress(message))
+    padded = cramjam.zstd.compress(message, min_output_len=frame_len + 1)
+    assert len(padded) == frame_len + 8
+    assert bytes(cramjam.zstd.decompress(padded)) == message
+
+    scratch = cramjam.Buffer()
+    assert bytes(cramjam.zstd.compress(message, min_output_len=256, scratch=scratch)) == bytes(compressed)
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress(message, min_output_len=256, magicless=True)
+
+
+def test_zstd_decompress_return_padding_info():
+    message = b"yes"
+    frame = bytes(cramjam.zstd.compress(message))
+    padded = bytes(cramjam.zstd.compress(message, min_output_len=256))
+
+    output, info = cramjam.zstd.decompress(padded, return_padding_info=True)
+    assert bytes(output) == message
+    assert info == {"skippable_frames": 1, "skippable_bytes": len(padded) - len(frame)}
+
+    # padding between concatenated frames is counted too
+    output, info = cramjam.zstd.decompress(padded + padded, return_padding_info=True)
+    assert bytes(output) == message * 2
+    assert info == {"skippable_frames": 2, "skippable_bytes": 2 * (len(padded) - len(frame))}
+
+    output, info = cramjam.zstd.decompress(frame, return_padding_info=True)
+    assert bytes(output) == message
+    assert info == {"skippable_frames": 0, "skippable_bytes": 0}
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.decompress(frame, magicless=True, return_padding_info=True)
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd"))
+def test_decompress_require_exact(variant_str):
+    variant = getattr(cramjam, variant_str)
+    frame = bytes(variant.compress(b"exactly one frame"))
+    assert bytes(variant.decompress(frame, require_exact=True)) == b"exactly one frame"
+
+    for trailing in (b"\x00", b"trailing bytes", frame):
+        with pytest.raises(cramjam.DecompressionError, match=f"{len(trailing)} bytes"):
+            variant.decompress(frame + trailing, require_exact=True)
+    # without it, another frame is decompressed too
+    assert bytes(variant.decompress(frame + frame)) == b"exactly one frame" * 2
+
+
+def test_buffer_protocol_views():
+    buffer = cramjam.Buffer(b"some bytes here")
+    view = memoryview(buffer)
+    assert view.readonly
+    assert (view.format, view.itemsize, view.ndim, view.c_contiguous) == ("B", 1, 1, True)
+    assert view.tobytes() == b"some bytes here"
+
+    # the buffer's memory can't move or be freed from under a view
+    for modify in (lambda: buffer.write(b"x" * 1024), lambda: buffer.set_len(1024), buffer.truncate):
+        with pytest.raises(BufferError):
+            modify()
+    with pytest.raises(BufferError):
+        cramjam.release_buffer(buffer)
+    with pytest.raises(BufferError):
+        cramjam.gzip.decompress_into(cramjam.gzip.compress(b"data"), buffer)
+    view.release()
+    buffer.seek(0, 2)
+    buffer.write(b", and more")
+    assert bytes(buffer) == b"some bytes here, and more"
+
+    array = np.frombuffer(buffer, dtype=np.uint8)
+    assert not array.flags.writeable
+    assert array.tobytes() == b"some bytes here, and more"
+    with pytest.raises(BufferError):
+        buffer.set_len(0)
+    del array
+    buffer.set_len(0)
+
+    # a view keeps its buffer alive
+    view = memoryview(cramjam.Buffer(b"temporary"))
+    assert view.tobytes() == b"temporary"
+
+
+def test_gzip_member_index(tmp_path):
+    members = [b"first member " * 10, b"", b"".join(b"line %d\n" % i for i in range(10_000))]
+    compressed = [gzip.compress(member) for member in members]
+    data = b"".join(compressed)
+
+    index = cramjam.gzip.member_index(data)
+    offsets = [sum(len(c) for c in compressed[:i]) for i in range(len(compressed))]
+    assert index == [(offset, len(c), len(m)) for offset, c, m in zip(offsets, compressed, members)]
+    for (offset, length, _), member in zip(index, members):
+        assert bytes(cramjam.gzip.decompress(data[offset : offset + length])) == member
+
+    path = tmp_path / "members.gz"
+    path.write_bytes(data)
+    file = cramjam.File(str(path))
+    assert cramjam.gzip.member_index(file) == index
+    assert file.tell() == 0
+
+    assert cramjam.gzip.member_index(b"") == []
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.member_index(data[:-5])
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.member_index(data + b"not gzip")
+
+
+def test_copy_buffer_size():
+    data = b"".join(b"line %d of some data\n" % i for i in range(50_000))
+    codecs = ("gzip", "deflate", "zlib", "zstd")
+    default_size = cramjam.get_copy_buffer_size()
+    expected = {codec: bytes(getattr(cramjam, codec).compress(data)) for codec in codecs}
+
+    cramjam.set_copy_buffer_size(1 << 20)
+    try:
+        for codec in codecs:
+            variant = getattr(cramjam, codec)
+            compressed = bytes(variant.compress(data))
+            assert compressed == expected[codec]
+            assert bytes(variant.decompress(compressed)) == data
+            # the setting persists across calls
+            assert cramjam.get_copy_buffer_size() == 1 << 20
+        with pytest.raises(ValueError):
+            cramjam.set_copy_buffer_size(0)
+        with pytest.raises(ValueError):
+            cramjam.set_copy_buffer_size((16 << 20) + 1)
+        assert cramjam.get_copy_buffer_size() == 1 << 20
+    finally:
+        cramjam.set_copy_buffer_size(default_size)
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd", "snappy", "lz4"))
+def test_variants_decompress_into_arrow_buffer(variant_str):
+    pa = pytest.importorskip("pyarrow")
+    variant = getattr(cramjam, variant_str)
+    data = b"".join(b"line %d of some data\n" % i for i in range(1_000))
+    compressed = variant.compress(data)
+
+    output = pa.allocate_buffer(len(data))
+    assert output.is_mutable
+    assert variant.decompress_into(compressed, output) == len(data)
+    assert output.to_pybytes() == data
+
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress_into(compressed, pa.allocate_buffer(len(data) - 1))
+
+    immutable = pa.py_buffer(bytes(len(data)))
+    assert not immutable.is_mutable
+    with pytest.raises(BufferError):
+        variant.decompress_into(compressed, immutable)
+
+
+def test_decompress_into_readonly_output():
+    data = b"some bytes here" * 100
+    output = np.zeros(len(data), dtype=np.uint8)
+    output.setflags(write=False)
+    with pytest.raises(BufferError):
+        cramjam.gzip.decompress_into(cramjam.gzip.compress(data), output)
+    assert not output.any()
+
+    # nor are bytes, or views of readonly buffers
+    with pytest.raises(BufferError):
+        cramjam.gzip.decompress_into(cramjam.gzip.compress(data), bytes(len(data)))
+    with pytest.raises(BufferError):
+        cramjam.gzip.decompress_into(cramjam.gzip.compress(data), memoryview(output))
+    writable = bytearray(len(data))
+    assert cramjam.gzip.decompress_into(cramjam.gzip.compress(data), memoryview(writable)) == len(data)
+    assert writable == data
+
+
+def test_crc32_adler32(tmp_path):
+    data = os.urandom(100_000)
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    for cramjam_fn, zlib_fn in ((cramjam.crc32, zlib.crc32), (cramjam.adler32, zlib.adler32)):
+        assert cramjam_fn(data) == zlib_fn(data)
+        assert cramjam_fn(b"") == zlib_fn(b"")
+        assert cramjam_fn(cramjam.Buffer(data)) == zlib_fn(data)
+        assert cramjam_fn(cramjam.File(str(path))) == zlib_fn(data)
+
+        # piecewise accumulation gives the checksum of the whole
+        checksum = None
+        for start in range(0, len(data), 7_919):
+            chunk = data[start : start + 7_919]
+            checksum = cramjam_fn(chunk) if checksum is None else cramjam_fn(chunk, checksum)
+        assert checksum == zlib_fn(data)
+        assert cramjam_fn(data, 12345) == zlib_fn(data, 12345)
+
+    # the trailers of stdlib gzip/zlib payloads validate
+    assert cramjam.crc32(data) == int.from_bytes(gzip.compress(data)[-8:-4], "little")
+    assert cramjam.adler32(data) == int.from_bytes(zlib.compress(data)[-4:], "big")
+
+
+@pytest.mark.parametrize("variant_str", ("brotli", "bzip2", "deflate", "gzip", "lz4", "snappy", "zstd"))
+def test_variants_compressed_size(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"".join(b"line %d of some data\n" % i for i in range(10_000))
+
+    assert variant.compressed_size(data) == len(variant.compress(data))
+    path = tmp_path / "data"
+    path.write_bytes(data)
+    assert variant.compressed_size(cramjam.File(str(path))) == len(variant.compress(data))
+    assert variant.compressed_size(b"") == len(variant.compress(b""))
+    if variant_str != "snappy":
+        assert variant.compressed_size(data, level=1) == len(variant.compress(data, level=1))
+        assert variant.compressed_size(data, 1) == len(variant.compress(data, 1))
+
+
+@pytest.mark.parametrize("variant_str", ("brotli", "bzip2", "deflate", "gzip", "xz", "zlib", "zstd"))
+def test_variants_decompressor_streams_span_feeds(variant_str):
+    variant = getattr(cramjam, variant_str)
+    first = b"".join(b"line %d of the first stream\n" % i for i in range(20_000))
+    second = b"the second stream " * 1_000
+    stream = bytes(variant.compress(first)) + bytes(variant.compress(second))
+
+    decompressor = variant.Decompressor()
+    pieces = [bytes(decompressor.decompress(stream[i : i + 1000])) for i in range(0, len(stream), 1000)]
+    pieces.append(bytes(decompressor.finish()))
+    assert b"".join(pieces) == first + second
+    # each call returned only the output it produced, as it was produced
+    assert sum(1 for piece in pieces if piece) > 1
+
+    # a stream ending part way through is reported at finish
+    decompressor = variant.Decompressor()
+    decompressor.decompress(stream[: len(stream) // 3])
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+
+@pytest.mark.parametrize("block_size", (1000, 128 * 1024, None))
+def test_gzip_compress_parallel(block_size, tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(50_000))
+    serial = bytes(cramjam.gzip.compress(data, level=6))
+
+    compressed = bytes(cramjam.gzip.compress_parallel(data, block_size=block_size, level=6, n_workers=4))
+    assert bytes(cramjam.gzip.decompress(compressed)) == bytes(cramjam.gzip.decompress(serial)) == data
+    # a single member, readable by any gzip reader
+    assert gzip.decompress(compressed) == data
+    assert cramjam.gzip.member_index(compressed) == [(0, len(compressed), len(data))]
+
+    path = tmp_path / "data.txt"
+    path.write_bytes(data)
+    file = cramjam.File(str(path))
+    assert gzip.decompress(bytes(cramjam.gzip.compress_parallel(file, block_size=block_size))) == data
+
+    assert gzip.decompress(bytes(cramjam.gzip.compress_parallel(b""))) == b""
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress_parallel(data, block_size=0)
+    with pytest.raises(ValueError):
+        cramjam.gzip.compress_parallel(data, n_workers=0)
+
+
+def test_zstd_compress_file(tmp_path):
+    data = b"".join(b"line %d of some data\n" % i for i in range(100_000))
+    path = tmp_path / "data.txt"
+    path.write_bytes(data)
+
+    with open(path, "rb") as f:
+        compressed = cramjam.zstd.compress_file(f, level=3)
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+    assert bytes(cramjam.zstd.decompress(cramjam.zstd.compress_file(io.BytesIO(b"")))) == b""
+
+    class FailingReader:
+        def read(self, n):
+            raise OSError("connection reset")
+
+    with pytest.raises(cramjam.CompressionError, match="connection reset") as exc_info:
+        cramjam.zstd.compress_file(FailingReader())
+    assert isinstance(exc_info.value.__cause__, OSError)
+
+
+def test_zstd_error_code_dst_too_small():
+    data = b"some bytes here" * 1_000
+    compressed = cramjam.zstd.compress(data)
+
+    output = np.zeros(len(data) - 1, dtype=np.uint8)
+    with pytest.raises(cramjam.DecompressionError) as excinfo:
+        cramjam.zstd.decompress_into(compressed, output)
+    err = excinfo.value
+    assert err.codec == "zstd"
+    assert err.zstd_code == 70
+    assert err.zstd_name == "ZSTD_error_dstSize_tooSmall"
+
+    output = np.zeros(len(data), dtype=np.uint8)
+    assert cramjam.zstd.decompress_into(compressed, output) == len(data)
+    assert output.tobytes() == data
+
+    with pytest.raises(cramjam.DecompressionError) as excinfo:
+        cramjam.zstd.frame_compressed_size(b"not a zstd frame")
+    assert excinfo.value.zstd_name == "ZSTD_error_prefix_unknown"
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "brotli", "lz4", "gzip", "deflate", "zstd", "xz", "bzip2", "zlib"))
+def test_variants_into_output_too_small(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 1_000
+    compressed = bytes(variant.compress(data))
+
+    output = np.zeros(len(compressed) - 1, dtype=np.uint8)
+    expected = f"Output buffer of {len(compressed) - 1} bytes too small, needs at least {len(compressed)}"
+    with pytest.raises(cramjam.CompressionError, match=expected):
+        variant.compress_into(data, output)
+
+    if variant_str != "zstd":  # decompressed by libzstd in one go, see test_zstd_error_code_dst_too_small
+        output = np.zeros(100, dtype=np.uint8)
+        expected = f"Output buffer of 100 bytes too small, needs at least {len(data)}"
+        with pytest.raises(cramjam.DecompressionError, match=expected):
+            variant.decompress_into(compressed, output)
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "deflate", "zstd"))
+@pytest.mark.parametrize("n_bytes", (0, 1, 1_000, 100_000))
+def test_variants_compress_bound(variant_str, n_bytes):
+    variant = getattr(cramjam, variant_str)
+    data = os.urandom(n_bytes)  # incompressible, so output nears the bound
+    bound = variant.compress_bound(len(data))
+    assert bound > len(data)
+
+    output = bytearray(bound)
+    n_written = variant.compress_into(data, output)
+    assert n_written <= bound
+    assert bytes(variant.decompress(output[:n_written])) == data
+
+
+@pytest.mark.parametrize("variant_str,error", (("gzip", OverflowError), ("deflate", OverflowError), ("zstd", ValueError)))
+def test_variants_compress_bound_too_large(variant_str, error):
+    variant = getattr(cramjam, variant_str)
+    with pytest.raises(error):
+        variant.compress_bound(sys.maxsize * 2 + 1)  # usize::MAX
+
+
+@pytest.mark.parametrize(
+    "variant_str,min_level,max_level",
+    [
+        ("brotli", 0, 11),
+        ("bzip2", 1, 9),
+        ("deflate", 0, 9),
+        ("gzip", 0, 9),
+        ("lz4", 0, 12),
+        ("xz", 0, 9),
+        ("zlib", 0, 9),
+        ("zstd", None, 22),
+    ],
+)
+def test_variants_level_range(variant_str, min_level, max_level):
+    variant = getattr(cramjam, variant_str)
+    if min_level is not None:
+        assert variant.min_level() == min_level
+    assert variant.max_level() == max_level
+    data = b"some bytes here" * 100
+
+    for level in (variant.min_level(), variant.max_level()):
+        assert bytes(variant.decompress(variant.compress(data, level))) == data
+
+    expected = f"level {max_level + 1} out of range {variant.min_level()}..={max_level} for {variant_str}"
+    with pytest.raises(cramjam.CompressionError, match=expected):
+        variant.compress(data, max_level + 1)
+    with pytest.raises(cramjam.CompressionError, match=expected):
+        variant.compress_into(data, np.zeros(1024, dtype=np.uint8), max_level + 1)
+
+    # every other path taking a level checks it the same
+    if variant_str not in ("xz", "zlib"):
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            variant.compress_checked(data, max_level + 1)
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            variant.compressed_size(data, max_level + 1)
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            variant.compress_into_ring(data, cramjam.Buffer(b"\x00" * 1024), 0, max_level + 1)
+    if variant_str == "gzip":
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            variant.compress_parallel(data, level=max_level + 1)
+    if variant_str == "xz":
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            variant.Compressor(preset=max_level + 1)
+    for compress in (cramjam.compress, cramjam.compress_tagged, cramjam.compress_with_tag, cramjam.compress_envelope):
+        with pytest.raises(cramjam.CompressionError, match=expected):
+            compress(variant_str, data, level=max_level + 1)
+    with pytest.raises(cramjam.CompressionError, match=expected):
+        cramjam.Transcoder("gzip", variant_str, level=max_level + 1)
+
+
+def test_gzip_decompress_member(tmp_path):
+    members = [b"first member " * 100, b"second member " * 200, b"third member " * 300]
+    compressed = b"".join(bytes(cramjam.gzip.compress(member)) for member in members)
+    whole = bytes(cramjam.gzip.decompress(compressed))
+
+    start = len(members[0])
+    second = bytes(cramjam.gzip.decompress_member(compressed, 1))
+    assert second == whole[start : start + len(members[1])] == members[1]
+
+    index = cramjam.gzip.member_index(compressed)
+    for i, member in enumerate(members):
+        assert bytes(cramjam.gzip.decompress_member(compressed, i)) == member
+        assert bytes(cramjam.gzip.decompress_member(compressed, i, members=index)) == member
+
+    path = tmp_path / "members.gz"
+    path.write_bytes(compressed)
+    assert bytes(cramjam.gzip.decompress_member(cramjam.File(str(path)), 2, members=index)) == members[2]
+
+    with pytest.raises(IndexError):
+        cramjam.gzip.decompress_member(compressed, 3)
+    with pytest.raises(IndexError):
+        cramjam.gzip.decompress_member(compressed, 3, members=index)
+    with pytest.raises(IndexError):
+        cramjam.gzip.decompress_member(b"", 0)
+
+
+def test_zstd_long_distance_matching():
+    block = os.urandom(4 << 20)
+    data = block + block  # repeated further back than level 1's window reaches
+    plain = cramjam.zstd.compress(data, level=1)
+    ldm = cramjam.zstd.compress(data, level=1, enable_ldm=True, window_log=23)
+    assert len(ldm) < len(plain) * 0.6
+    assert bytes(cramjam.zstd.decompress(ldm)) == data
+
+    compressor = cramjam.zstd.Compressor(level=1, enable_ldm=True, window_log=23)
+    compressor.compress(data)
+    streamed = compressor.finish()
+    assert len(streamed) < len(plain) * 0.6
+    assert bytes(cramjam.zstd.decompress(streamed)) == data
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.Compressor(window_log=64)
+    with pytest.raises(cramjam.CompressionError):
+        cramjam.zstd.compress(data, window_log=64)
+
+
+def test_zstd_window_log_max():
+    data = b"".join(b"line %d of the log\n" % i for i in range(10_000))
+    compressed = cramjam.zstd.compress(data, window_log=28)
+
+    with pytest.raises(cramjam.DecompressionError, match="window_log_max") as excinfo:
+        cramjam.zstd.decompress(compressed)
+    assert excinfo.value.zstd_name == "ZSTD_error_frameParameter_windowTooLarge"
+    assert bytes(cramjam.zstd.decompress(compressed, window_log_max=28)) == data
+
+
+def test_zstd_decompress_file_range(tmp_path):
+    first, second = b"first block " * 1_000, b"second block " * 2_000
+    header = b"\x00" * 100
+    frames = [bytes(cramjam.zstd.compress(first)), bytes(cramjam.zstd.compress(second))]
+    path = tmp_path / "blocks.bin"
+    path.write_bytes(header + frames[0] + frames[1])
+
+    offsets = [len(header), len(header) + len(frames[0])]
+    assert bytes(cramjam.zstd.decompress_file_range(path, offsets[0], len(frames[0]))) == first
+    assert bytes(cramjam.zstd.decompress_file_range(str(path), offsets[1], len(frames[1]))) == second
+    assert bytes(cramjam.zstd.decompress_file_range(path, offsets[1], len(frames[1]), output_len=len(second))) == second
+
+    with pytest.raises(cramjam.DecompressionError, match="past the end"):
+        cramjam.zstd.decompress_file_range(path, offsets[1], len(frames[1]) + 1)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress_file_range(path, 0, len(header))
+    with pytest.raises(FileNotFoundError):
+        cramjam.zstd.decompress_file_range(tmp_path / "missing.bin", 0, 10)
+
+
+def test_zstd_compressor_flush_total_len():
+    compressor = cramjam.zstd.Compressor(level=3)
+    sent = []
+    for i in range(10):
+        compressor.compress(b"message %d, some payload " % i * 100)
+        buffer, total_len = compressor.flush(return_total_len=True)
+        sent.append(bytes(buffer))
+        assert total_len == sum(len(chunk) for chunk in sent)
+    sent.append(bytes(compressor.finish()))
+
+    stream = b"".join(sent)
+    assert len(stream) == total_len + len(sent[-1])
+    assert bytes(cramjam.zstd.decompress(stream)) == b"".join(b"message %d, some payload " % i * 100 for i in range(10))
+    assert isinstance(cramjam.zstd.Compressor().flush(), cramjam.Buffer)
+
+
+def test_zstd_compressor_reuse_entropy(tmp_path):
+    messages = [
+        json.dumps({"id": i, "kind": "sensor_reading", "unit": "celsius", "value": 20 + i % 5}).encode()
+        for i in range(50)
+    ]
+    plain = cramjam.zstd.Compressor(level=3)
+    frames = [bytes(plain.compress_frame(message)) for message in messages]
+    assert not plain.reuse_entropy
+    assert [bytes(cramjam.zstd.decompress(frame)) for frame in frames] == messages
+
+    path = tmp_path / "message"
+    path.write_bytes(messages[0])
+    assert bytes(plain.compress_frame(cramjam.File(str(path)))) == frames[0]
+
+    # the compressor's options apply to its frames too
+    checksummed = bytes(cramjam.zstd.Compressor(level=3, checksum=True).compress_frame(messages[0]))
+    assert len(checksummed) == len(frames[0]) + 4
+    assert bytes(cramjam.zstd.decompress(checksummed)) == messages[0]
+
+    chaining = cramjam.zstd.Compressor(level=3, reuse_entropy=True)
+    assert chaining.reuse_entropy
+    chained = [bytes(chaining.compress_frame(message)) for message in messages]
+    assert chained[0] == frames[0]
+    assert sum(len(frame) for frame in chained[1:]) < sum(len(frame) for frame in frames[1:])
+
+    # each frame decodes in order, given the previous frame's content
+    previous = None
+    for frame, message in zip(chained, messages):
+        decompressed = bytes(cramjam.zstd.decompress(frame, prefix=previous))
+        assert decompressed == message
+        previous = decompressed
+
+    # chain_prefix is an alias
+    aliased = cramjam.zstd.Compressor(level=3, chain_prefix=True)
+    assert aliased.reuse_entropy and aliased.chain_prefix
+    assert [bytes(aliased.compress_frame(message)) for message in messages] == chained
+
+    with pytest.raises(ValueError):
+        cramjam.zstd.Compressor(reuse_entropy=True, dictionary=b"some dictionary" * 10)
+
+
+def test_gzip_decompress_first_member_only():
+    first = gzip.compress(b"header member " * 100)
+    trailing = b"unrelated trailing data, not gzip"
+    data = first + trailing
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.decompress(data)
+    output, n_consumed = cramjam.gzip.decompress(data, first_member_only=True)
+    assert bytes(output) == b"header member " * 100
+    assert n_consumed == len(first)
+    assert data[n_consumed:] == trailing
+
+    # Only the first of several members
+    both = first + gzip.compress(b"second member")
+    output, n_consumed = cramjam.gzip.decompress(both, first_member_only=True)
+    assert bytes(output) == b"header member " * 100 and n_consumed == len(first)
+    assert bytes(cramjam.gzip.decompress(both)) == b"header member " * 100 + b"second member"
+
+
+def test_zstd_compress_time_limit():
+    data = b"".join(b"row %d, some column values\n" % i for i in range(1_000_000))
+    with pytest.raises(cramjam.CompressionError, match="time limit exceeded"):
+        cramjam.zstd.compress(data, level=19, time_limit_ms=1)
+
+    small = data[: 1 << 16]
+    compressed = cramjam.zstd.compress(small, level=3, time_limit_ms=60_000)
+    assert bytes(compressed) == bytes(cramjam.zstd.compress(small, level=3))
+    with pytest.raises(ValueError):
+        cramjam.zstd.compress(small, time_limit_ms=1_000, scratch=cramjam.Buffer())
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_decompress_records(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    records = [b"record %08d" % i + b"\x00" * 1 for i in range(1_000)]
+    assert all(len(record) == 16 for record in records)
+    compressed = variant.compress(b"".join(records))
+
+    split = variant.decompress_records(compressed, 16)
+    assert [bytes(record) for record in split] == records
+    assert len(variant.decompress_records(compressed, 16_000)) == 1
+    path = tmp_path / "blob"
+    path.write_bytes(bytes(compressed))
+    assert [bytes(record) for record in variant.decompress_records(cramjam.File(str(path)), 16)] == records
+
+    with pytest.raises(cramjam.DecompressionError, match="multiple of record_size"):
+        variant.decompress_records(compressed, 15)
+    with pytest.raises(ValueError):
+        variant.decompress_records(compressed, 0)
+
+
+EXTRAS = (
+    "compress_checked",
+    "decompress_checked",
+    "compress_reproducible",
+    "decompress_bench",
+    "decompress_strict",
+    "decompress_prefix",
+    "decompress_records",
+    "compress_into_ring",
+    "compressed_size",
+)
+
+
+@pytest.mark.parametrize(
+    "variant_str,missing",
+    (
+        ("brotli", {"decompress_strict"}),
+        ("bzip2", set()),
+        ("deflate", {"decompress_strict"}),
+        ("gzip", set()),
+        ("lz4", set()),
+        ("snappy", set()),
+        ("xz", {"compress_checked", "decompress_checked", "compress_into_ring", "compressed_size"}),
+        ("zlib", set(EXTRAS) - {"compress_reproducible"}),
+        ("zstd", set()),
+    ),
+)
+def test_variants_codec_extras(variant_str, missing):
+    variant = getattr(cramjam, variant_str)
+    assert {name for name in EXTRAS if not hasattr(variant, name)} == missing